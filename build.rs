@@ -73,6 +73,7 @@ error: the `wasm32-wasi` target is not installed
         "crates/trigger-http/tests/rust-http-test",
     );
     build_wasm_test_program("redis-rust.wasm", "crates/redis/tests/rust");
+    build_wasm_test_program("key-value-rust.wasm", "crates/trigger-key-value/tests/rust");
     build_wasm_test_program("wagi-test.wasm", "crates/trigger-http/tests/wagi-test");
 
     build_wasm_test_program(
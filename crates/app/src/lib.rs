@@ -21,7 +21,7 @@ use metadata::MetadataExt;
 
 pub use async_trait::async_trait;
 pub use host_component::DynamicHostComponent;
-pub use locked::Variable;
+pub use locked::{Variable, VariableType};
 pub use metadata::MetadataKey;
 
 /// MetadataKey for extracting the application name.
@@ -107,7 +107,7 @@ pub struct LockedTrigger {
 }
 
 /// A Variable specifies a custom configuration variable.
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct Variable {
     /// The variable's default value. If unset, the variable is required.
     #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -115,6 +115,40 @@ pub struct Variable {
     /// If set, the variable's value may be sensitive and e.g. shouldn't be logged.
     #[serde(default, skip_serializing_if = "std::ops::Not::not")]
     pub secret: bool,
+    /// The type of value the variable holds.
+    #[serde(
+        rename = "type",
+        default,
+        skip_serializing_if = "VariableType::is_string"
+    )]
+    pub value_type: VariableType,
+    /// If set, a regular expression which the variable's value must match.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    /// If set, the only values the variable may take.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_values: Option<Vec<String>>,
+}
+
+/// The type of value a Variable holds.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VariableType {
+    /// Any string.
+    #[default]
+    String,
+    /// A signed 64-bit integer.
+    Integer,
+    /// `true` or `false`.
+    Boolean,
+    /// An absolute URL.
+    Url,
+}
+
+impl VariableType {
+    fn is_string(&self) -> bool {
+        *self == Self::String
+    }
 }
 
 /// FixedVersion represents a schema version field with a const value.
@@ -0,0 +1,70 @@
+use regex::Regex;
+use spin_app::{Variable, VariableType};
+
+use crate::{Error, Result};
+
+/// The type and constraints a variable's value must satisfy.
+#[derive(Debug)]
+pub(crate) struct Constraints {
+    value_type: VariableType,
+    pattern: Option<Regex>,
+    allowed_values: Option<Vec<String>>,
+}
+
+impl Constraints {
+    /// Returns the constraints on the given variable, or `None` if it may
+    /// hold any string.
+    pub fn new(key: &str, var: &Variable) -> Result<Option<Self>> {
+        if var.value_type == VariableType::String
+            && var.pattern.is_none()
+            && var.allowed_values.is_none()
+        {
+            return Ok(None);
+        }
+        let pattern = var
+            .pattern
+            .as_deref()
+            .map(Regex::new)
+            .transpose()
+            .map_err(|err| {
+                Error::InvalidSchema(format!("variable {key:?} has an invalid pattern: {err}"))
+            })?;
+        Ok(Some(Self {
+            value_type: var.value_type,
+            pattern,
+            allowed_values: var.allowed_values.clone(),
+        }))
+    }
+
+    /// Checks a value against the constraints, returning why it is invalid
+    /// if it is.
+    pub fn check(&self, value: &str) -> std::result::Result<(), String> {
+        match self.value_type {
+            VariableType::String => {}
+            VariableType::Integer => {
+                value
+                    .parse::<i64>()
+                    .map_err(|_| "must be an integer".to_string())?;
+            }
+            VariableType::Boolean => {
+                if !matches!(value, "true" | "false") {
+                    return Err("must be `true` or `false`".into());
+                }
+            }
+            VariableType::Url => {
+                url::Url::parse(value).map_err(|err| format!("must be a URL ({err})"))?;
+            }
+        }
+        if let Some(pattern) = &self.pattern {
+            if !pattern.is_match(value) {
+                return Err(format!("must match the pattern {:?}", pattern.as_str()));
+            }
+        }
+        if let Some(allowed_values) = &self.allowed_values {
+            if !allowed_values.iter().any(|allowed| allowed == value) {
+                return Err(format!("must be one of {allowed_values:?}"));
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,4 +1,4 @@
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 
 use anyhow::Result;
 use once_cell::sync::OnceCell;
@@ -6,20 +6,26 @@ use spin_app::{AppComponent, DynamicHostComponent};
 use spin_core::{async_trait, HostComponent};
 use spin_world::config;
 
-use crate::{Error, Key, Provider, Resolver};
+use crate::{Error, Key, Provider, ProviderChain, Resolver};
 
 pub struct ConfigHostComponent {
-    providers: Mutex<Vec<Box<dyn Provider>>>,
+    providers: ProviderChain,
     resolver: Arc<OnceCell<Resolver>>,
 }
 
 impl ConfigHostComponent {
     pub fn new(providers: Vec<Box<dyn Provider>>) -> Self {
         Self {
-            providers: Mutex::new(providers),
+            providers: ProviderChain::new(providers),
             resolver: Default::default(),
         }
     }
+
+    /// Returns the Providers used to resolve variables. Replacing them takes
+    /// effect for later `get-config` calls.
+    pub fn providers(&self) -> ProviderChain {
+        self.providers.clone()
+    }
 }
 
 impl HostComponent for ConfigHostComponent {
@@ -55,9 +61,7 @@ impl DynamicHostComponent for ConfigHostComponent {
                     component.config().map(|(k, v)| (k.into(), v.into())),
                 )?;
             }
-            for provider in self.providers.lock().unwrap().drain(..) {
-                resolver.add_provider(provider);
-            }
+            resolver.set_providers(self.providers.clone());
             Ok::<_, anyhow::Error>(resolver)
         })?;
         data.component_id = Some(component.id().to_string());
@@ -1,22 +1,31 @@
+mod constraints;
 mod host_component;
 pub mod provider;
 mod template;
 
-use std::{borrow::Cow, collections::HashMap, fmt::Debug};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fmt::Debug,
+    sync::{Arc, RwLock},
+};
 
 use spin_app::Variable;
 
 pub use crate::{host_component::ConfigHostComponent, provider::Provider};
-use template::{Part, Template};
+use constraints::Constraints;
+use template::{Expr, Part, Template};
 
 /// A configuration resolver.
 #[derive(Debug, Default)]
 pub struct Resolver {
     // variable key -> variable
     variables: HashMap<String, Variable>,
+    // variable key -> constraints on its value, for variables which have any
+    constraints: HashMap<String, Constraints>,
     // component ID -> config key -> config value template
     component_configs: HashMap<String, HashMap<String, Template>>,
-    providers: Vec<Box<dyn Provider>>,
+    providers: ProviderChain,
 }
 
 impl Resolver {
@@ -25,8 +34,15 @@ impl Resolver {
         let variables: HashMap<_, _> = variables.into_iter().collect();
         // Validate keys so that we can rely on them during resolution
         variables.keys().try_for_each(|key| Key::validate(key))?;
+        let mut constraints = HashMap::new();
+        for (key, var) in &variables {
+            if let Some(var_constraints) = Constraints::new(key, var)? {
+                constraints.insert(key.clone(), var_constraints);
+            }
+        }
         Ok(Self {
             variables,
+            constraints,
             component_configs: Default::default(),
             providers: Default::default(),
         })
@@ -59,6 +75,26 @@ impl Resolver {
         self.providers.push(provider);
     }
 
+    /// Uses the given chain of Providers, replacing any added so far.
+    ///
+    /// Values are resolved from whichever Providers are in the chain at the
+    /// time, so replacing them takes effect for later lookups.
+    pub fn set_providers(&mut self, providers: ProviderChain) {
+        self.providers = providers;
+    }
+
+    /// Resolves each variable which has a type or constraints, checking that
+    /// its value is valid. This allows misconfiguration to be reported before
+    /// any component asks for a value.
+    pub async fn validate_variables(&self) -> Result<()> {
+        let mut keys: Vec<_> = self.constraints.keys().collect();
+        keys.sort();
+        for key in keys {
+            self.resolve_variable(key).await?;
+        }
+        Ok(())
+    }
+
     /// Resolves a config value for the given path.
     pub async fn resolve(&self, component_id: &str, key: Key<'_>) -> Result<String> {
         let configs = self.component_configs.get(component_id).ok_or_else(|| {
@@ -78,43 +114,138 @@ impl Resolver {
         for part in template.parts() {
             resolved_parts.push(match part {
                 Part::Lit(lit) => lit.as_ref().into(),
-                Part::Expr(var) => self.resolve_variable(var).await?.into(),
+                Part::Expr(expr) => self.resolve_expr(expr).await?.into(),
             });
         }
         Ok(resolved_parts.concat())
     }
 
+    async fn resolve_expr(&self, expr: &Expr) -> Result<String> {
+        let mut value = self.lookup_variable(&expr.var).await?;
+        for filter in &expr.filters {
+            value = filter.apply(value)?;
+        }
+        value.ok_or_else(|| required_variable_error(&expr.var))
+    }
+
     async fn resolve_variable(&self, key: &str) -> Result<String> {
+        self.lookup_variable(key)
+            .await?
+            .ok_or_else(|| required_variable_error(key))
+    }
+
+    // Returns the variable's value, or `None` if it is required and no
+    // provider has a value for it.
+    async fn lookup_variable(&self, key: &str) -> Result<Option<String>> {
         let var = self
             .variables
             .get(key)
             // This should have been caught by validate_template
             .ok_or_else(|| Error::InvalidKey(key.to_string()))?;
 
-        for provider in &self.providers {
-            if let Some(value) = provider.get(&Key(key)).await.map_err(Error::Provider)? {
-                return Ok(value);
+        let mut value = None;
+        for provider in self.providers.current().iter() {
+            value = provider.get(&Key(key)).await.map_err(Error::Provider)?;
+            if value.is_some() {
+                break;
             }
         }
 
-        var.default.clone().ok_or_else(|| {
-            Error::Provider(anyhow::anyhow!(
-                "no provider resolved required variable {key:?}"
-            ))
-        })
+        let Some(value) = value.or_else(|| var.default.clone()) else {
+            return Ok(None);
+        };
+
+        if let Some(constraints) = self.constraints.get(key) {
+            constraints.check(&value).map_err(|reason| {
+                // Don't reveal secret values in errors
+                if var.secret {
+                    Error::InvalidValue(format!("variable {key:?} {reason}"))
+                } else {
+                    Error::InvalidValue(format!("variable {key:?} {reason}, but is {value:?}"))
+                }
+            })?;
+        }
+
+        Ok(Some(value))
     }
 
     fn validate_template(&self, template: String) -> Result<Template> {
         let template = Template::new(template)?;
-        // Validate template variables are valid
+        // Validate template variables and filters are valid
         template.parts().try_for_each(|part| match part {
-            Part::Expr(var) if !self.variables.contains_key(var.as_ref()) => {
-                Err(Error::InvalidTemplate(format!("unknown variable {var:?}")))
+            Part::Expr(expr) if !self.variables.contains_key(expr.var.as_ref()) => Err(
+                Error::InvalidTemplate(format!("unknown variable {:?}", expr.var)),
+            ),
+            Part::Expr(expr) => {
+                expr.filters
+                    .iter()
+                    .try_for_each(|filter| filter.validate())?;
+                self.validate_filter_defaults(expr)
             }
-            _ => Ok(()),
+            Part::Lit(_) => Ok(()),
         })?;
         Ok(template)
     }
+
+    // Checks that `default` filters which stand in for the variable's own
+    // value (i.e. those before any other filter) satisfy its constraints.
+    fn validate_filter_defaults(&self, expr: &Expr) -> Result<()> {
+        let Some(constraints) = self.constraints.get(expr.var.as_ref()) else {
+            return Ok(());
+        };
+        expr.filters
+            .iter()
+            .take_while(|filter| filter.name.as_ref() == "default")
+            .filter_map(|filter| filter.arg.as_deref())
+            .try_for_each(|default| {
+                constraints.check(default).map_err(|reason| {
+                    Error::InvalidValue(format!(
+                        "default for variable {:?} {reason}, but is {default:?}",
+                        expr.var
+                    ))
+                })
+            })
+    }
+}
+
+fn required_variable_error(key: &str) -> Error {
+    Error::Provider(anyhow::anyhow!(
+        "no provider resolved required variable {key:?}"
+    ))
+}
+
+/// A shared list of config Providers, consulted in order.
+///
+/// Clones share the same list, so the Providers can be replaced (e.g. to
+/// pick up rotated secrets) while a [`Resolver`] is using them. Lookups
+/// already in progress finish with the Providers they started with.
+#[derive(Clone, Debug, Default)]
+pub struct ProviderChain(Arc<RwLock<Providers>>);
+
+type Providers = Arc<Vec<Arc<dyn Provider>>>;
+
+impl ProviderChain {
+    /// Creates a ProviderChain with the given Providers.
+    pub fn new(providers: impl IntoIterator<Item = Box<dyn Provider>>) -> Self {
+        let chain = Self::default();
+        chain.replace(providers);
+        chain
+    }
+
+    /// Replaces all Providers in the chain.
+    pub fn replace(&self, providers: impl IntoIterator<Item = Box<dyn Provider>>) {
+        let providers = providers.into_iter().map(Arc::from).collect();
+        *self.0.write().unwrap() = Arc::new(providers);
+    }
+
+    fn push(&self, provider: Box<dyn Provider>) {
+        let mut providers = self.0.write().unwrap();
+        Arc::make_mut(&mut providers).push(provider.into());
+    }
+
+    fn current(&self) -> Providers {
+        self.0.read().unwrap().clone()
+    }
 }
 
 /// A config key
@@ -189,11 +320,16 @@ pub enum Error {
     /// Unknown config path.
     #[error("unknown config path: {0}")]
     UnknownPath(String),
+
+    /// A variable's value does not have the declared type or constraints.
+    #[error("invalid variable value: {0}")]
+    InvalidValue(String),
 }
 
 #[cfg(test)]
 mod tests {
     use async_trait::async_trait;
+    use spin_app::VariableType;
 
     use super::*;
 
@@ -211,6 +347,16 @@ mod tests {
         }
     }
 
+    #[derive(Debug)]
+    struct ValueProvider(&'static str);
+
+    #[async_trait]
+    impl Provider for ValueProvider {
+        async fn get(&self, _key: &Key) -> anyhow::Result<Option<String>> {
+            Ok(Some(self.0.to_string()))
+        }
+    }
+
     async fn test_resolve(config_template: &str) -> Result<String> {
         let mut resolver = Resolver::new([
             (
@@ -218,6 +364,7 @@ mod tests {
                 Variable {
                     default: None,
                     secret: false,
+                    ..Default::default()
                 },
             ),
             (
@@ -225,16 +372,23 @@ mod tests {
                 Variable {
                     default: Some("default-value".into()),
                     secret: false,
+                    ..Default::default()
+                },
+            ),
+            (
+                "unset".into(),
+                Variable {
+                    default: None,
+                    secret: false,
+                    ..Default::default()
                 },
             ),
         ])
         .unwrap();
-        resolver
-            .add_component_config(
-                "test-component",
-                [("test_key".into(), config_template.into())],
-            )
-            .unwrap();
+        resolver.add_component_config(
+            "test-component",
+            [("test_key".into(), config_template.into())],
+        )?;
         resolver.add_provider(Box::new(TestProvider));
         resolver.resolve("test-component", Key("test_key")).await
     }
@@ -260,6 +414,216 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn resolve_variable_filters() {
+        assert_eq!(
+            test_resolve("{{ required | upper }}").await.unwrap(),
+            "PROVIDER-VALUE"
+        );
+        assert_eq!(
+            test_resolve(r#"{{ required | default: "fallback" }}"#)
+                .await
+                .unwrap(),
+            "provider-value"
+        );
+        assert_eq!(
+            test_resolve(r#"{{ unset | default: "Fall Back" | lower | url_encode }}"#)
+                .await
+                .unwrap(),
+            "fall+back"
+        );
+        assert_eq!(
+            test_resolve("{{ default | base64 }}").await.unwrap(),
+            "ZGVmYXVsdC12YWx1ZQ=="
+        );
+        test_resolve("{{ unset | upper }}").await.unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn resolve_invalid_filters() {
+        for template in [
+            "{{ required | reverse }}",
+            "{{ required | default }}",
+            r#"{{ required | upper: "x" }}"#,
+        ] {
+            let err = test_resolve(template).await.unwrap_err();
+            assert!(matches!(err, Error::InvalidTemplate(_)), "{err}");
+        }
+    }
+
+    #[tokio::test]
+    async fn resolve_with_replaced_providers() {
+        let mut resolver = Resolver::new([(
+            "required".into(),
+            Variable {
+                default: None,
+                secret: false,
+                ..Default::default()
+            },
+        )])
+        .unwrap();
+        resolver
+            .add_component_config(
+                "test-component",
+                [("test_key".into(), "{{ required }}".into())],
+            )
+            .unwrap();
+        let providers = ProviderChain::new([Box::new(ValueProvider("old")) as Box<dyn Provider>]);
+        resolver.set_providers(providers.clone());
+        assert_eq!(
+            resolver
+                .resolve("test-component", Key("test_key"))
+                .await
+                .unwrap(),
+            "old"
+        );
+
+        providers.replace([Box::new(ValueProvider("rotated")) as Box<dyn Provider>]);
+        assert_eq!(
+            resolver
+                .resolve("test-component", Key("test_key"))
+                .await
+                .unwrap(),
+            "rotated"
+        );
+    }
+
+    fn typed_resolver(variables: impl IntoIterator<Item = (&'static str, Variable)>) -> Resolver {
+        let mut resolver =
+            Resolver::new(variables.into_iter().map(|(key, var)| (key.into(), var))).unwrap();
+        resolver.add_provider(Box::new(TestProvider));
+        resolver
+    }
+
+    #[tokio::test]
+    async fn validate_typed_variables() {
+        let resolver = typed_resolver([
+            (
+                "port",
+                Variable {
+                    default: Some("8080".into()),
+                    value_type: VariableType::Integer,
+                    ..Default::default()
+                },
+            ),
+            (
+                "debug",
+                Variable {
+                    default: Some("false".into()),
+                    value_type: VariableType::Boolean,
+                    ..Default::default()
+                },
+            ),
+            (
+                "api_url",
+                Variable {
+                    default: Some("https://example.com/api".into()),
+                    value_type: VariableType::Url,
+                    ..Default::default()
+                },
+            ),
+            (
+                "region",
+                Variable {
+                    default: Some("eu".into()),
+                    pattern: Some("^[a-z]{2}$".into()),
+                    allowed_values: Some(vec!["eu".into(), "us".into()]),
+                    ..Default::default()
+                },
+            ),
+        ]);
+        resolver.validate_variables().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn validate_invalid_variables() {
+        for (value_type, default) in [
+            (VariableType::Integer, "8080.5"),
+            (VariableType::Boolean, "yes"),
+            (VariableType::Url, "example.com"),
+        ] {
+            let resolver = typed_resolver([(
+                "value",
+                Variable {
+                    default: Some(default.into()),
+                    value_type,
+                    ..Default::default()
+                },
+            )]);
+            let err = resolver.validate_variables().await.unwrap_err();
+            assert!(matches!(err, Error::InvalidValue(_)), "{err}");
+        }
+
+        let resolver = typed_resolver([(
+            "region",
+            Variable {
+                default: Some("mars".into()),
+                allowed_values: Some(vec!["eu".into(), "us".into()]),
+                ..Default::default()
+            },
+        )]);
+        let err = resolver.validate_variables().await.unwrap_err();
+        assert!(err.to_string().contains(r#"but is "mars""#), "{err}");
+
+        // Values from providers are checked too, but secret values aren't revealed.
+        let resolver = typed_resolver([(
+            "required",
+            Variable {
+                secret: true,
+                pattern: Some("^[0-9]+$".into()),
+                ..Default::default()
+            },
+        )]);
+        let err = resolver.validate_variables().await.unwrap_err();
+        assert!(matches!(err, Error::InvalidValue(_)), "{err}");
+        assert!(!err.to_string().contains("provider-value"), "{err}");
+    }
+
+    #[test]
+    fn invalid_pattern_is_rejected() {
+        let err = Resolver::new([(
+            "value".into(),
+            Variable {
+                pattern: Some("(".into()),
+                ..Default::default()
+            },
+        )])
+        .unwrap_err();
+        assert!(matches!(err, Error::InvalidSchema(_)), "{err}");
+    }
+
+    #[test]
+    fn invalid_filter_default_is_rejected() {
+        let mut resolver = typed_resolver([(
+            "region",
+            Variable {
+                default: Some("eu".into()),
+                allowed_values: Some(vec!["eu".into(), "us".into()]),
+                ..Default::default()
+            },
+        )]);
+        resolver
+            .add_component_config(
+                "test-component",
+                [
+                    ("good".into(), r#"{{ region | default: "us" }}"#.into()),
+                    // Defaults after other filters don't stand in for the variable
+                    (
+                        "mapped".into(),
+                        r#"{{ region | upper | default: "US" }}"#.into(),
+                    ),
+                ],
+            )
+            .unwrap();
+        let err = resolver
+            .add_component_config(
+                "test-component",
+                [("bad".into(), r#"{{ region | default: "mars" }}"#.into())],
+            )
+            .unwrap_err();
+        assert!(matches!(err, Error::InvalidValue(_)), "{err}");
+    }
+
     #[test]
     fn keys_good() {
         for key in ["a", "abc", "a1b2c3", "a_1", "a_1_b_3"] {
@@ -6,6 +6,8 @@ use crate::Key;
 
 /// Environment variable based provider.
 pub mod env;
+/// TOML or JSON file based provider.
+pub mod file;
 pub mod vault;
 
 /// A config provider.
@@ -0,0 +1,203 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::SystemTime,
+};
+
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use serde_json::Value;
+
+use crate::{Key, Provider};
+
+/// A config Provider that reads variables from a TOML or JSON file.
+///
+/// The file is checked for changes on each lookup and reloaded if it has
+/// been modified.
+#[derive(Debug)]
+pub struct FileProvider {
+    path: PathBuf,
+    flatten: bool,
+    loaded: Mutex<LoadedFile>,
+}
+
+#[derive(Debug)]
+struct LoadedFile {
+    version: FileVersion,
+    values: HashMap<String, String>,
+}
+
+// Identifies a version of the file, to tell when it has changed.
+#[derive(Debug, PartialEq)]
+struct FileVersion {
+    modified: SystemTime,
+    len: u64,
+}
+
+impl FileProvider {
+    /// Creates a new FileProvider, loading the file at `path`. The file's
+    /// format is determined by its extension, which must be `.toml` or
+    /// `.json`.
+    ///
+    /// If `flatten` is true, nested tables are flattened into variables
+    /// named by joining the keys with underscores, so `password` in a
+    /// `database` table is the variable `database_password`. Otherwise the
+    /// file may only contain top-level values.
+    pub fn new(path: impl Into<PathBuf>, flatten: bool) -> Result<Self> {
+        let path = path.into();
+        let version = file_version(&path)?;
+        let values = load(&path, flatten)?;
+        Ok(Self {
+            path,
+            flatten,
+            loaded: Mutex::new(LoadedFile { version, values }),
+        })
+    }
+
+    fn get_sync(&self, key: &Key) -> Result<Option<String>> {
+        let version = file_version(&self.path)?;
+        let mut loaded = self.loaded.lock().expect("loaded file lock poisoned");
+        if loaded.version != version {
+            *loaded = LoadedFile {
+                values: load(&self.path, self.flatten)?,
+                version,
+            };
+        }
+        Ok(loaded.values.get(key.as_ref()).cloned())
+    }
+}
+
+#[async_trait]
+impl Provider for FileProvider {
+    async fn get(&self, key: &Key) -> Result<Option<String>> {
+        tokio::task::block_in_place(|| self.get_sync(key))
+    }
+}
+
+fn file_version(path: &Path) -> Result<FileVersion> {
+    let metadata =
+        fs::metadata(path).with_context(|| format!("Failed to read variables file {path:?}"))?;
+    Ok(FileVersion {
+        modified: metadata.modified()?,
+        len: metadata.len(),
+    })
+}
+
+fn load(path: &Path, flatten: bool) -> Result<HashMap<String, String>> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read variables file {path:?}"))?;
+    let table = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("toml") => toml::from_str(&contents)
+            .with_context(|| format!("Failed to parse TOML variables file {path:?}"))?,
+        Some("json") => serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse JSON variables file {path:?}"))?,
+        _ => bail!("Variables file {path:?} must have a '.toml' or '.json' extension"),
+    };
+    let Value::Object(table) = table else {
+        bail!("Variables file {path:?} must contain a table of variables");
+    };
+    let mut values = HashMap::new();
+    add_values(&mut values, None, table, flatten)
+        .with_context(|| format!("Invalid variables file {path:?}"))?;
+    Ok(values)
+}
+
+fn add_values(
+    values: &mut HashMap<String, String>,
+    prefix: Option<&str>,
+    table: serde_json::Map<String, Value>,
+    flatten: bool,
+) -> Result<()> {
+    for (name, value) in table {
+        let key = match prefix {
+            Some(prefix) => format!("{prefix}_{name}"),
+            None => name,
+        };
+        let value = match value {
+            Value::String(value) => value,
+            Value::Number(value) => value.to_string(),
+            Value::Bool(value) => value.to_string(),
+            Value::Object(table) if flatten => {
+                add_values(values, Some(&key), table, flatten)?;
+                continue;
+            }
+            Value::Object(_) => {
+                bail!("'{key}' is a table; enable flattening to use nested keys as variables")
+            }
+            Value::Array(_) | Value::Null => {
+                bail!("'{key}' must be a string, number or boolean")
+            }
+        };
+        if values.insert(key.clone(), value).is_some() {
+            bail!("'{key}' is set more than once");
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn write_file(dir: &tempfile::TempDir, name: &str, contents: &str) -> PathBuf {
+        let path = dir.path().join(name);
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn get(provider: &FileProvider, key: &str) -> Option<String> {
+        provider.get_sync(&Key::new(key).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn provider_get_toml() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_file(
+            &dir,
+            "vars.toml",
+            "api_key = \"abc\"\nretries = 3\nenabled = true\n",
+        );
+        let provider = FileProvider::new(path, false).unwrap();
+        assert_eq!(get(&provider, "api_key"), Some("abc".to_string()));
+        assert_eq!(get(&provider, "retries"), Some("3".to_string()));
+        assert_eq!(get(&provider, "enabled"), Some("true".to_string()));
+        assert_eq!(get(&provider, "missing"), None);
+    }
+
+    #[test]
+    fn provider_get_json_flattened() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_file(
+            &dir,
+            "vars.json",
+            r#"{"database": {"password": "pw", "pool": {"size": 5}}, "region": "eu"}"#,
+        );
+        let provider = FileProvider::new(&path, true).unwrap();
+        assert_eq!(get(&provider, "database_password"), Some("pw".to_string()));
+        assert_eq!(get(&provider, "database_pool_size"), Some("5".to_string()));
+        assert_eq!(get(&provider, "region"), Some("eu".to_string()));
+
+        // Nested tables are only allowed when flattening.
+        FileProvider::new(&path, false).unwrap_err();
+    }
+
+    #[test]
+    fn provider_reloads_changed_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_file(&dir, "vars.json", r#"{"password": "old"}"#);
+        let provider = FileProvider::new(path, false).unwrap();
+        assert_eq!(get(&provider, "password"), Some("old".to_string()));
+
+        write_file(&dir, "vars.json", r#"{"password": "rotated"}"#);
+        assert_eq!(get(&provider, "password"), Some("rotated".to_string()));
+    }
+
+    #[test]
+    fn provider_rejects_unknown_format() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_file(&dir, "vars.yaml", "password: pw");
+        FileProvider::new(path, false).unwrap_err();
+    }
+}
@@ -1,39 +1,155 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
 use anyhow::{Context, Result};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use vaultrs::{
-    client::{VaultClient, VaultClientSettingsBuilder},
+    client::{Client, VaultClient, VaultClientSettingsBuilder},
     error::ClientError,
     kv2,
 };
 
 use crate::{Key, Provider};
 
+/// How a [`VaultProvider`] authenticates with Vault.
+#[derive(Clone)]
+pub enum VaultAuth {
+    /// A static token.
+    Token(String),
+    /// AppRole login. The token issued by logging in is replaced by logging
+    /// in again shortly before it expires.
+    AppRole {
+        /// The path at which the AppRole auth method is mounted, e.g. `approle`.
+        mount: String,
+        role_id: String,
+        secret_id: String,
+    },
+}
+
 /// A config Provider that uses HashiCorp Vault.
-#[derive(Debug)]
 pub struct VaultProvider {
     url: String,
-    token: String,
+    auth: VaultAuth,
     mount: String,
     prefix: Option<String>,
+    cache_ttl: Option<Duration>,
+    // The client is created on first use and shared by later lookups.
+    session: tokio::sync::Mutex<Option<Session>>,
+    // Resolved values by secret path, along with when they expire.
+    cache: Mutex<HashMap<String, (Option<String>, Instant)>>,
+}
+
+struct Session {
+    client: Arc<VaultClient>,
+    expires: Option<Instant>,
 }
 
 impl VaultProvider {
     pub fn new(
         url: impl Into<String>,
-        token: impl Into<String>,
+        auth: VaultAuth,
         mount: impl Into<String>,
         prefix: Option<impl Into<String>>,
     ) -> Self {
         Self {
             url: url.into(),
-            token: token.into(),
+            auth,
             mount: mount.into(),
             prefix: prefix.map(Into::into),
+            cache_ttl: None,
+            session: Default::default(),
+            cache: Default::default(),
+        }
+    }
+
+    /// Caches resolved values for the given time rather than reading them
+    /// from Vault on every lookup.
+    pub fn with_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.cache_ttl = Some(ttl);
+        self
+    }
+
+    async fn client(&self) -> Result<Arc<VaultClient>> {
+        let mut session = self.session.lock().await;
+        if let Some(session) = session.as_ref() {
+            if session
+                .expires
+                .map_or(true, |expires| Instant::now() < expires)
+            {
+                return Ok(session.client.clone());
+            }
+        }
+        let new_session = self.login().await?;
+        let client = new_session.client.clone();
+        *session = Some(new_session);
+        Ok(client)
+    }
+
+    async fn login(&self) -> Result<Session> {
+        let mut settings = VaultClientSettingsBuilder::default();
+        settings.address(&self.url);
+        match &self.auth {
+            VaultAuth::Token(token) => Ok(Session {
+                client: Arc::new(VaultClient::new(settings.token(token).build()?)?),
+                expires: None,
+            }),
+            VaultAuth::AppRole {
+                mount,
+                role_id,
+                secret_id,
+            } => {
+                let mut client = VaultClient::new(settings.build()?)?;
+                let auth = vaultrs::auth::approle::login(&client, mount, role_id, secret_id)
+                    .await
+                    .context("Failed to log in to Vault using AppRole")?;
+                client.set_token(&auth.client_token);
+                // A lease duration of 0 means the token never expires.
+                let expires = (auth.lease_duration > 0).then(|| {
+                    // Leave some leeway so the token doesn't expire mid-lookup.
+                    Instant::now() + Duration::from_secs(auth.lease_duration).mul_f64(0.9)
+                });
+                Ok(Session {
+                    client: Arc::new(client),
+                    expires,
+                })
+            }
+        }
+    }
+
+    fn cached(&self, path: &str) -> Option<Option<String>> {
+        let cache = self.cache.lock().unwrap();
+        match cache.get(path) {
+            Some((value, expires)) if Instant::now() < *expires => Some(value.clone()),
+            _ => None,
+        }
+    }
+
+    fn cache(&self, path: String, value: Option<String>) {
+        if let Some(ttl) = self.cache_ttl {
+            let mut cache = self.cache.lock().unwrap();
+            let now = Instant::now();
+            cache.retain(|_, (_, expires)| now < *expires);
+            cache.insert(path, (value, now + ttl));
         }
     }
 }
 
+impl std::fmt::Debug for VaultProvider {
+    // Leaves out the credentials.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VaultProvider")
+            .field("url", &self.url)
+            .field("mount", &self.mount)
+            .field("prefix", &self.prefix)
+            .field("cache_ttl", &self.cache_ttl)
+            .finish_non_exhaustive()
+    }
+}
+
 #[derive(Deserialize, Serialize)]
 struct Secret {
     value: String,
@@ -42,22 +158,170 @@ struct Secret {
 #[async_trait]
 impl Provider for VaultProvider {
     async fn get(&self, key: &Key) -> Result<Option<String>> {
-        let client = VaultClient::new(
-            VaultClientSettingsBuilder::default()
-                .address(&self.url)
-                .token(&self.token)
-                .build()?,
-        )?;
         let path = match &self.prefix {
             Some(prefix) => format!("{}/{}", prefix, key.0),
             None => key.0.to_string(),
         };
-        match kv2::read::<Secret>(&client, &self.mount, &path).await {
-            Ok(secret) => Ok(Some(secret.value)),
+        if let Some(value) = self.cached(&path) {
+            return Ok(value);
+        }
+        let client = self.client().await?;
+        let value = match kv2::read::<Secret>(client.as_ref(), &self.mount, &path).await {
+            Ok(secret) => Some(secret.value),
             // Vault doesn't have this entry so pass along the chain
-            Err(ClientError::APIError { code: 404, .. }) => Ok(None),
-            // Other Vault error so bail rather than looking elsewhere
-            Err(e) => Err(e).context("Failed to check Vault for config"),
+            Err(ClientError::APIError { code: 404, .. }) => None,
+            Err(e) => {
+                if let ClientError::APIError { code: 403, .. } = e {
+                    // The token may have been revoked, so log in again next time.
+                    *self.session.lock().await = None;
+                }
+                // Other Vault error so bail rather than looking elsewhere
+                return Err(e).context("Failed to check Vault for config");
+            }
+        };
+        self.cache(path, value.clone());
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use wiremock::{
+        matchers::{any, header, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+
+    const LOGIN_RESPONSE: &str = r#"{
+        "request_id": "1", "lease_id": "", "renewable": false, "lease_duration": 0,
+        "data": null, "wrap_info": null, "warnings": null,
+        "auth": {
+            "client_token": "approle-token", "accessor": "accessor",
+            "policies": ["default"], "token_policies": ["default"],
+            "metadata": {"role_name": "spin"}, "lease_duration": 1200,
+            "renewable": true, "entity_id": "entity", "token_type": "service",
+            "orphan": true
+        }
+    }"#;
+
+    const SECRET_RESPONSE: &str = r#"{
+        "request_id": "2", "lease_id": "", "renewable": false, "lease_duration": 0,
+        "data": {
+            "data": {"value": "s3cr3t"},
+            "metadata": {
+                "created_time": "2023-09-01T00:00:00.000000000Z",
+                "custom_metadata": null, "deletion_time": "",
+                "destroyed": false, "version": 1
+            }
+        },
+        "wrap_info": null, "warnings": null, "auth": null
+    }"#;
+
+    // Stands in for a Vault dev server with an AppRole login, a `secret`
+    // kv-v2 mount containing `password` which can be read with `token`, and
+    // nothing else.
+    async fn serve_vault(token: &str) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/auth/approle/login"))
+            .respond_with(json_response(200, LOGIN_RESPONSE))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/secret/data/password"))
+            .and(header("x-vault-token", token))
+            .respond_with(json_response(200, SECRET_RESPONSE))
+            .mount(&server)
+            .await;
+        Mock::given(any())
+            .respond_with(json_response(404, r#"{"errors": []}"#))
+            .with_priority(u8::MAX)
+            .mount(&server)
+            .await;
+        server
+    }
+
+    fn json_response(status: u16, body: &str) -> ResponseTemplate {
+        ResponseTemplate::new(status).set_body_raw(body, "application/json")
+    }
+
+    async fn request_paths(server: &MockServer) -> Vec<String> {
+        let requests = server.received_requests().await.unwrap();
+        requests.iter().map(|r| r.url.path().to_owned()).collect()
+    }
+
+    fn key(name: &str) -> Key {
+        Key::new(name).unwrap()
+    }
+
+    #[tokio::test]
+    async fn token_provider_gets_secrets() -> Result<()> {
+        let server = serve_vault("root").await;
+        let provider = VaultProvider::new(
+            server.uri(),
+            VaultAuth::Token("root".into()),
+            "secret",
+            None::<String>,
+        );
+
+        assert_eq!(Some("s3cr3t".into()), provider.get(&key("password")).await?);
+        assert_eq!(None, provider.get(&key("missing")).await?);
+
+        assert_eq!(
+            vec!["/v1/secret/data/password", "/v1/secret/data/missing"],
+            request_paths(&server).await
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn approle_login_is_reused() -> Result<()> {
+        let server = serve_vault("approle-token").await;
+        let provider = VaultProvider::new(
+            server.uri(),
+            VaultAuth::AppRole {
+                mount: "approle".into(),
+                role_id: "role".into(),
+                secret_id: "secret".into(),
+            },
+            "secret",
+            None::<String>,
+        );
+
+        for _ in 0..3 {
+            assert_eq!(Some("s3cr3t".into()), provider.get(&key("password")).await?);
         }
+
+        assert_eq!(
+            vec![
+                "/v1/auth/approle/login",
+                "/v1/secret/data/password",
+                "/v1/secret/data/password",
+                "/v1/secret/data/password",
+            ],
+            request_paths(&server).await
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn resolved_values_are_cached() -> Result<()> {
+        let server = serve_vault("root").await;
+        let provider = VaultProvider::new(
+            server.uri(),
+            VaultAuth::Token("root".into()),
+            "secret",
+            None::<String>,
+        )
+        .with_cache_ttl(Duration::from_secs(60));
+
+        for _ in 0..3 {
+            assert_eq!(Some("s3cr3t".into()), provider.get(&key("password")).await?);
+            assert_eq!(None, provider.get(&key("missing")).await?);
+        }
+
+        assert_eq!(2, request_paths(&server).await.len());
+        Ok(())
     }
 }
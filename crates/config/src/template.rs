@@ -4,6 +4,9 @@ use crate::{Error, Result};
 
 /// Template represents a simple string template that allows expressions in
 /// double curly braces, similar to Mustache or Liquid.
+///
+/// An expression names a variable, optionally followed by filters separated
+/// by `|`, e.g. `{{ api_url | default: "http://localhost" | lower }}`.
 #[derive(Clone, Debug, PartialEq)]
 pub(crate) struct Template(Vec<Part>);
 
@@ -14,9 +17,10 @@ impl Template {
         while !remainder.is_empty() {
             let (part, rest) = if let Some(expr_rest) = remainder.strip_prefix("{{") {
                 // Expression should be next
-                if let Some((expr, rest)) = expr_rest.split_once("}}") {
+                if let Some(idx) = find_unquoted(expr_rest, "}}") {
                     // Take up through the next '}}'...
-                    (Part::expr(expr.trim()), rest)
+                    let (expr, rest) = expr_rest.split_at(idx);
+                    (Part::Expr(Expr::parse(expr)?), &rest[2..])
                 } else {
                     // ...or we have unmatched braces
                     return Err(Error::InvalidTemplate(
@@ -57,7 +61,7 @@ impl Display for Template {
 #[derive(Clone, Debug, PartialEq)]
 pub(crate) enum Part {
     Lit(Box<str>),
-    Expr(Box<str>),
+    Expr(Expr),
 }
 
 impl Part {
@@ -65,11 +69,182 @@ impl Part {
         Self::Lit(lit.into())
     }
 
-    pub fn expr(expr: impl Into<Box<str>>) -> Self {
-        Self::Expr(expr.into())
+    #[cfg(test)]
+    pub fn expr(var: impl Into<Box<str>>) -> Self {
+        Self::Expr(Expr {
+            var: var.into(),
+            filters: vec![],
+        })
+    }
+}
+
+/// A template expression: a variable and the filters to apply to its value.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Expr {
+    pub var: Box<str>,
+    pub filters: Vec<Filter>,
+}
+
+impl Expr {
+    fn parse(expr: &str) -> Result<Self> {
+        let mut segments = split_unquoted(expr, "|").into_iter();
+        // split_unquoted always returns at least one segment
+        let var = segments.next().unwrap_or_default().trim().into();
+        let filters = segments.map(Filter::parse).collect::<Result<_>>()?;
+        Ok(Self { var, filters })
     }
 }
 
+impl Display for Expr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.var)?;
+        self.filters
+            .iter()
+            .try_for_each(|filter| write!(f, " | {}", filter))
+    }
+}
+
+/// A filter applied to a value in a template expression, e.g. `upper` or
+/// `default: "value"`. Filter names are checked by `Resolver::validate_template`.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Filter {
+    pub name: Box<str>,
+    pub arg: Option<Box<str>>,
+}
+
+impl Filter {
+    fn parse(filter: &str) -> Result<Self> {
+        let (name, arg) = match find_unquoted(filter, ":") {
+            Some(idx) => {
+                let (name, arg) = filter.split_at(idx);
+                (name, Some(unquote(arg[1..].trim())?))
+            }
+            None => (filter, None),
+        };
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(Error::InvalidTemplate(format!(
+                "missing filter name in {:?}",
+                filter.trim()
+            )));
+        }
+        Ok(Self {
+            name: name.into(),
+            arg,
+        })
+    }
+
+    /// Checks that this is a known filter with the arguments it expects.
+    pub fn validate(&self) -> Result<()> {
+        let takes_arg = match self.name.as_ref() {
+            "default" => true,
+            "lower" | "upper" | "base64" | "url_encode" => false,
+            name => {
+                return Err(Error::InvalidTemplate(format!("unknown filter {name:?}")));
+            }
+        };
+        match (takes_arg, &self.arg) {
+            (true, None) => Err(Error::InvalidTemplate(format!(
+                "filter {:?} requires a quoted string argument",
+                self.name
+            ))),
+            (false, Some(_)) => Err(Error::InvalidTemplate(format!(
+                "filter {:?} does not take an argument",
+                self.name
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Applies this filter to a value, which is `None` if the variable was
+    /// not resolved. `default` replaces a missing or empty value; the other
+    /// filters leave a missing value missing.
+    pub fn apply(&self, value: Option<String>) -> Result<Option<String>> {
+        if self.name.as_ref() == "default" {
+            return Ok(match value {
+                Some(value) if !value.is_empty() => Some(value),
+                _ => self.arg.as_deref().map(Into::into),
+            });
+        }
+        let Some(value) = value else {
+            return Ok(None);
+        };
+        Ok(Some(match self.name.as_ref() {
+            "lower" => value.to_lowercase(),
+            "upper" => value.to_uppercase(),
+            "base64" => base64::Engine::encode(&base64::engine::general_purpose::STANDARD, value),
+            "url_encode" => url::form_urlencoded::byte_serialize(value.as_bytes()).collect(),
+            // This should have been caught by validate_template
+            name => {
+                return Err(Error::InvalidTemplate(format!("unknown filter {name:?}")));
+            }
+        }))
+    }
+}
+
+impl Display for Filter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.name)?;
+        if let Some(arg) = &self.arg {
+            write!(f, ": {:?}", arg)?;
+        }
+        Ok(())
+    }
+}
+
+// Returns the byte index of the first occurrence of `pat` which is not
+// inside a quoted string.
+fn find_unquoted(s: &str, pat: &str) -> Option<usize> {
+    let mut quote = None;
+    let mut escaped = false;
+    for (idx, c) in s.char_indices() {
+        match quote {
+            Some(_) if escaped => escaped = false,
+            Some(_) if c == '\\' => escaped = true,
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if s[idx..].starts_with(pat) => return Some(idx),
+            None => {}
+        }
+    }
+    None
+}
+
+// Splits a string on each `sep` which is not inside a quoted string.
+fn split_unquoted<'a>(mut s: &'a str, sep: &str) -> Vec<&'a str> {
+    let mut segments = vec![];
+    while let Some(idx) = find_unquoted(s, sep) {
+        segments.push(&s[..idx]);
+        s = &s[idx + sep.len()..];
+    }
+    segments.push(s);
+    segments
+}
+
+// Parses a single- or double-quoted string, in which a backslash escapes the
+// following character.
+fn unquote(s: &str) -> Result<Box<str>> {
+    let invalid = || Error::InvalidTemplate(format!("expected a quoted string but got {s:?}"));
+    let mut chars = s.chars();
+    let quote = chars.next().filter(|c| *c == '"' || *c == '\'');
+    let quote = quote.ok_or_else(invalid)?;
+    let mut unquoted = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unquoted.push(chars.next().ok_or_else(invalid)?),
+            c if c == quote => {
+                return match chars.next() {
+                    None => Ok(unquoted.into()),
+                    Some(_) => Err(invalid()),
+                };
+            }
+            c => unquoted.push(c),
+        }
+    }
+    Err(invalid())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -103,4 +278,80 @@ mod tests {
     fn template_parts_bad() {
         Template::new("{{ matched }} {{ unmatched").unwrap_err();
     }
+
+    #[test]
+    fn template_filters() {
+        let template =
+            Template::new(r#"{{ url | default: "http://a|b/{{x}}" | lower }}/{{ name|upper }}"#)
+                .unwrap();
+        let expected = vec![
+            Part::Expr(Expr {
+                var: "url".into(),
+                filters: vec![
+                    Filter {
+                        name: "default".into(),
+                        arg: Some("http://a|b/{{x}}".into()),
+                    },
+                    Filter {
+                        name: "lower".into(),
+                        arg: None,
+                    },
+                ],
+            }),
+            Part::lit("/"),
+            Part::Expr(Expr {
+                var: "name".into(),
+                filters: vec![Filter {
+                    name: "upper".into(),
+                    arg: None,
+                }],
+            }),
+        ];
+        assert!(template.parts().eq(&expected), "{template:?}");
+
+        let template = Template::new(r#"{{ x | default: 'it\'s' }}"#).unwrap();
+        let Some(Part::Expr(expr)) = template.parts().next() else {
+            panic!("expected an expression: {template:?}");
+        };
+        assert_eq!(expr.filters[0].arg.as_deref(), Some("it's"));
+    }
+
+    #[test]
+    fn template_filters_bad() {
+        for tmpl in [
+            "{{ x | }}",
+            "{{ x | default: unquoted }}",
+            r#"{{ x | default: "unterminated }}"#,
+            r#"{{ x | default: "a" "b" }}"#,
+        ] {
+            Template::new(tmpl).expect_err(tmpl);
+        }
+    }
+
+    #[test]
+    fn filters_apply() {
+        let apply = |filter: &str, value: Option<&str>| {
+            let filter = Filter::parse(filter).unwrap();
+            filter.validate().unwrap();
+            filter.apply(value.map(Into::into)).unwrap()
+        };
+        assert_eq!(apply("lower", Some("MiXeD")).as_deref(), Some("mixed"));
+        assert_eq!(apply("upper", Some("MiXeD")).as_deref(), Some("MIXED"));
+        assert_eq!(apply("base64", Some("spin")).as_deref(), Some("c3Bpbg=="));
+        assert_eq!(
+            apply("url_encode", Some("a b&c=d/é")).as_deref(),
+            Some("a+b%26c%3Dd%2F%C3%A9")
+        );
+        assert_eq!(apply("upper", None), None);
+        assert_eq!(apply(r#"default: "x""#, None).as_deref(), Some("x"));
+        assert_eq!(apply(r#"default: "x""#, Some("")).as_deref(), Some("x"));
+        assert_eq!(apply(r#"default: "x""#, Some("y")).as_deref(), Some("y"));
+    }
+
+    #[test]
+    fn filters_validate() {
+        for filter in ["unknown", "default", r#"upper: "x""#] {
+            Filter::parse(filter).unwrap().validate().expect_err(filter);
+        }
+    }
 }
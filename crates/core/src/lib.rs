@@ -12,6 +12,7 @@ mod io;
 mod limits;
 mod preview1;
 mod store;
+pub mod table;
 
 use std::{sync::Arc, time::Duration};
 
@@ -1,5 +1,4 @@
-// TODO: there's nothing key-value-specific about this utility, so it could be moved elsewhere, e.g. to a utility
-// crate of some kind.
+//! A table of host resources which are referred to by guests by handle.
 
 use std::collections::HashMap;
 
@@ -56,6 +55,11 @@ impl<V> Table<V> {
         self.tuples.get(&key)
     }
 
+    /// Get a mutable reference to the resource identified by the specified `key`, if it exists.
+    pub fn get_mut(&mut self, key: u32) -> Option<&mut V> {
+        self.tuples.get_mut(&key)
+    }
+
     /// Remove the resource identified by the specified `key`, if present.
     ///
     /// This makes the key eligible for eventual reuse (i.e. for a newly-pushed resource).
@@ -1,14 +1,19 @@
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 use anyhow::Result;
+use azure_core::{prelude::IfMatchCondition, StatusCode};
 use azure_data_cosmos::{
-    prelude::{AuthorizationToken, CollectionClient, CosmosClient, Query},
+    prelude::{AuthorizationToken, CollectionClient, CosmosClient, Param, Query},
     CosmosEntity,
 };
-use futures::StreamExt;
+use futures::{future, StreamExt};
 use serde::{Deserialize, Serialize};
 use spin_core::async_trait;
-use spin_key_value::{log_error, Error, Store, StoreManager};
+use spin_key_value::{add_to_counter, log_error, Error, KeyPage, Store, StoreManager};
 
 pub struct KeyValueAzureCosmos {
     client: CollectionClient,
@@ -55,6 +60,8 @@ impl Store for AzureCosmosStore {
         let pair = Pair {
             id: key.to_string(),
             value: value.to_vec(),
+            ttl: None,
+            ts: None,
         };
         self.client
             .create_document(pair)
@@ -80,13 +87,167 @@ impl Store for AzureCosmosStore {
         }
     }
 
-    async fn get_keys(&self) -> Result<Vec<String>, Error> {
-        self.get_keys().await
+    async fn get_many(&self, keys: &[String]) -> Result<Vec<Option<Vec<u8>>>, Error> {
+        let query = Query::with_params(
+            "SELECT * FROM c WHERE ARRAY_CONTAINS(@keys, c.id)".to_string(),
+            vec![Param::new("@keys".to_string(), keys.to_vec())],
+        );
+        let query = self
+            .client
+            .query_documents(query)
+            .query_cross_partition(true);
+
+        let mut values = HashMap::new();
+        let mut stream = query.into_stream::<Pair>();
+        while let Some(resp) = stream.next().await {
+            let resp = resp.map_err(log_error)?;
+            for (pair, _) in resp.results {
+                if !pair.is_expired() {
+                    values.insert(pair.id, pair.value);
+                }
+            }
+        }
+
+        Ok(keys.iter().map(|key| values.get(key).cloned()).collect())
+    }
+
+    async fn set_many(&self, key_values: &[(String, Vec<u8>)]) -> Result<(), Error> {
+        // Cosmos transactional batches are limited to a single partition, and each key is its own partition, so
+        // we issue the upserts concurrently instead.  As the `Store` trait allows, this isn't atomic.
+        future::try_join_all(key_values.iter().map(|(key, value)| self.set(key, value))).await?;
+        Ok(())
+    }
+
+    async fn delete_many(&self, keys: &[String]) -> Result<(), Error> {
+        future::try_join_all(keys.iter().map(|key| async move {
+            let document_client = self.client.document_client(key, key).map_err(log_error)?;
+            match document_client.delete_document().await {
+                Ok(_) => Ok(()),
+                Err(e) if is_status(&e, StatusCode::NotFound) => Ok(()),
+                Err(e) => Err(log_error(e)),
+            }
+        }))
+        .await?;
+        Ok(())
+    }
+
+    async fn list_keys(
+        &self,
+        prefix: &str,
+        cursor: Option<&str>,
+        limit: u32,
+    ) -> Result<KeyPage, Error> {
+        // Keys are listed in order, and the cursor is simply the last key returned, as for the other stores.  This
+        // version of the Cosmos SDK can't resume a query from a continuation token, so we read every matching key
+        // and sort them here.
+        let query = Query::with_params(
+            format!("SELECT c.id FROM c WHERE STARTSWITH(c.id, @prefix) AND {UNEXPIRED}"),
+            vec![
+                Param::new("@prefix".to_string(), prefix),
+                Param::new("@now".to_string(), now()),
+            ],
+        );
+        let mut stream = self
+            .client
+            .query_documents(query)
+            .query_cross_partition(true)
+            .into_stream::<Key>();
+        let mut keys = Vec::new();
+        while let Some(resp) = stream.next().await {
+            let resp = resp.map_err(log_error)?;
+            keys.extend(
+                resp.results
+                    .into_iter()
+                    .map(|(key, _)| key.id)
+                    .filter(|key| cursor.map_or(true, |cursor| key.as_str() > cursor)),
+            );
+        }
+        keys.sort();
+
+        let cursor = if keys.len() > limit as usize {
+            keys.truncate(limit as usize);
+            keys.last().cloned()
+        } else {
+            None
+        };
+
+        Ok(KeyPage { keys, cursor })
+    }
+
+    async fn increment(&self, key: &str, delta: i64) -> Result<i64, Error> {
+        // Cosmos has no native counters, so we read the current value and write the new one conditionally on the
+        // document's etag, retrying until no one else has modified it in between.
+        loop {
+            let current = self.get_pair_and_etag(key).await?;
+            let live = current.as_ref().map(|(p, _)| p).filter(|p| !p.is_expired());
+            let value = add_to_counter(live.map(|p| p.value.as_slice()), delta)?;
+            let pair = Pair {
+                id: key.to_string(),
+                value: value.to_string().into_bytes(),
+                ttl: live.and_then(Pair::remaining_ttl),
+                ts: None,
+            };
+            if self
+                .conditional_write(pair, current.map(|(_, etag)| etag))
+                .await?
+            {
+                return Ok(value);
+            }
+        }
+    }
+
+    async fn compare_and_swap(
+        &self,
+        key: &str,
+        expected: Option<&[u8]>,
+        value: &[u8],
+    ) -> Result<bool, Error> {
+        let current = self.get_pair_and_etag(key).await?;
+        let live = current.as_ref().map(|(p, _)| p).filter(|p| !p.is_expired());
+        if live.map(|p| p.value.as_slice()) != expected {
+            return Ok(false);
+        }
+        let pair = Pair {
+            id: key.to_string(),
+            value: value.to_vec(),
+            ttl: live.and_then(Pair::remaining_ttl),
+            ts: None,
+        };
+        self.conditional_write(pair, current.map(|(_, etag)| etag))
+            .await
+    }
+
+    async fn set_with_ttl(&self, key: &str, value: &[u8], ttl: Duration) -> Result<(), Error> {
+        // Cosmos expires items natively, provided time-to-live is enabled on the container (with a default of -1,
+        // i.e. "never expire", so that items without a `ttl` live forever).  If it isn't, Cosmos keeps the item, so
+        // reads also hide items which have outlived their `ttl`.  Cosmos TTLs have a granularity of one second.
+        let pair = Pair {
+            id: key.to_string(),
+            value: value.to_vec(),
+            ttl: Some(ttl.as_secs().clamp(1, i32::MAX as u64) as i32),
+            ts: None,
+        };
+        self.client
+            .create_document(pair)
+            .is_upsert(true)
+            .await
+            .map_err(log_error)?;
+        Ok(())
     }
 }
 
 impl AzureCosmosStore {
     async fn get_pair(&self, key: &str) -> Result<Pair, Error> {
+        self.get_pair_and_etag(key)
+            .await?
+            .map(|(p, _)| p)
+            .filter(|p| !p.is_expired())
+            .ok_or(Error::NoSuchKey)
+    }
+
+    /// Get the pair for `key`, if any, along with the etag of the document holding it.  The pair may have
+    /// expired, in which case the document is still needed to overwrite it.
+    async fn get_pair_and_etag(&self, key: &str) -> Result<Option<(Pair, String)>, Error> {
         let query = self
             .client
             .query_documents(Query::new(format!("SELECT * FROM c WHERE c.id='{}'", key)))
@@ -100,30 +261,48 @@ impl AzureCosmosStore {
             Some(r) => {
                 let r = r.map_err(log_error)?;
                 match r.results.first().cloned() {
-                    Some((p, _)) => Ok(p),
-                    None => Err(Error::NoSuchKey),
+                    Some((p, attributes)) => {
+                        let etag = attributes
+                            .ok_or_else(|| Error::Io("document attributes missing".to_owned()))?
+                            .etag()
+                            .to_owned();
+                        Ok(Some((p, etag)))
+                    }
+                    None => Ok(None),
                 }
             }
-            None => Err(Error::NoSuchKey),
+            None => Ok(None),
         }
     }
 
-    async fn get_keys(&self) -> Result<Vec<String>, Error> {
-        let query = self
-            .client
-            .query_documents(Query::new("SELECT * FROM c".to_string()))
-            .query_cross_partition(true);
-        let mut res = Vec::new();
+    /// Write `pair` only if the existing document still has the specified `etag` or, if `etag` is `None`, only
+    /// if no document exists yet.  Returns `false` if that precondition did not hold.
+    async fn conditional_write(&self, pair: Pair, etag: Option<String>) -> Result<bool, Error> {
+        let result = match etag {
+            Some(etag) => {
+                let document_client = self
+                    .client
+                    .document_client(pair.id.clone(), &pair.id)
+                    .map_err(log_error)?;
+                document_client
+                    .replace_document(pair)
+                    .if_match_condition(IfMatchCondition::Match(etag))
+                    .await
+                    .map(drop)
+            }
+            None => self.client.create_document(pair).await.map(drop),
+        };
 
-        let mut stream = query.into_stream::<Pair>();
-        while let Some(resp) = stream.next().await {
-            let resp = resp.map_err(log_error)?;
-            for (pair, _) in resp.results {
-                res.push(pair.id);
+        match result {
+            Ok(()) => Ok(true),
+            Err(e)
+                if is_status(&e, StatusCode::PreconditionFailed)
+                    || is_status(&e, StatusCode::Conflict) =>
+            {
+                Ok(false)
             }
+            Err(e) => Err(log_error(e)),
         }
-
-        Ok(res)
     }
 }
 
@@ -132,6 +311,43 @@ pub struct Pair {
     // In Azure CosmosDB, the default partition key is "/id", and this implementation assumes that partition ID is not changed.
     pub id: String,
     pub value: Vec<u8>,
+    // The number of seconds after its last modification at which Cosmos will expire this item.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<i32>,
+    // The time of the item's last modification, in seconds since the Unix epoch, as recorded by Cosmos.
+    #[serde(rename = "_ts", default, skip_serializing)]
+    pub ts: Option<i64>,
+}
+
+impl Pair {
+    /// Whether this item has outlived its `ttl`.  This mirrors `UNEXPIRED`.
+    fn is_expired(&self) -> bool {
+        match (self.ttl, self.ts) {
+            (Some(ttl), Some(ts)) if ttl >= 0 => ts + i64::from(ttl) <= now(),
+            _ => false,
+        }
+    }
+
+    /// The `ttl` with which to rewrite this item so that it still expires at the same time, since Cosmos measures
+    /// `ttl` from the last modification.
+    fn remaining_ttl(&self) -> Option<i32> {
+        let ttl = self.ttl?;
+        let Some(ts) = self.ts else {
+            return Some(ttl);
+        };
+        Some((ts + i64::from(ttl) - now()).clamp(1, i32::MAX as i64) as i32)
+    }
+}
+
+/// A query condition matching items which haven't outlived their `ttl`, given the current time as `@now`.  Cosmos
+/// only deletes expired items itself if time-to-live is enabled on the container.
+const UNEXPIRED: &str = "(NOT IS_DEFINED(c.ttl) OR c.ttl < 0 OR c._ts + c.ttl > @now)";
+
+/// The current time in seconds since the Unix epoch, as used for Cosmos's `_ts`.
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |now| now.as_secs() as i64)
 }
 
 impl CosmosEntity for Pair {
@@ -141,3 +357,13 @@ impl CosmosEntity for Pair {
         self.id.clone()
     }
 }
+
+fn is_status(error: &azure_core::Error, status: StatusCode) -> bool {
+    error.as_http_error().map(|e| e.status()) == Some(status)
+}
+
+/// The projection of a `Pair` returned by `list_keys`.
+#[derive(Deserialize, Clone, Debug)]
+struct Key {
+    id: String,
+}
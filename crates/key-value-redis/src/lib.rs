@@ -1,11 +1,31 @@
 use anyhow::{Context, Result};
-use redis::{aio::Connection, parse_redis_url, AsyncCommands};
+use futures::StreamExt;
+use redis::{aio::Connection, parse_redis_url, AsyncCommands, Script};
 use spin_core::async_trait;
-use spin_key_value::{log_error, Error, Store, StoreManager};
-use std::sync::Arc;
-use tokio::sync::{Mutex, OnceCell};
+use spin_key_value::{log_error, Error, KeyPage, Store, StoreEvent, StoreEventKind, StoreManager};
+use std::{sync::Arc, time::Duration};
+use tokio::sync::{broadcast, Mutex, OnceCell};
 use url::Url;
 
+/// Set `KEYS[1]` to `ARGV[3]` if its current value matches the expectation described by `ARGV[1]` (a flag
+/// indicating whether a value is expected at all) and `ARGV[2]` (the expected value).
+const COMPARE_AND_SWAP_SCRIPT: &str = r#"
+local current = redis.call('GET', KEYS[1])
+if ARGV[1] == '1' then
+  if current ~= ARGV[2] then
+    return 0
+  end
+elseif current then
+  return 0
+end
+redis.call('SET', KEYS[1], ARGV[3], 'KEEPTTL')
+return 1
+"#;
+
+/// A key-value store manager backed by a single Redis database.
+///
+/// The whole database backs one store: store names are not used to partition keys, so a database should not be
+/// shared with other stores or applications, whose changes would otherwise be visible (and reported by `watch`).
 pub struct KeyValueRedis {
     database_url: Url,
     connection: OnceCell<Arc<Mutex<Connection>>>,
@@ -45,6 +65,90 @@ impl StoreManager for KeyValueRedis {
     fn is_defined(&self, _store_name: &str) -> bool {
         true
     }
+
+    /// Publish changes using Redis keyspace notifications, which must be enabled on the server (e.g. via
+    /// `notify-keyspace-events K$gx`).
+    ///
+    /// Every key in the database is reported as belonging to the store `name`, since the database backs exactly
+    /// one store.
+    async fn watch(
+        &self,
+        name: &str,
+        events: broadcast::Sender<StoreEvent>,
+    ) -> Result<bool, Error> {
+        let client = redis::Client::open(self.database_url.clone()).map_err(log_error)?;
+        let db = client.get_connection_info().redis.db;
+        let mut connection = client.get_async_connection().await.map_err(log_error)?;
+
+        // Managed servers may not allow `CONFIG`, so only warn if we can tell for sure that notifications are off.
+        let config: redis::RedisResult<Vec<String>> = redis::cmd("CONFIG")
+            .arg("GET")
+            .arg("notify-keyspace-events")
+            .query_async(&mut connection)
+            .await;
+        if let Ok([_, flags]) = config.as_deref() {
+            if !flags.contains('K') {
+                tracing::warn!(
+                    "Keyspace notifications are not enabled on the Redis server for key-value store {name:?}, so changes will not be reported"
+                );
+            }
+        }
+
+        let prefix = format!("__keyspace@{db}__:");
+        let mut pubsub = connection.into_pubsub();
+        pubsub
+            .psubscribe(format!("{prefix}*"))
+            .await
+            .map_err(log_error)?;
+
+        let name = name.to_owned();
+        tokio::spawn(async move {
+            let mut messages = pubsub.into_on_message();
+            while let Some(message) = messages.next().await {
+                let Some(key) = message.get_channel_name().strip_prefix(&prefix) else {
+                    continue;
+                };
+                let Some(kind) = message
+                    .get_payload::<String>()
+                    .ok()
+                    .and_then(|event| keyspace_event_kind(&event))
+                else {
+                    continue;
+                };
+                // An error here just means there are currently no subscribers.
+                _ = events.send(StoreEvent {
+                    store: name.clone(),
+                    key: key.to_owned(),
+                    kind,
+                });
+            }
+            tracing::warn!("Lost connection watching Redis key-value store {name:?} for changes");
+        });
+
+        Ok(true)
+    }
+}
+
+/// Map the name of a keyspace notification event to the kind of change it represents, if any.
+fn keyspace_event_kind(event: &str) -> Option<StoreEventKind> {
+    match event {
+        "set" | "setrange" | "append" | "incrby" | "incrbyfloat" | "rename_to" | "restore"
+        | "copy_to" => Some(StoreEventKind::Set),
+        "del" | "expired" | "evicted" | "rename_from" => Some(StoreEventKind::Delete),
+        _ => None,
+    }
+}
+
+/// Escape the glob-style metacharacters in `s` so it only matches itself in a `SCAN MATCH` pattern.
+fn escape_pattern(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
 }
 
 struct RedisStore {
@@ -91,11 +195,113 @@ impl Store for RedisStore {
             .map_err(log_error)
     }
 
-    async fn get_keys(&self) -> Result<Vec<String>, Error> {
+    async fn get_many(&self, keys: &[String]) -> Result<Vec<Option<Vec<u8>>>, Error> {
+        // `MGET` requires at least one key.
+        if keys.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let values: Vec<Option<Vec<u8>>> = redis::cmd("MGET")
+            .arg(keys)
+            .query_async(&mut *self.connection.lock().await)
+            .await
+            .map_err(log_error)?;
+
+        // Treat empty values as missing, consistent with `get`.
+        Ok(values
+            .into_iter()
+            .map(|value| value.filter(|value| !value.is_empty()))
+            .collect())
+    }
+
+    async fn set_many(&self, key_values: &[(String, Vec<u8>)]) -> Result<(), Error> {
+        if key_values.is_empty() {
+            return Ok(());
+        }
+
+        self.connection
+            .lock()
+            .await
+            .set_multiple(key_values)
+            .await
+            .map_err(log_error)
+    }
+
+    async fn delete_many(&self, keys: &[String]) -> Result<(), Error> {
+        if keys.is_empty() {
+            return Ok(());
+        }
+
+        self.connection
+            .lock()
+            .await
+            .del(keys)
+            .await
+            .map_err(log_error)
+    }
+
+    async fn list_keys(
+        &self,
+        prefix: &str,
+        cursor: Option<&str>,
+        limit: u32,
+    ) -> Result<KeyPage, Error> {
+        // `SCAN` treats `COUNT` as a hint, so pages may be larger or smaller than `limit`, and a given key may be
+        // returned more than once.  A cursor of zero both starts and ends an iteration.
+        let cursor = match cursor {
+            Some(cursor) => cursor
+                .parse::<u64>()
+                .map_err(|_| Error::Io(format!("invalid cursor: {cursor}")))?,
+            None => 0,
+        };
+
+        let (next, keys): (u64, Vec<String>) = redis::cmd("SCAN")
+            .arg(cursor)
+            .arg("MATCH")
+            .arg(format!("{}*", escape_pattern(prefix)))
+            .arg("COUNT")
+            .arg(limit)
+            .query_async(&mut *self.connection.lock().await)
+            .await
+            .map_err(log_error)?;
+
+        Ok(KeyPage {
+            keys,
+            cursor: (next != 0).then(|| next.to_string()),
+        })
+    }
+
+    async fn increment(&self, key: &str, delta: i64) -> Result<i64, Error> {
+        self.connection
+            .lock()
+            .await
+            .incr(key, delta)
+            .await
+            .map_err(log_error)
+    }
+
+    async fn compare_and_swap(
+        &self,
+        key: &str,
+        expected: Option<&[u8]>,
+        value: &[u8],
+    ) -> Result<bool, Error> {
+        let mut conn = self.connection.lock().await;
+        Script::new(COMPARE_AND_SWAP_SCRIPT)
+            .key(key)
+            .arg(if expected.is_some() { "1" } else { "0" })
+            .arg(expected.unwrap_or_default())
+            .arg(value)
+            .invoke_async(&mut *conn)
+            .await
+            .map_err(log_error)
+    }
+
+    async fn set_with_ttl(&self, key: &str, value: &[u8], ttl: Duration) -> Result<(), Error> {
         self.connection
             .lock()
             .await
-            .keys("*")
+            .pset_ex(key, value, ttl.as_millis().try_into().unwrap_or(usize::MAX))
             .await
             .map_err(log_error)
     }
@@ -1,14 +1,21 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use once_cell::sync::OnceCell;
-use rusqlite::Connection;
+use rusqlite::{
+    backup::Progress, Connection, DatabaseName, OptionalExtension, TransactionBehavior,
+};
 use spin_core::async_trait;
-use spin_key_value::{log_error, Error, Store, StoreManager};
+use spin_key_value::{add_to_counter, log_error, Error, KeyPage, Store, StoreManager};
 use std::{
-    path::PathBuf,
-    sync::{Arc, Mutex},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, Weak},
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 use tokio::task;
 
+/// How often expired tuples are purged from the database in the background.  Expired tuples are hidden from
+/// readers regardless of whether they have been purged yet.
+const PURGE_INTERVAL: Duration = Duration::from_secs(60);
+
 pub enum DatabaseLocation {
     InMemory,
     Path(PathBuf),
@@ -26,6 +33,55 @@ impl KeyValueSqlite {
             connection: OnceCell::new(),
         }
     }
+
+    /// Writes a consistent snapshot of the database to `destination` with
+    /// SQLite's online backup API, without blocking readers or writers.
+    ///
+    /// The snapshot is written alongside `destination` and then moved into
+    /// place, so `destination` always holds a complete backup.
+    pub async fn backup(&self, destination: &Path) -> Result<()> {
+        let path = self.path()?.to_owned();
+        let destination = destination.to_owned();
+        task::spawn_blocking(move || {
+            let mut partial = destination.as_os_str().to_owned();
+            partial.push(".partial");
+            let _ = std::fs::remove_file(&partial);
+            Connection::open(path)?
+                .backup(DatabaseName::Main, &partial, None)
+                .with_context(|| format!("Failed to back up to {}", destination.display()))?;
+            std::fs::rename(&partial, &destination)
+                .with_context(|| format!("Failed to move backup to {}", destination.display()))
+        })
+        .await?
+    }
+
+    /// Replaces the contents of the database with a backup written by
+    /// [`KeyValueSqlite::backup`].
+    pub async fn restore(&self, source: &Path) -> Result<()> {
+        let path = self.path()?.to_owned();
+        let source = source.to_owned();
+        task::spawn_blocking(move || {
+            // Opening a missing file would create an empty database and restore that.
+            anyhow::ensure!(
+                source.is_file(),
+                "Backup {} does not exist",
+                source.display()
+            );
+            Connection::open(path)?
+                .restore(DatabaseName::Main, &source, None::<fn(Progress)>)
+                .with_context(|| format!("Failed to restore from {}", source.display()))
+        })
+        .await?
+    }
+
+    fn path(&self) -> Result<&Path> {
+        match &self.location {
+            DatabaseLocation::Path(path) => Ok(path),
+            DatabaseLocation::InMemory => {
+                anyhow::bail!("In-memory key-value stores cannot be backed up or restored")
+            }
+        }
+    }
 }
 
 #[async_trait]
@@ -39,20 +95,13 @@ impl StoreManager for KeyValueSqlite {
                 }
                 .map_err(log_error)?;
 
-                connection
-                    .execute(
-                        "CREATE TABLE IF NOT EXISTS spin_key_value (
-                           store TEXT NOT NULL,
-                           key   TEXT NOT NULL,
-                           value BLOB NOT NULL,
-
-                           PRIMARY KEY (store, key)
-                        )",
-                        [],
-                    )
-                    .map_err(log_error)?;
+                create_schema(&connection).map_err(log_error)?;
+
+                let connection = Arc::new(Mutex::new(connection));
+
+                task::spawn(purge_periodically(Arc::downgrade(&connection)));
 
-                Ok(Arc::new(Mutex::new(connection)))
+                Ok(connection)
             })
         })?;
 
@@ -67,6 +116,78 @@ impl StoreManager for KeyValueSqlite {
     }
 }
 
+fn create_schema(connection: &Connection) -> rusqlite::Result<()> {
+    connection.execute(
+        "CREATE TABLE IF NOT EXISTS spin_key_value (
+           store  TEXT NOT NULL,
+           key    TEXT NOT NULL,
+           value  BLOB NOT NULL,
+           expiry INTEGER,
+
+           PRIMARY KEY (store, key)
+        )",
+        [],
+    )?;
+
+    // Databases created by earlier versions of Spin lack the `expiry` column.
+    let has_expiry: bool = connection.query_row(
+        "SELECT COUNT(*) FROM pragma_table_info('spin_key_value') WHERE name='expiry'",
+        [],
+        |row| row.get(0),
+    )?;
+
+    if !has_expiry {
+        connection.execute("ALTER TABLE spin_key_value ADD COLUMN expiry INTEGER", [])?;
+    }
+
+    Ok(())
+}
+
+/// Purge expired tuples every `PURGE_INTERVAL` until the connection is dropped.
+async fn purge_periodically(connection: Weak<Mutex<Connection>>) {
+    let mut interval = tokio::time::interval(PURGE_INTERVAL);
+    loop {
+        interval.tick().await;
+
+        let Some(connection) = connection.upgrade() else {
+            break;
+        };
+
+        _ = task::block_in_place(|| {
+            connection
+                .lock()
+                .unwrap()
+                .prepare_cached("DELETE FROM spin_key_value WHERE expiry <= $1")
+                .map_err(log_error)?
+                .execute([now()])
+                .map_err(log_error)
+        });
+    }
+}
+
+/// The current time in milliseconds since the Unix epoch, which is how expiry times are stored.
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as i64
+}
+
+/// Return the smallest string which is greater than every string starting with `prefix`, or `None` if there is no
+/// such string (i.e. every string is either less than `prefix` or starts with it).
+fn prefix_upper_bound(prefix: &str) -> Option<String> {
+    // UTF-8 preserves code point order, so we can increment the last character which isn't already `char::MAX`
+    // (skipping the surrogate range, which aren't valid characters) and drop everything after it.
+    let mut chars = prefix.chars().collect::<Vec<_>>();
+    while let Some(last) = chars.pop() {
+        if let Some(next) = (u32::from(last) + 1..=u32::from(char::MAX)).find_map(char::from_u32) {
+            chars.push(next);
+            return Some(chars.into_iter().collect());
+        }
+    }
+    None
+}
+
 struct SqliteStore {
     name: String,
     connection: Arc<Mutex<Connection>>,
@@ -76,33 +197,38 @@ struct SqliteStore {
 impl Store for SqliteStore {
     async fn get(&self, key: &str) -> Result<Vec<u8>, Error> {
         task::block_in_place(|| {
-            self.connection
-                .lock()
-                .unwrap()
-                .prepare_cached("SELECT value FROM spin_key_value WHERE store=$1 AND key=$2")
+            let connection = self.connection.lock().unwrap();
+
+            let (value, expiry): (Vec<u8>, Option<i64>) = connection
+                .prepare_cached(
+                    "SELECT value, expiry FROM spin_key_value WHERE store=$1 AND key=$2",
+                )
                 .map_err(log_error)?
-                .query_map([&self.name, key], |row| row.get(0))
+                .query_row([&self.name, key], |row| Ok((row.get(0)?, row.get(1)?)))
+                .optional()
                 .map_err(log_error)?
-                .next()
-                .ok_or(Error::NoSuchKey)?
-                .map_err(log_error)
+                .ok_or(Error::NoSuchKey)?;
+
+            let now = now();
+            if expiry.map(|expiry| expiry <= now).unwrap_or(false) {
+                // Lazily purge the expired tuple rather than waiting for `purge_periodically` to get to it.
+                connection
+                    .prepare_cached(
+                        "DELETE FROM spin_key_value WHERE store=$1 AND key=$2 AND expiry <= $3",
+                    )
+                    .map_err(log_error)?
+                    .execute(rusqlite::params![&self.name, key, now])
+                    .map_err(log_error)?;
+
+                return Err(Error::NoSuchKey);
+            }
+
+            Ok(value)
         })
     }
 
     async fn set(&self, key: &str, value: &[u8]) -> Result<(), Error> {
-        task::block_in_place(|| {
-            self.connection
-                .lock()
-                .unwrap()
-                .prepare_cached(
-                    "INSERT INTO spin_key_value (store, key, value) VALUES ($1, $2, $3)
-                     ON CONFLICT(store, key) DO UPDATE SET value=$3",
-                )
-                .map_err(log_error)?
-                .execute(rusqlite::params![&self.name, key, value])
-                .map_err(log_error)
-                .map(drop)
-        })
+        self.set_with_expiry(key, value, None)
     }
 
     async fn delete(&self, key: &str) -> Result<(), Error> {
@@ -126,17 +252,216 @@ impl Store for SqliteStore {
         }
     }
 
-    async fn get_keys(&self) -> Result<Vec<String>, Error> {
+    async fn get_many(&self, keys: &[String]) -> Result<Vec<Option<Vec<u8>>>, Error> {
+        task::block_in_place(|| {
+            let mut connection = self.connection.lock().unwrap();
+            // Read within a transaction so the values are consistent with each other.
+            let transaction = connection.transaction().map_err(log_error)?;
+            let now = now();
+
+            let values = {
+                let mut statement = transaction
+                    .prepare_cached(
+                        "SELECT value FROM spin_key_value
+                         WHERE store=$1 AND key=$2 AND (expiry IS NULL OR expiry > $3)",
+                    )
+                    .map_err(log_error)?;
+
+                keys.iter()
+                    .map(|key| {
+                        statement
+                            .query_row(rusqlite::params![&self.name, key, now], |row| row.get(0))
+                            .optional()
+                            .map_err(log_error)
+                    })
+                    .collect::<Result<Vec<_>, _>>()?
+            };
+
+            transaction.commit().map_err(log_error)?;
+
+            Ok(values)
+        })
+    }
+
+    async fn set_many(&self, key_values: &[(String, Vec<u8>)]) -> Result<(), Error> {
+        task::block_in_place(|| {
+            let mut connection = self.connection.lock().unwrap();
+            let transaction = connection.transaction().map_err(log_error)?;
+
+            {
+                let mut statement = transaction
+                    .prepare_cached(
+                        "INSERT INTO spin_key_value (store, key, value, expiry) VALUES ($1, $2, $3, NULL)
+                         ON CONFLICT(store, key) DO UPDATE SET value=$3, expiry=NULL",
+                    )
+                    .map_err(log_error)?;
+
+                for (key, value) in key_values {
+                    statement
+                        .execute(rusqlite::params![&self.name, key, value])
+                        .map_err(log_error)?;
+                }
+            }
+
+            transaction.commit().map_err(log_error)
+        })
+    }
+
+    async fn delete_many(&self, keys: &[String]) -> Result<(), Error> {
         task::block_in_place(|| {
+            let mut connection = self.connection.lock().unwrap();
+            let transaction = connection.transaction().map_err(log_error)?;
+
+            {
+                let mut statement = transaction
+                    .prepare_cached("DELETE FROM spin_key_value WHERE store=$1 AND key=$2")
+                    .map_err(log_error)?;
+
+                for key in keys {
+                    statement.execute([&self.name, key]).map_err(log_error)?;
+                }
+            }
+
+            transaction.commit().map_err(log_error)
+        })
+    }
+
+    async fn list_keys(
+        &self,
+        prefix: &str,
+        cursor: Option<&str>,
+        limit: u32,
+    ) -> Result<KeyPage, Error> {
+        // Keys are listed in order, and the cursor is simply the last key returned.  Since SQLite compares text
+        // bytewise by default, all keys starting with `prefix` fall in the range [prefix, upper bound), which
+        // lets SQLite use the primary key index rather than scanning the whole store.
+        let mut keys = task::block_in_place(|| {
             self.connection
                 .lock()
                 .unwrap()
-                .prepare_cached("SELECT key FROM spin_key_value WHERE store=$1")
+                .prepare_cached(
+                    "SELECT key FROM spin_key_value
+                     WHERE store=$1 AND key >= $2 AND ($3 IS NULL OR key < $3) AND ($4 IS NULL OR key > $4)
+                       AND (expiry IS NULL OR expiry > $5)
+                     ORDER BY key LIMIT $6",
+                )
                 .map_err(log_error)?
-                .query_map([&self.name], |row| row.get(0))
+                .query_map(
+                    rusqlite::params![
+                        &self.name,
+                        prefix,
+                        prefix_upper_bound(prefix),
+                        cursor,
+                        now(),
+                        // Fetch one extra key so we know whether there's another page.
+                        i64::from(limit) + 1
+                    ],
+                    |row| row.get(0),
+                )
                 .map_err(log_error)?
                 .map(|r| r.map_err(log_error))
-                .collect()
+                .collect::<Result<Vec<String>, Error>>()
+        })?;
+
+        let cursor = if keys.len() > limit as usize {
+            keys.truncate(limit as usize);
+            keys.last().cloned()
+        } else {
+            None
+        };
+
+        Ok(KeyPage { keys, cursor })
+    }
+
+    async fn increment(&self, key: &str, delta: i64) -> Result<i64, Error> {
+        self.read_modify_write(key, |current| {
+            let value = add_to_counter(current, delta)?;
+            Ok((Some(value.to_string().into_bytes()), value))
+        })
+    }
+
+    async fn compare_and_swap(
+        &self,
+        key: &str,
+        expected: Option<&[u8]>,
+        value: &[u8],
+    ) -> Result<bool, Error> {
+        self.read_modify_write(key, |current| {
+            if current == expected {
+                Ok((Some(value.to_owned()), true))
+            } else {
+                Ok((None, false))
+            }
+        })
+    }
+
+    async fn set_with_ttl(&self, key: &str, value: &[u8], ttl: Duration) -> Result<(), Error> {
+        let expiry = now().saturating_add(ttl.as_millis().try_into().unwrap_or(i64::MAX));
+        self.set_with_expiry(key, value, Some(expiry))
+    }
+}
+
+impl SqliteStore {
+    fn set_with_expiry(&self, key: &str, value: &[u8], expiry: Option<i64>) -> Result<(), Error> {
+        task::block_in_place(|| {
+            self.connection
+                .lock()
+                .unwrap()
+                .prepare_cached(
+                    "INSERT INTO spin_key_value (store, key, value, expiry) VALUES ($1, $2, $3, $4)
+                     ON CONFLICT(store, key) DO UPDATE SET value=$3, expiry=$4",
+                )
+                .map_err(log_error)?
+                .execute(rusqlite::params![&self.name, key, value, expiry])
+                .map_err(log_error)
+                .map(drop)
+        })
+    }
+
+    /// Read the current value for `key`, pass it to `f`, and write back the new value (if any) returned by `f`,
+    /// all within a single immediate transaction so that no other writer can intervene.
+    ///
+    /// The expiry of an existing, unexpired tuple is preserved.
+    fn read_modify_write<T>(
+        &self,
+        key: &str,
+        f: impl FnOnce(Option<&[u8]>) -> Result<(Option<Vec<u8>>, T), Error>,
+    ) -> Result<T, Error> {
+        task::block_in_place(|| {
+            let mut connection = self.connection.lock().unwrap();
+            let transaction = connection
+                .transaction_with_behavior(TransactionBehavior::Immediate)
+                .map_err(log_error)?;
+
+            let now = now();
+
+            let current: Option<Vec<u8>> = transaction
+                .prepare_cached(
+                    "SELECT value FROM spin_key_value
+                     WHERE store=$1 AND key=$2 AND (expiry IS NULL OR expiry > $3)",
+                )
+                .map_err(log_error)?
+                .query_row(rusqlite::params![&self.name, key, now], |row| row.get(0))
+                .optional()
+                .map_err(log_error)?;
+
+            let (new_value, result) = f(current.as_deref())?;
+
+            if let Some(new_value) = new_value {
+                transaction
+                    .prepare_cached(
+                        "INSERT INTO spin_key_value (store, key, value) VALUES ($1, $2, $3)
+                         ON CONFLICT(store, key) DO UPDATE
+                         SET value=$3, expiry=CASE WHEN expiry <= $4 THEN NULL ELSE expiry END",
+                    )
+                    .map_err(log_error)?
+                    .execute(rusqlite::params![&self.name, key, new_value, now])
+                    .map_err(log_error)?;
+            }
+
+            transaction.commit().map_err(log_error)?;
+
+            Ok(result)
         })
     }
 }
@@ -144,7 +469,7 @@ impl Store for SqliteStore {
 #[cfg(test)]
 mod test {
     use super::*;
-    use spin_key_value::{DelegatingStoreManager, KeyValueDispatch};
+    use spin_key_value::{DelegatingStoreManager, KeyValueDispatch, StoreEvent, StoreEventKind};
     use spin_world::key_value::Host;
 
     #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
@@ -216,4 +541,275 @@ mod test {
 
         Ok(())
     }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn atomic() -> Result<()> {
+        let mut kv = KeyValueDispatch::new();
+        kv.init(
+            ["default"].into_iter().map(ToOwned::to_owned).collect(),
+            Arc::new(DelegatingStoreManager::new([(
+                "default".to_owned(),
+                Arc::new(KeyValueSqlite::new(DatabaseLocation::InMemory)) as _,
+            )])),
+        );
+
+        let store = kv.open("default".to_owned()).await??;
+
+        assert_eq!(5, kv.increment(store, "counter".to_owned(), 5).await??);
+        assert_eq!(3, kv.increment(store, "counter".to_owned(), -2).await??);
+        assert_eq!(b"3" as &[_], &kv.get(store, "counter".to_owned()).await??);
+
+        kv.set(store, "bar".to_owned(), b"baz".to_vec()).await??;
+
+        assert!(matches!(
+            kv.increment(store, "bar".to_owned(), 1).await?,
+            Err(Error::Io(_))
+        ));
+
+        assert!(
+            !kv.compare_and_swap(store, "bar".to_owned(), None, b"wow".to_vec())
+                .await??
+        );
+        assert!(
+            !kv.compare_and_swap(
+                store,
+                "bar".to_owned(),
+                Some(b"nope".to_vec()),
+                b"wow".to_vec()
+            )
+            .await??
+        );
+        assert_eq!(b"baz" as &[_], &kv.get(store, "bar".to_owned()).await??);
+
+        assert!(
+            kv.compare_and_swap(
+                store,
+                "bar".to_owned(),
+                Some(b"baz".to_vec()),
+                b"wow".to_vec()
+            )
+            .await??
+        );
+        assert_eq!(b"wow" as &[_], &kv.get(store, "bar".to_owned()).await??);
+
+        assert!(
+            kv.compare_and_swap(store, "new".to_owned(), None, b"first".to_vec())
+                .await??
+        );
+        assert_eq!(b"first" as &[_], &kv.get(store, "new".to_owned()).await??);
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn ttl() -> Result<()> {
+        let mut kv = KeyValueDispatch::new();
+        kv.init(
+            ["default"].into_iter().map(ToOwned::to_owned).collect(),
+            Arc::new(DelegatingStoreManager::new([(
+                "default".to_owned(),
+                Arc::new(KeyValueSqlite::new(DatabaseLocation::InMemory)) as _,
+            )])),
+        );
+
+        let store = kv.open("default".to_owned()).await??;
+
+        assert!(matches!(
+            kv.set_with_ttl(store, "bar".to_owned(), b"baz".to_vec(), 0)
+                .await?,
+            Err(Error::Io(_))
+        ));
+
+        kv.set_with_ttl(store, "bar".to_owned(), b"baz".to_vec(), 1)
+            .await??;
+        kv.set_with_ttl(store, "keep".to_owned(), b"me".to_vec(), 1)
+            .await??;
+        kv.set(store, "keep".to_owned(), b"me".to_vec()).await??;
+
+        assert!(kv.exists(store, "bar".to_owned()).await??);
+        assert_eq!(b"baz" as &[_], &kv.get(store, "bar".to_owned()).await??);
+
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+
+        assert!(!kv.exists(store, "bar".to_owned()).await??);
+        assert!(matches!(
+            kv.get(store, "bar".to_owned()).await?,
+            Err(Error::NoSuchKey)
+        ));
+        assert_eq!(&["keep".to_owned()] as &[_], &kv.get_keys(store).await??);
+
+        assert_eq!(1, kv.increment(store, "bar".to_owned(), 1).await??);
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn list_keys() -> Result<()> {
+        let mut kv = KeyValueDispatch::new();
+        kv.init(
+            ["default"].into_iter().map(ToOwned::to_owned).collect(),
+            Arc::new(DelegatingStoreManager::new([(
+                "default".to_owned(),
+                Arc::new(KeyValueSqlite::new(DatabaseLocation::InMemory)) as _,
+            )])),
+        );
+
+        let store = kv.open("default".to_owned()).await??;
+
+        for key in ["a", "b/1", "b/2", "b/3", "b0", "c"] {
+            kv.set(store, key.to_owned(), b"x".to_vec()).await??;
+        }
+
+        assert!(matches!(
+            kv.list_keys(store, "".to_owned(), None, 0).await?,
+            Err(Error::Io(_))
+        ));
+
+        let page = kv.list_keys(store, "b/".to_owned(), None, 2).await??;
+        assert_eq!(&["b/1", "b/2"] as &[_], &page.keys);
+        assert!(page.cursor.is_some());
+
+        let page = kv
+            .list_keys(store, "b/".to_owned(), page.cursor, 2)
+            .await??;
+        assert_eq!(&["b/3"] as &[_], &page.keys);
+        assert!(page.cursor.is_none());
+
+        let page = kv.list_keys(store, "".to_owned(), None, 10).await??;
+        assert_eq!(&["a", "b/1", "b/2", "b/3", "b0", "c"] as &[_], &page.keys);
+        assert!(page.cursor.is_none());
+
+        let mut keys = kv.get_keys(store).await??;
+        keys.sort();
+        assert_eq!(&["a", "b/1", "b/2", "b/3", "b0", "c"] as &[_], &keys);
+
+        assert_eq!(Some("b0".to_owned()), prefix_upper_bound("b/"));
+        assert_eq!(None, prefix_upper_bound("\u{10FFFF}"));
+        assert_eq!(
+            Some("\u{E000}".to_owned()),
+            prefix_upper_bound("\u{D7FF}\u{10FFFF}")
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn batch() -> Result<()> {
+        let mut kv = KeyValueDispatch::new();
+        kv.init(
+            ["default"].into_iter().map(ToOwned::to_owned).collect(),
+            Arc::new(DelegatingStoreManager::new([(
+                "default".to_owned(),
+                Arc::new(KeyValueSqlite::new(DatabaseLocation::InMemory)) as _,
+            )])),
+        );
+
+        let store = kv.open("default".to_owned()).await??;
+
+        kv.set(store, "a".to_owned(), b"old".to_vec()).await??;
+
+        kv.set_many(
+            store,
+            vec![
+                ("a".to_owned(), b"1".to_vec()),
+                ("b".to_owned(), b"2".to_vec()),
+                ("c".to_owned(), b"3".to_vec()),
+            ],
+        )
+        .await??;
+
+        assert_eq!(
+            vec![Some(b"1".to_vec()), None, Some(b"3".to_vec())],
+            kv.get_many(store, vec!["a".to_owned(), "x".to_owned(), "c".to_owned()])
+                .await??
+        );
+
+        kv.delete_many(store, vec!["a".to_owned(), "b".to_owned(), "x".to_owned()])
+            .await??;
+
+        assert_eq!(&["c".to_owned()] as &[_], &kv.get_keys(store).await??);
+
+        assert_eq!(
+            Vec::<Option<Vec<u8>>>::new(),
+            kv.get_many(store, vec![]).await??
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn events() -> Result<()> {
+        let manager = Arc::new(DelegatingStoreManager::new([(
+            "default".to_owned(),
+            Arc::new(KeyValueSqlite::new(DatabaseLocation::InMemory)) as _,
+        )]));
+
+        assert!(matches!(
+            manager.subscribe(["foo"]).await,
+            Err(Error::NoSuchStore)
+        ));
+        let mut events = manager.subscribe(["default"]).await?;
+
+        let mut kv = KeyValueDispatch::new();
+        kv.init(
+            ["default"].into_iter().map(ToOwned::to_owned).collect(),
+            manager,
+        );
+
+        let store = kv.open("default".to_owned()).await??;
+
+        let event = |key: &str, kind| StoreEvent {
+            store: "default".to_owned(),
+            key: key.to_owned(),
+            kind,
+        };
+
+        kv.set(store, "a".to_owned(), b"1".to_vec()).await??;
+        assert_eq!(event("a", StoreEventKind::Set), events.recv().await?);
+
+        kv.increment(store, "n".to_owned(), 1).await??;
+        assert_eq!(event("n", StoreEventKind::Set), events.recv().await?);
+
+        // Failed swaps don't change anything, so they aren't reported.
+        assert!(
+            !kv.compare_and_swap(store, "a".to_owned(), None, b"2".to_vec())
+                .await??
+        );
+        kv.delete_many(store, vec!["a".to_owned(), "n".to_owned()])
+            .await??;
+        assert_eq!(event("a", StoreEventKind::Delete), events.recv().await?);
+        assert_eq!(event("n", StoreEventKind::Delete), events.recv().await?);
+
+        // Reads aren't reported either.
+        kv.get_keys(store).await??;
+        assert!(events.try_recv().is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn backup_and_restore() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let backup = dir.path().join("backup.db");
+
+        let kv = KeyValueSqlite::new(DatabaseLocation::Path(dir.path().join("kv.db")));
+        let store = kv.get("default").await?;
+        store.set("a", b"1").await?;
+
+        kv.backup(&backup).await?;
+        store.set("a", b"2").await?;
+        store.set("b", b"3").await?;
+
+        kv.restore(&backup).await?;
+        assert_eq!(b"1", store.get("a").await?.as_slice());
+        assert!(matches!(store.get("b").await, Err(Error::NoSuchKey)));
+
+        assert!(kv.restore(&dir.path().join("missing.db")).await.is_err());
+        assert!(KeyValueSqlite::new(DatabaseLocation::InMemory)
+            .backup(&backup)
+            .await
+            .is_err());
+
+        Ok(())
+    }
 }
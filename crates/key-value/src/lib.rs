@@ -1,12 +1,11 @@
 use anyhow::Result;
 use spin_app::MetadataKey;
-use spin_core::async_trait;
+use spin_core::{async_trait, table::Table};
 use spin_world::key_value;
-use std::{collections::HashSet, sync::Arc};
-use table::Table;
+use std::{collections::HashSet, sync::Arc, time::Duration};
+use tokio::sync::broadcast;
 
 mod host_component;
-pub mod table;
 mod util;
 
 pub use host_component::{manager, KeyValueComponent};
@@ -16,12 +15,65 @@ pub const KEY_VALUE_STORES_KEY: MetadataKey<Vec<String>> = MetadataKey::new("key
 
 const DEFAULT_STORE_TABLE_CAPACITY: u32 = 256;
 
-pub use key_value::{Error, Store as StoreHandle};
+/// The page size used when `Store::get_keys` lists keys via `Store::list_keys`.
+const GET_KEYS_PAGE_SIZE: u32 = 1000;
+
+pub use key_value::{Error, KeyPage, Store as StoreHandle};
+
+/// The kind of change described by a [`StoreEvent`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StoreEventKind {
+    /// A value was written for the key, whether by `set`, `increment`, etc.
+    Set,
+    /// The tuple for the key was deleted.  Stores which are watched directly (see [`StoreManager::watch`]) also
+    /// report expiry this way.
+    Delete,
+}
+
+/// A change made to a tuple in a key-value store.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StoreEvent {
+    pub store: String,
+    pub key: String,
+    pub kind: StoreEventKind,
+}
 
 #[async_trait]
 pub trait StoreManager: Sync + Send {
     async fn get(&self, name: &str) -> Result<Arc<dyn Store>, Error>;
     fn is_defined(&self, store_name: &str) -> bool;
+
+    /// Begin sending an event to `events` for each change made to the named store, if the implementation is able
+    /// to observe changes made by other processes.
+    ///
+    /// Returns `true` if so, in which case the events include changes made via this process as well.  The
+    /// default implementation does nothing and returns `false`.
+    async fn watch(
+        &self,
+        _name: &str,
+        _events: broadcast::Sender<StoreEvent>,
+    ) -> Result<bool, Error> {
+        Ok(false)
+    }
+}
+
+#[async_trait]
+impl<T: StoreManager + ?Sized> StoreManager for Arc<T> {
+    async fn get(&self, name: &str) -> Result<Arc<dyn Store>, Error> {
+        (**self).get(name).await
+    }
+
+    fn is_defined(&self, store_name: &str) -> bool {
+        (**self).is_defined(store_name)
+    }
+
+    async fn watch(
+        &self,
+        name: &str,
+        events: broadcast::Sender<StoreEvent>,
+    ) -> Result<bool, Error> {
+        (**self).watch(name, events).await
+    }
 }
 
 #[async_trait]
@@ -34,7 +86,70 @@ pub trait Store: Sync + Send {
 
     async fn exists(&self, key: &str) -> Result<bool, Error>;
 
-    async fn get_keys(&self) -> Result<Vec<String>, Error>;
+    /// Get the value for each of `keys`, returning `None` for keys with no tuple.
+    async fn get_many(&self, keys: &[String]) -> Result<Vec<Option<Vec<u8>>>, Error>;
+
+    /// Set the value for each key in `key_values`.
+    ///
+    /// This need not be atomic (e.g. for Azure Cosmos DB, where each key is its own partition), so if it fails,
+    /// some of the values may have been set, and other clients may observe some values set before others.
+    async fn set_many(&self, key_values: &[(String, Vec<u8>)]) -> Result<(), Error>;
+
+    /// Delete the tuple for each of `keys`, ignoring keys with no tuple.
+    ///
+    /// As with `set_many`, this need not be atomic, so if it fails, some of the tuples may have been deleted.
+    async fn delete_many(&self, keys: &[String]) -> Result<(), Error>;
+
+    /// List every key in the store.
+    ///
+    /// This is provided for compatibility and is implemented in terms of `list_keys`, so implementations need
+    /// not override it.
+    async fn get_keys(&self) -> Result<Vec<String>, Error> {
+        let mut keys = Vec::new();
+        let mut seen = HashSet::new();
+        let mut cursor = None;
+        loop {
+            let page = self
+                .list_keys("", cursor.as_deref(), GET_KEYS_PAGE_SIZE)
+                .await?;
+            // Some backends (e.g. Redis' `SCAN`) may return a key more than once across pages.
+            keys.extend(page.keys.into_iter().filter(|k| seen.insert(k.clone())));
+            match page.cursor {
+                Some(next) => cursor = Some(next),
+                None => return Ok(keys),
+            }
+        }
+    }
+
+    /// List up to (approximately) `limit` keys starting with `prefix`, continuing from `cursor` if specified.
+    async fn list_keys(
+        &self,
+        prefix: &str,
+        cursor: Option<&str>,
+        limit: u32,
+    ) -> Result<KeyPage, Error>;
+
+    /// Atomically add `delta` to the counter stored at `key`, returning the new value.
+    ///
+    /// A missing tuple is treated as a counter with value zero.  See [`parse_counter`] for the encoding.  An
+    /// existing tuple keeps its expiry time, if it has one.
+    async fn increment(&self, key: &str, delta: i64) -> Result<i64, Error>;
+
+    /// Atomically set `value` for `key` if and only if the current value matches `expected` (where `None` means
+    /// "no tuple exists"), returning whether the value was set.  An existing tuple keeps its expiry time, if it
+    /// has one.
+    async fn compare_and_swap(
+        &self,
+        key: &str,
+        expected: Option<&[u8]>,
+        value: &[u8],
+    ) -> Result<bool, Error>;
+
+    /// Set `value` for `key`, after which the tuple will expire once `ttl` has elapsed.
+    ///
+    /// Expired tuples must be hidden from `get`, `exists` and `get_keys`, whether or not they have actually been
+    /// removed from the backing store yet.
+    async fn set_with_ttl(&self, key: &str, value: &[u8], ttl: Duration) -> Result<(), Error>;
 }
 
 pub struct KeyValueDispatch {
@@ -143,6 +258,120 @@ impl key_value::Host for KeyValueDispatch {
         .await)
     }
 
+    async fn list_keys(
+        &mut self,
+        store: StoreHandle,
+        prefix: String,
+        cursor: Option<String>,
+        limit: u32,
+    ) -> Result<Result<KeyPage, Error>> {
+        Ok(async {
+            let store = self.stores.get(store).ok_or(Error::InvalidStore)?;
+            if limit == 0 {
+                return Err(Error::Io("limit must be greater than zero".to_owned()));
+            }
+            store.list_keys(&prefix, cursor.as_deref(), limit).await
+        }
+        .await)
+    }
+
+    async fn increment(
+        &mut self,
+        store: StoreHandle,
+        key: String,
+        delta: i64,
+    ) -> Result<Result<i64, Error>> {
+        Ok(async {
+            self.stores
+                .get(store)
+                .ok_or(Error::InvalidStore)?
+                .increment(&key, delta)
+                .await
+        }
+        .await)
+    }
+
+    async fn compare_and_swap(
+        &mut self,
+        store: StoreHandle,
+        key: String,
+        expected: Option<Vec<u8>>,
+        value: Vec<u8>,
+    ) -> Result<Result<bool, Error>> {
+        Ok(async {
+            self.stores
+                .get(store)
+                .ok_or(Error::InvalidStore)?
+                .compare_and_swap(&key, expected.as_deref(), &value)
+                .await
+        }
+        .await)
+    }
+
+    async fn get_many(
+        &mut self,
+        store: StoreHandle,
+        keys: Vec<String>,
+    ) -> Result<Result<Vec<Option<Vec<u8>>>, Error>> {
+        Ok(async {
+            self.stores
+                .get(store)
+                .ok_or(Error::InvalidStore)?
+                .get_many(&keys)
+                .await
+        }
+        .await)
+    }
+
+    async fn set_many(
+        &mut self,
+        store: StoreHandle,
+        key_values: Vec<(String, Vec<u8>)>,
+    ) -> Result<Result<(), Error>> {
+        Ok(async {
+            self.stores
+                .get(store)
+                .ok_or(Error::InvalidStore)?
+                .set_many(&key_values)
+                .await
+        }
+        .await)
+    }
+
+    async fn delete_many(
+        &mut self,
+        store: StoreHandle,
+        keys: Vec<String>,
+    ) -> Result<Result<(), Error>> {
+        Ok(async {
+            self.stores
+                .get(store)
+                .ok_or(Error::InvalidStore)?
+                .delete_many(&keys)
+                .await
+        }
+        .await)
+    }
+
+    async fn set_with_ttl(
+        &mut self,
+        store: StoreHandle,
+        key: String,
+        value: Vec<u8>,
+        ttl_seconds: u32,
+    ) -> Result<Result<(), Error>> {
+        Ok(async {
+            let store = self.stores.get(store).ok_or(Error::InvalidStore)?;
+            if ttl_seconds == 0 {
+                return Err(Error::Io("ttl must be greater than zero".to_owned()));
+            }
+            store
+                .set_with_ttl(&key, &value, Duration::from_secs(ttl_seconds.into()))
+                .await
+        }
+        .await)
+    }
+
     async fn close(&mut self, store: StoreHandle) -> Result<()> {
         self.stores.remove(store);
         Ok(())
@@ -153,3 +382,21 @@ pub fn log_error(err: impl std::fmt::Debug) -> Error {
     tracing::warn!("key-value error: {err:?}");
     Error::Io(format!("{err:?}"))
 }
+
+/// Decode a counter value as written by [`Store::increment`], i.e. a decimal integer encoded as UTF-8.
+pub fn parse_counter(value: &[u8]) -> Result<i64, Error> {
+    std::str::from_utf8(value)
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| Error::Io("value is not an integer".to_owned()))
+}
+
+/// Add `delta` to the (possibly missing) counter value `current`, returning the new value.
+pub fn add_to_counter(current: Option<&[u8]>, delta: i64) -> Result<i64, Error> {
+    current
+        .map(parse_counter)
+        .transpose()?
+        .unwrap_or(0)
+        .checked_add(delta)
+        .ok_or_else(|| Error::Io("increment would overflow".to_owned()))
+}
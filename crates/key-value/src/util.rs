@@ -1,19 +1,23 @@
-use crate::{Error, Store, StoreManager};
+use crate::{Error, KeyPage, Store, StoreEvent, StoreEventKind, StoreManager};
 use lru::LruCache;
 use spin_core::async_trait;
 use std::{
     collections::{HashMap, HashSet},
     future::Future,
     num::NonZeroUsize,
-    sync::Arc,
+    sync::{Arc, Mutex},
+    time::Duration,
 };
 use tokio::{
-    sync::Mutex as AsyncMutex,
+    sync::{broadcast, Mutex as AsyncMutex},
     task::{self, JoinHandle},
 };
 
 const DEFAULT_CACHE_SIZE: usize = 256;
 
+/// The number of events buffered for each `DelegatingStoreManager` subscriber before the oldest are dropped.
+const EVENT_CHANNEL_CAPACITY: usize = 1024;
+
 pub struct EmptyStoreManager;
 
 #[async_trait]
@@ -27,30 +31,188 @@ impl StoreManager for EmptyStoreManager {
     }
 }
 
+/// Route each store name to the `StoreManager` configured for it.
+///
+/// This also acts as the hook for change notifications: while anyone is subscribed (see
+/// [`DelegatingStoreManager::subscribe`]), each write made via a store it returns is published as a
+/// [`StoreEvent`].  Stores whose delegates can observe changes directly (see [`StoreManager::watch`]) are
+/// published by the delegate instead, so that changes made by other processes are included and none are
+/// reported twice.
 pub struct DelegatingStoreManager {
     delegates: HashMap<String, Arc<dyn StoreManager>>,
+    events: broadcast::Sender<StoreEvent>,
+    // Map of store name to whether its delegate publishes events itself
+    watched: Mutex<HashMap<String, bool>>,
 }
 
 impl DelegatingStoreManager {
     pub fn new(delegates: impl IntoIterator<Item = (String, Arc<dyn StoreManager>)>) -> Self {
         let delegates = delegates.into_iter().collect();
-        Self { delegates }
+        let (events, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        Self {
+            delegates,
+            events,
+            watched: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Subscribe to changes made to the named stores.
+    ///
+    /// The returned receiver yields events for every store subscribed to via this manager, so callers should
+    /// filter by [`StoreEvent::store`] as necessary.
+    pub async fn subscribe(
+        &self,
+        names: impl IntoIterator<Item = &str>,
+    ) -> Result<broadcast::Receiver<StoreEvent>, Error> {
+        let receiver = self.events.subscribe();
+        for name in names {
+            let delegate = self.delegates.get(name).ok_or(Error::NoSuchStore)?;
+            if self.watched.lock().unwrap().contains_key(name) {
+                continue;
+            }
+            let external = delegate.watch(name, self.events.clone()).await?;
+            self.watched
+                .lock()
+                .unwrap()
+                .insert(name.to_owned(), external);
+        }
+        Ok(receiver)
     }
 }
 
 #[async_trait]
 impl StoreManager for DelegatingStoreManager {
     async fn get(&self, name: &str) -> Result<Arc<dyn Store>, Error> {
-        self.delegates
+        let store = self
+            .delegates
             .get(name)
             .ok_or(Error::NoSuchStore)?
             .get(name)
-            .await
+            .await?;
+
+        // Only publish events for stores someone has subscribed to and whose delegate doesn't do so itself.
+        if self.watched.lock().unwrap().get(name) == Some(&false) {
+            Ok(Arc::new(NotifyingStore {
+                name: name.to_owned(),
+                inner: store,
+                events: self.events.clone(),
+            }))
+        } else {
+            Ok(store)
+        }
     }
 
     fn is_defined(&self, store_name: &str) -> bool {
         self.delegates.contains_key(store_name)
     }
+
+    async fn watch(
+        &self,
+        name: &str,
+        events: broadcast::Sender<StoreEvent>,
+    ) -> Result<bool, Error> {
+        self.delegates
+            .get(name)
+            .ok_or(Error::NoSuchStore)?
+            .watch(name, events)
+            .await
+    }
+}
+
+/// Wrap a `Store`, publishing a [`StoreEvent`] for each successful write.
+struct NotifyingStore {
+    name: String,
+    inner: Arc<dyn Store>,
+    events: broadcast::Sender<StoreEvent>,
+}
+
+impl NotifyingStore {
+    fn publish<'a>(&self, keys: impl IntoIterator<Item = &'a str>, kind: StoreEventKind) {
+        for key in keys {
+            // An error here just means there are currently no subscribers.
+            _ = self.events.send(StoreEvent {
+                store: self.name.clone(),
+                key: key.to_owned(),
+                kind,
+            });
+        }
+    }
+}
+
+#[async_trait]
+impl Store for NotifyingStore {
+    async fn get(&self, key: &str) -> Result<Vec<u8>, Error> {
+        self.inner.get(key).await
+    }
+
+    async fn set(&self, key: &str, value: &[u8]) -> Result<(), Error> {
+        self.inner.set(key, value).await?;
+        self.publish([key], StoreEventKind::Set);
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), Error> {
+        self.inner.delete(key).await?;
+        self.publish([key], StoreEventKind::Delete);
+        Ok(())
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool, Error> {
+        self.inner.exists(key).await
+    }
+
+    async fn get_many(&self, keys: &[String]) -> Result<Vec<Option<Vec<u8>>>, Error> {
+        self.inner.get_many(keys).await
+    }
+
+    async fn set_many(&self, key_values: &[(String, Vec<u8>)]) -> Result<(), Error> {
+        self.inner.set_many(key_values).await?;
+        self.publish(
+            key_values.iter().map(|(key, _)| key.as_str()),
+            StoreEventKind::Set,
+        );
+        Ok(())
+    }
+
+    async fn delete_many(&self, keys: &[String]) -> Result<(), Error> {
+        self.inner.delete_many(keys).await?;
+        self.publish(keys.iter().map(String::as_str), StoreEventKind::Delete);
+        Ok(())
+    }
+
+    async fn list_keys(
+        &self,
+        prefix: &str,
+        cursor: Option<&str>,
+        limit: u32,
+    ) -> Result<KeyPage, Error> {
+        self.inner.list_keys(prefix, cursor, limit).await
+    }
+
+    async fn increment(&self, key: &str, delta: i64) -> Result<i64, Error> {
+        let value = self.inner.increment(key, delta).await?;
+        self.publish([key], StoreEventKind::Set);
+        Ok(value)
+    }
+
+    async fn compare_and_swap(
+        &self,
+        key: &str,
+        expected: Option<&[u8]>,
+        value: &[u8],
+    ) -> Result<bool, Error> {
+        let swapped = self.inner.compare_and_swap(key, expected, value).await?;
+        if swapped {
+            self.publish([key], StoreEventKind::Set);
+        }
+        Ok(swapped)
+    }
+
+    async fn set_with_ttl(&self, key: &str, value: &[u8], ttl: Duration) -> Result<(), Error> {
+        self.inner.set_with_ttl(key, value, ttl).await?;
+        self.publish([key], StoreEventKind::Set);
+        Ok(())
+    }
 }
 
 /// Wrap each `Store` produced by the inner `StoreManager` in an asynchronous, write-behind cache.
@@ -107,6 +269,14 @@ impl<T: StoreManager> StoreManager for CachingStoreManager<T> {
     fn is_defined(&self, store_name: &str) -> bool {
         self.inner.is_defined(store_name)
     }
+
+    async fn watch(
+        &self,
+        name: &str,
+        events: broadcast::Sender<StoreEvent>,
+    ) -> Result<bool, Error> {
+        self.inner.watch(name, events).await
+    }
 }
 
 struct CachingStoreState {
@@ -211,6 +381,75 @@ impl Store for CachingStore {
         }
     }
 
+    async fn get_many(&self, keys: &[String]) -> Result<Vec<Option<Vec<u8>>>, Error> {
+        // Serve what we can from the cache, and fetch the rest from the backing store in a single batch.
+
+        let mut state = self.state.lock().await;
+
+        let mut values = keys
+            .iter()
+            .map(|key| state.cache.get(key).cloned())
+            .collect::<Vec<_>>();
+
+        let missing = keys
+            .iter()
+            .zip(&values)
+            .filter(|(_, value)| value.is_none())
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+
+        if !missing.is_empty() {
+            // As in `get`, flush outstanding writes so the guest reads its own writes.
+            state.flush().await?;
+
+            let mut fetched = self.inner.get_many(&missing).await?.into_iter();
+
+            for (key, value) in keys.iter().zip(&mut values) {
+                if value.is_none() {
+                    let fetched = fetched.next().ok_or_else(|| {
+                        Error::Io("backing store returned too few values".to_owned())
+                    })?;
+                    state.cache.put(key.clone(), fetched.clone());
+                    *value = Some(fetched);
+                }
+            }
+        }
+
+        Ok(values.into_iter().map(Option::flatten).collect())
+    }
+
+    async fn set_many(&self, key_values: &[(String, Vec<u8>)]) -> Result<(), Error> {
+        // Update the cache and spawn a single task to update the backing store asynchronously.
+
+        let mut state = self.state.lock().await;
+
+        for (key, value) in key_values {
+            state.cache.put(key.clone(), Some(value.clone()));
+        }
+
+        let inner = self.inner.clone();
+        let key_values = key_values.to_owned();
+        state.spawn(async move { inner.set_many(&key_values).await });
+
+        Ok(())
+    }
+
+    async fn delete_many(&self, keys: &[String]) -> Result<(), Error> {
+        // Update the cache and spawn a single task to update the backing store asynchronously.
+
+        let mut state = self.state.lock().await;
+
+        for key in keys {
+            state.cache.put(key.clone(), None);
+        }
+
+        let inner = self.inner.clone();
+        let keys = keys.to_owned();
+        state.spawn(async move { inner.delete_many(&keys).await });
+
+        Ok(())
+    }
+
     async fn get_keys(&self) -> Result<Vec<String>, Error> {
         // Get the keys from the backing store, remove any which are `None` in the cache, and add any which are
         // `Some` in the cache, returning the result.
@@ -246,4 +485,75 @@ impl Store for CachingStore {
             .into_iter()
             .collect())
     }
+
+    async fn list_keys(
+        &self,
+        prefix: &str,
+        cursor: Option<&str>,
+        limit: u32,
+    ) -> Result<KeyPage, Error> {
+        // Overlaying the cache onto a paginated listing would be complicated, so we just flush any outstanding
+        // writes and delegate to the backing store, which is then up to date with respect to this cache.
+
+        let mut state = self.state.lock().await;
+
+        state.flush().await?;
+
+        self.inner.list_keys(prefix, cursor, limit).await
+    }
+
+    async fn increment(&self, key: &str, delta: i64) -> Result<i64, Error> {
+        // Atomic operations must see the backing store's current value, so we flush any outstanding writes and
+        // delegate synchronously, then update the cache with the result.
+
+        let mut state = self.state.lock().await;
+
+        state.flush().await?;
+
+        let value = self.inner.increment(key, delta).await?;
+
+        state
+            .cache
+            .put(key.to_owned(), Some(value.to_string().into_bytes()));
+
+        Ok(value)
+    }
+
+    async fn compare_and_swap(
+        &self,
+        key: &str,
+        expected: Option<&[u8]>,
+        value: &[u8],
+    ) -> Result<bool, Error> {
+        let mut state = self.state.lock().await;
+
+        state.flush().await?;
+
+        let swapped = self.inner.compare_and_swap(key, expected, value).await?;
+
+        if swapped {
+            state.cache.put(key.to_owned(), Some(value.to_owned()));
+        } else {
+            // Someone else changed the value behind our back, so whatever we have cached is stale.
+            state.cache.pop(key);
+        }
+
+        Ok(swapped)
+    }
+
+    async fn set_with_ttl(&self, key: &str, value: &[u8], ttl: Duration) -> Result<(), Error> {
+        // Rather than tracking expiry times in the cache, we evict the key and let the next read go to the backing
+        // store (which will flush this write first, preserving read-your-writes).
+
+        let mut state = self.state.lock().await;
+
+        state.cache.pop(key);
+
+        let inner = self.inner.clone();
+        let key = key.to_owned();
+        let value = value.to_owned();
+        state.spawn(async move { inner.set_with_ttl(&key, &value, ttl).await });
+
+        Ok(())
+    }
 }
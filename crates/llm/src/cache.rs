@@ -0,0 +1,87 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use bytesize::ByteSize;
+use llm::Model;
+use lru::LruCache;
+use spin_world::llm::{self as wasi_llm};
+
+/// Models loaded from the model registry, shared between inference calls.
+///
+/// Once the combined size of the loaded models' files exceeds the capacity,
+/// the least recently used models are evicted. Models are handed out as
+/// `Arc`s, so a model evicted while an inference is using it stays loaded
+/// until that inference finishes.
+pub(crate) struct ModelCache {
+    capacity: ByteSize,
+    models: Mutex<LruCache<PathBuf, CachedModel>>,
+    // Held while loading so that concurrent requests for a model which isn't
+    // loaded yet don't each load their own copy.
+    loading: tokio::sync::Mutex<()>,
+}
+
+struct CachedModel {
+    model: Arc<dyn Model>,
+    size: u64,
+}
+
+impl ModelCache {
+    pub fn new(capacity: ByteSize) -> Self {
+        Self {
+            capacity,
+            models: Mutex::new(LruCache::unbounded()),
+            loading: Default::default(),
+        }
+    }
+
+    /// Returns the model at `path`, calling `load` to load it on a blocking
+    /// thread if it isn't already loaded.
+    pub async fn get_or_load(
+        &self,
+        path: &Path,
+        load: impl FnOnce(&Path) -> Result<Box<dyn Model>, wasi_llm::Error> + Send + 'static,
+    ) -> Result<Arc<dyn Model>, wasi_llm::Error> {
+        if let Some(model) = self.get(path) {
+            return Ok(model);
+        }
+        let _loading = self.loading.lock().await;
+        // Another request may have loaded the model while we were waiting.
+        if let Some(model) = self.get(path) {
+            return Ok(model);
+        }
+
+        let owned_path = path.to_owned();
+        let model: Arc<dyn Model> = tokio::task::spawn_blocking(move || load(&owned_path))
+            .await
+            .map_err(|e| wasi_llm::Error::RuntimeError(format!("Failed to load model: {e}")))??
+            .into();
+        self.insert(path.to_owned(), model.clone());
+        Ok(model)
+    }
+
+    fn get(&self, path: &Path) -> Option<Arc<dyn Model>> {
+        let mut models = self.models.lock().unwrap();
+        // `LruCache` only supports lookups by the key type itself.
+        models
+            .get(&path.to_owned())
+            .map(|cached| cached.model.clone())
+    }
+
+    fn insert(&self, path: PathBuf, model: Arc<dyn Model>) {
+        let size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        let mut models = self.models.lock().unwrap();
+        models.put(path, CachedModel { model, size });
+
+        let mut total: u64 = models.iter().map(|(_, cached)| cached.size).sum();
+        // The model just loaded is always kept, even if it alone exceeds the capacity.
+        while total > self.capacity.as_u64() && models.len() > 1 {
+            let Some((path, evicted)) = models.pop_lru() else {
+                break;
+            };
+            tracing::debug!("Evicting model {path:?} from the model cache");
+            total -= evicted.size;
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Context};
+use candle_core::{DType, Device, Tensor};
+use candle_nn::VarBuilder;
+use candle_transformers::models::bert::{BertModel, Config};
+use tokenizers::{PaddingParams, PaddingStrategy, Tokenizer, TruncationParams};
+
+/// The input length used when the model config doesn't give one; this is the
+/// limit for the BERT models this is used with.
+const DEFAULT_MAX_LENGTH: usize = 512;
+
+/// A sentence embedding model run on the CPU.
+pub(crate) struct EmbeddingModel {
+    model: BertModel,
+    tokenizer: Tokenizer,
+}
+
+impl EmbeddingModel {
+    /// Loads a BERT sentence embedding model from a directory containing the
+    /// `config.json`, `tokenizer.json` and `model.safetensors` files published
+    /// with the model.
+    pub fn load(dir: &Path) -> anyhow::Result<Self> {
+        let config = std::fs::read(dir.join("config.json"))
+            .with_context(|| format!("Failed to read model config from {dir:?}"))?;
+        let tokenizer = Tokenizer::from_file(dir.join("tokenizer.json"))
+            .map_err(|e| anyhow!("Failed to load tokenizer from {dir:?}: {e}"))?;
+        let device = Device::Cpu;
+        let tensors = candle_core::safetensors::load(dir.join("model.safetensors"), &device)
+            .with_context(|| format!("Failed to load model weights from {dir:?}"))?;
+        Self::new(
+            &config,
+            tokenizer,
+            VarBuilder::from_tensors(tensors, DType::F32, &device),
+        )
+    }
+
+    /// Builds the model from the contents of its `config.json`, its tokenizer
+    /// and its weights.
+    fn new(config: &[u8], mut tokenizer: Tokenizer, weights: VarBuilder) -> anyhow::Result<Self> {
+        let max_length = serde_json::from_slice::<serde_json::Value>(config)
+            .context("Failed to parse model config")?["max_position_embeddings"]
+            .as_u64()
+            .map_or(DEFAULT_MAX_LENGTH, |n| n as usize);
+        let config: Config =
+            serde_json::from_slice(config).context("Failed to parse model config")?;
+
+        // Sentences are embedded as a batch, so pad them all to the same length.
+        tokenizer.with_padding(Some(PaddingParams {
+            strategy: PaddingStrategy::BatchLongest,
+            ..Default::default()
+        }));
+        // The model has no position embeddings past its maximum length, so
+        // longer sentences are embedded from their leading tokens only.
+        tokenizer
+            .with_truncation(Some(TruncationParams {
+                max_length,
+                ..Default::default()
+            }))
+            .map_err(|e| anyhow!("Failed to set tokenizer truncation: {e}"))?;
+
+        let model = BertModel::load(weights, &config).context("Failed to load model")?;
+
+        Ok(Self { model, tokenizer })
+    }
+
+    /// Returns one embedding for each sentence: the mean of the sentence's
+    /// token embeddings, normalized to unit length.
+    pub fn embed(&self, sentences: Vec<String>) -> anyhow::Result<Vec<Vec<f32>>> {
+        if sentences.is_empty() {
+            return Ok(vec![]);
+        }
+        let encodings = self
+            .tokenizer
+            .encode_batch(sentences, true)
+            .map_err(|e| anyhow!("Failed to tokenize input: {e}"))?;
+
+        let device = Device::Cpu;
+        let token_ids = encodings
+            .iter()
+            .map(|encoding| Tensor::new(encoding.get_ids(), &device))
+            .collect::<Result<Vec<_>, _>>()?;
+        let token_ids = Tensor::stack(&token_ids, 0)?;
+        let attention_mask = encodings
+            .iter()
+            .map(|encoding| Tensor::new(encoding.get_attention_mask(), &device))
+            .collect::<Result<Vec<_>, _>>()?;
+        let attention_mask = Tensor::stack(&attention_mask, 0)?.to_dtype(DType::F32)?;
+
+        let embeddings = self.model.forward(&token_ids, &token_ids.zeros_like()?)?;
+
+        // Average over each sentence's tokens, leaving out the padding.
+        let embeddings = embeddings
+            .broadcast_mul(&attention_mask.unsqueeze(2)?)?
+            .sum(1)?
+            .broadcast_div(&attention_mask.sum_keepdim(1)?)?;
+        let embeddings = embeddings.broadcast_div(&embeddings.sqr()?.sum_keepdim(1)?.sqrt()?)?;
+
+        Ok(embeddings.to_vec2()?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use candle_nn::VarMap;
+    use tokenizers::{
+        models::{wordlevel::WordLevel, ModelWrapper},
+        pre_tokenizers::{whitespace::Whitespace, PreTokenizerWrapper},
+    };
+
+    use super::*;
+
+    const HIDDEN_SIZE: usize = 4;
+    const MAX_LENGTH: usize = 8;
+
+    // A model small enough to build in memory; its weights are randomly
+    // initialized, so only the shape of its output is meaningful.
+    fn tiny_model() -> EmbeddingModel {
+        let config = serde_json::json!({
+            "vocab_size": 3,
+            "hidden_size": HIDDEN_SIZE,
+            "num_hidden_layers": 1,
+            "num_attention_heads": 1,
+            "intermediate_size": 8,
+            "hidden_act": "gelu",
+            "hidden_dropout_prob": 0.0,
+            "max_position_embeddings": MAX_LENGTH,
+            "type_vocab_size": 2,
+            "initializer_range": 0.02,
+            "layer_norm_eps": 1e-12,
+            "pad_token_id": 1,
+        });
+        let vocab = [("[UNK]", 0), ("[PAD]", 1), ("word", 2)]
+            .into_iter()
+            .map(|(token, id)| (token.to_owned(), id))
+            .collect();
+        let model = WordLevel::builder()
+            .vocab(vocab)
+            .unk_token("[UNK]".to_owned())
+            .build()
+            .unwrap();
+        let mut tokenizer = Tokenizer::new(ModelWrapper::from(model));
+        tokenizer.with_pre_tokenizer(PreTokenizerWrapper::from(Whitespace::default()));
+        EmbeddingModel::new(
+            &serde_json::to_vec(&config).unwrap(),
+            tokenizer,
+            VarBuilder::from_varmap(&VarMap::new(), DType::F32, &Device::Cpu),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn no_sentences_give_no_embeddings() {
+        let model = tiny_model();
+        assert!(model.embed(vec![]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn each_sentence_gets_an_embedding() {
+        let model = tiny_model();
+        let embeddings = model
+            .embed(vec!["word".to_owned(), "word word word".to_owned()])
+            .unwrap();
+        assert_eq!(2, embeddings.len());
+        assert!(embeddings.iter().all(|e| e.len() == HIDDEN_SIZE));
+    }
+
+    #[test]
+    fn sentences_longer_than_the_model_accepts_are_truncated() {
+        let model = tiny_model();
+        let embeddings = model.embed(vec!["word ".repeat(20)]).unwrap();
+        assert_eq!(1, embeddings.len());
+        assert_eq!(
+            MAX_LENGTH,
+            model.tokenizer.get_truncation().unwrap().max_length
+        );
+    }
+}
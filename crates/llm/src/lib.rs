@@ -1,24 +1,71 @@
-use llm::{
-    InferenceFeedback, InferenceParameters, InferenceResponse, InferenceSessionConfig, Model,
-    ModelArchitecture, ModelKVMemoryType, ModelParameters,
-};
-use rand::SeedableRng;
-use spin_core::{async_trait, HostComponent};
+mod cache;
+mod embeddings;
+mod local;
+mod queue;
+mod remote_http;
+
+pub use local::{InferenceLimits, LLmOptions, LocalBackend, RegistryModel};
+pub use remote_http::{RemoteHttpBackend, RemoteHttpOptions};
+
+use spin_app::{AppComponent, DynamicHostComponent, MetadataKey};
+use spin_core::{async_trait, table::Table, HostComponent};
 use spin_world::llm::{self as wasi_llm};
-use std::{
-    convert::Infallible,
-    path::PathBuf,
-    sync::{Arc, Mutex},
-};
+use std::{collections::HashSet, sync::Arc};
+use tokio::{sync::mpsc, task::JoinHandle};
+
+/// MetadataKey for the AI models a component is allowed to use.
+pub const AI_MODELS_KEY: MetadataKey<Vec<String>> = MetadataKey::new("ai_models");
+
+/// The name of the `all-mini-lm-l6-v2` embedding model, which is also the
+/// directory in the model registry containing its files.
+const EMBEDDING_MODEL_NAME: &str = "all-minilm-l6-v2";
+
+/// The maximum number of inferencing streams an instance may have open at once.
+const MAX_OPEN_STREAMS: u32 = 16;
+
+/// Runs inference and generates embeddings for the LLM component.
+#[async_trait]
+pub trait LlmBackend: Send + Sync {
+    /// Runs inference on the prompt with the given model.
+    async fn infer(
+        &self,
+        model: wasi_llm::InferencingModel,
+        prompt: String,
+        options: InferenceOptions,
+    ) -> Result<wasi_llm::InferencingResult, wasi_llm::Error>;
+
+    /// Runs inference on the prompt with the given model, sending the text to
+    /// `tokens` as it is generated. Inference may stop early once the
+    /// receiver has been dropped.
+    ///
+    /// The default implementation sends the whole text once inference is done.
+    async fn infer_stream(
+        &self,
+        model: wasi_llm::InferencingModel,
+        prompt: String,
+        options: InferenceOptions,
+        tokens: mpsc::UnboundedSender<String>,
+    ) -> Result<(), wasi_llm::Error> {
+        let result = self.infer(model, prompt, options).await?;
+        // The receiver no longer wanting the text isn't an error.
+        let _ = tokens.send(result.text);
+        Ok(())
+    }
+
+    /// Returns one embedding for each string in `data`.
+    async fn generate_embeddings(
+        &self,
+        model: wasi_llm::EmbeddingModel,
+        data: Vec<String>,
+    ) -> Result<Vec<Vec<f32>>, wasi_llm::Error>;
 
-#[derive(Default)]
-pub struct LLmOptions {
-    pub model_registry: PathBuf,
-    pub use_gpu: bool,
+    /// Checks that the model with the given name can be used, so that apps
+    /// using missing models fail to start rather than failing at runtime.
+    fn check_model_available(&self, name: &str) -> anyhow::Result<()>;
 }
 
 pub struct LlmComponent {
-    engine: LlmEngine,
+    backend: Arc<dyn LlmBackend>,
 }
 
 impl HostComponent for LlmComponent {
@@ -32,107 +79,115 @@ impl HostComponent for LlmComponent {
     }
 
     fn build_data(&self) -> Self::Data {
-        self.engine.clone()
+        LlmEngine {
+            backend: self.backend.clone(),
+            allowed_models: Default::default(),
+            streams: Table::new(MAX_OPEN_STREAMS),
+        }
     }
 }
 
-impl LlmComponent {
-    pub fn new(registry: PathBuf, use_gpu: bool) -> Self {
-        Self {
-            engine: LlmEngine::new(registry, use_gpu),
+impl DynamicHostComponent for LlmComponent {
+    fn update_data(&self, data: &mut Self::Data, component: &AppComponent) -> anyhow::Result<()> {
+        data.allowed_models = component
+            .get_metadata(AI_MODELS_KEY)?
+            .unwrap_or_default()
+            .into_iter()
+            .collect();
+        Ok(())
+    }
+
+    fn validate_app(&self, app: &spin_app::App) -> anyhow::Result<()> {
+        let mut errors = vec![];
+
+        for component in app.components() {
+            for model in component.get_metadata(AI_MODELS_KEY)?.unwrap_or_default() {
+                if let Err(e) = self.backend.check_model_available(&model) {
+                    errors.push(format!(
+                        "- Component {} uses model '{model}': {e}",
+                        component.id()
+                    ));
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            let prologue = vec![
+                "One or more components use AI models which are not available.",
+                "Check the spelling, or add the models to the model registry.",
+                "Details:",
+            ];
+            let lines: Vec<_> = prologue
+                .into_iter()
+                .map(|s| s.to_owned())
+                .chain(errors)
+                .collect();
+            Err(anyhow::anyhow!(lines.join("\n")))
         }
     }
 }
 
-#[derive(Clone)]
+impl LlmComponent {
+    pub fn new(backend: Arc<dyn LlmBackend>) -> Self {
+        Self { backend }
+    }
+}
+
 pub struct LlmEngine {
-    pub registry: PathBuf,
-    pub use_gpu: bool,
+    backend: Arc<dyn LlmBackend>,
+    allowed_models: HashSet<String>,
+    streams: Table<InferenceStream>,
 }
 
 impl LlmEngine {
-    pub fn new(registry: PathBuf, use_gpu: bool) -> Self {
-        Self { registry, use_gpu }
+    /// Checks that the component may use the model and validates the params.
+    fn check_inference(
+        &self,
+        model: &wasi_llm::InferencingModel,
+        params: Option<wasi_llm::InferencingParams>,
+    ) -> Result<InferenceOptions, wasi_llm::Error> {
+        if !self.allowed_models.contains(model_name(model)) {
+            return Err(access_denied_error(model_name(model)));
+        }
+        InferenceOptions::from_params(params)
     }
+}
 
-    async fn run(
-        &mut self,
-        model: wasi_llm::InferencingModel,
-        prompt: String,
-    ) -> Result<String, wasi_llm::Error> {
-        let params = ModelParameters {
-            prefer_mmap: true,
-            context_size: 2048,
-            lora_adapters: None,
-            use_gpu: self.use_gpu,
-            gpu_layers: None,
-            rope_overrides: None,
-            n_gqa: None,
-        };
-
-        let progress_fn = |_| {};
-
-        let model = llm::load_dynamic(
-            Some(model_arch(&model)?),
-            &self.registry.join(&model_name(model)?),
-            llm::TokenizerSource::Embedded,
-            params,
-            progress_fn,
-        )
-        .map_err(|e| {
-            wasi_llm::Error::RuntimeError(format!("Failed to load model from model registry: {e}"))
-        })?;
-        let cfg = InferenceSessionConfig {
-            memory_k_type: ModelKVMemoryType::Float16,
-            memory_v_type: ModelKVMemoryType::Float16,
-            n_batch: 8,
-            n_threads: num_cpus::get(),
-        };
+/// An inference running in the background, whose text is read by the guest
+/// as it is generated.
+struct InferenceStream {
+    tokens: mpsc::UnboundedReceiver<String>,
+    // Taken once all text has been read, to report how inference ended.
+    task: Option<JoinHandle<Result<(), wasi_llm::Error>>>,
+}
 
-        let mut session = Model::start_session(model.as_ref(), cfg);
-        let params = InferenceParameters {
-            sampler: generate_sampler(),
-        };
-        let mut rng = rand::rngs::StdRng::from_entropy();
-        let mut response = String::new();
-
-        #[cfg(debug_assertions)]
-        {
-            terminal::warn!(
-                "\
-                This is a debug build - running inference might be prohibitively slow\n\
-                You may want to consider switching to the release build"
-            )
-        }
-        let res = session.infer::<Infallible>(
-            model.as_ref(),
-            &mut rng,
-            &llm::InferenceRequest {
-                prompt: prompt.as_str().into(),
-                parameters: &params,
-                play_back_previous_tokens: false,
-                maximum_token_count: Some(75),
-            },
-            &mut Default::default(),
-            |r| {
-                if let InferenceResponse::InferredToken(t) = r {
-                    response.push_str(&t);
-                }
-                Ok(InferenceFeedback::Continue)
-            },
-        );
-        let _ = res.map_err(|e| {
-            wasi_llm::Error::RuntimeError(format!("Failure ocurred during inferencing: {e}"))
-        })?;
-        Ok(response)
+impl InferenceStream {
+    async fn read(&mut self) -> Result<Option<String>, wasi_llm::Error> {
+        if let Some(mut text) = self.tokens.recv().await {
+            // Return everything generated since the last read rather than
+            // making the guest read one token at a time.
+            while let Ok(token) = self.tokens.try_recv() {
+                text.push_str(&token);
+            }
+            return Ok(Some(text));
+        }
+        // All senders are gone, so inference has finished.
+        if let Some(task) = self.task.take() {
+            task.await.map_err(|e| {
+                wasi_llm::Error::RuntimeError(format!("Inferencing task failed: {e}"))
+            })??;
+        }
+        Ok(None)
     }
+}
 
-    async fn generate_embeddings(
-        &mut self,
-        _model: wasi_llm::EmbeddingModel,
-        _data: Vec<String>,
-    ) -> Result<Vec<Vec<f32>>, wasi_llm::Error> {
-        Err(wasi_llm::Error::ModelNotSupported)
+impl Drop for InferenceStream {
+    fn drop(&mut self) {
+        if let Some(task) = &self.task {
+            task.abort();
+        }
     }
 }
 
@@ -142,10 +197,54 @@ impl wasi_llm::Host for LlmEngine {
         &mut self,
         m: wasi_llm::InferencingModel,
         p: String,
-        _params: Option<wasi_llm::InferencingParams>,
+        params: Option<wasi_llm::InferencingParams>,
     ) -> anyhow::Result<Result<wasi_llm::InferencingResult, wasi_llm::Error>> {
-        let res = self.run(m, p).await.unwrap();
-        Ok(Ok(res))
+        let options = match self.check_inference(&m, params) {
+            Ok(options) => options,
+            Err(e) => return Ok(Err(e)),
+        };
+        Ok(self.backend.infer(m, p, options).await)
+    }
+
+    async fn infer_stream(
+        &mut self,
+        m: wasi_llm::InferencingModel,
+        p: String,
+        params: Option<wasi_llm::InferencingParams>,
+    ) -> anyhow::Result<Result<wasi_llm::InferencingStream, wasi_llm::Error>> {
+        let options = match self.check_inference(&m, params) {
+            Ok(options) => options,
+            Err(e) => return Ok(Err(e)),
+        };
+        let (sender, tokens) = mpsc::unbounded_channel();
+        let backend = self.backend.clone();
+        let task = tokio::spawn(async move { backend.infer_stream(m, p, options, sender).await });
+        Ok(self
+            .streams
+            .push(InferenceStream {
+                tokens,
+                task: Some(task),
+            })
+            .map_err(|()| {
+                wasi_llm::Error::RuntimeError("Too many inferencing streams are open".into())
+            }))
+    }
+
+    async fn read_stream(
+        &mut self,
+        stream: wasi_llm::InferencingStream,
+    ) -> anyhow::Result<Result<Option<String>, wasi_llm::Error>> {
+        let Some(stream) = self.streams.get_mut(stream) else {
+            return Ok(Err(wasi_llm::Error::InvalidInput(
+                "Invalid inferencing stream handle".into(),
+            )));
+        };
+        Ok(stream.read().await)
+    }
+
+    async fn close_stream(&mut self, stream: wasi_llm::InferencingStream) -> anyhow::Result<()> {
+        self.streams.remove(stream);
+        Ok(())
     }
 
     async fn generate_embeddings(
@@ -153,77 +252,298 @@ impl wasi_llm::Host for LlmEngine {
         m: wasi_llm::EmbeddingModel,
         data: Vec<String>,
     ) -> anyhow::Result<Result<Vec<Vec<f32>>, wasi_llm::Error>> {
-        Ok(self.generate_embeddings(m, data).await)
+        if !self.allowed_models.contains(embedding_model_name(&m)) {
+            return Ok(Err(access_denied_error(embedding_model_name(&m))));
+        }
+        Ok(self.backend.generate_embeddings(m, data).await)
     }
 }
 
-fn model_name(model: wasi_llm::InferencingModel) -> Result<&'static str, wasi_llm::Error> {
+fn access_denied_error(model: &str) -> wasi_llm::Error {
+    wasi_llm::Error::AccessDenied(format!(
+        "The component does not have access to use '{model}'. To give the component access, add '{model}' to the 'ai_models' key for the component in your spin.toml manifest"
+    ))
+}
+
+fn model_name(model: &wasi_llm::InferencingModel) -> &str {
     match model {
-        wasi_llm::InferencingModel::Llama2V13bChat => Ok("llama2-13b-chat"),
-        _ => Err(wasi_llm::Error::ModelNotSupported),
+        wasi_llm::InferencingModel::Llama2V70bChat => "llama2-70b-chat",
+        wasi_llm::InferencingModel::Llama2V13bChat => "llama2-13b-chat",
+        wasi_llm::InferencingModel::Llama2V7bChat => "llama2-7b-chat",
+        wasi_llm::InferencingModel::Other(name) => name,
     }
 }
 
-fn model_arch(model: &wasi_llm::InferencingModel) -> Result<ModelArchitecture, wasi_llm::Error> {
+fn embedding_model_name(model: &wasi_llm::EmbeddingModel) -> &str {
     match model {
-        wasi_llm::InferencingModel::Llama2V70bChat
-        | wasi_llm::InferencingModel::Llama2V13bChat
-        | wasi_llm::InferencingModel::Llama2V7bChat => Ok(ModelArchitecture::Llama),
-        wasi_llm::InferencingModel::Other(_) => Err(wasi_llm::Error::ModelNotSupported),
-    }
-}
-
-// Sampling options for picking the next token in the sequence.
-// We start with a default sampler, then add the inference parameters supplied by the request.
-fn generate_sampler(
-) -> Arc<Mutex<dyn llm::samplers::llm_samplers::types::Sampler<llm::TokenId, f32>>> {
-    let mut result = llm::samplers::ConfiguredSamplers {
-        // We are *not* using the default implementation for ConfiguredSamplers here
-        // because the builder already sets values for parameters, which we cannot replace.
-        builder: llm::samplers::llm_samplers::configure::SamplerChainBuilder::default(),
-        ..Default::default()
-    };
-
-    result.builder += (
-        "temperature".into(),
-        llm::samplers::llm_samplers::configure::SamplerSlot::new_single(
-            move || {
-                Box::new(
-                    llm::samplers::llm_samplers::samplers::SampleTemperature::default()
-                        .temperature(0.8),
-                )
+        wasi_llm::EmbeddingModel::AllMiniLmL6V2 => EMBEDDING_MODEL_NAME,
+        wasi_llm::EmbeddingModel::Other(name) => name,
+    }
+}
+
+/// The options for a single inference, validated from the guest's
+/// `inferencing-params`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct InferenceOptions {
+    /// The maximum number of tokens to generate.
+    pub max_tokens: usize,
+    pub temperature: f32,
+    /// The number of most likely tokens to sample the next token from.
+    pub top_k: usize,
+    /// The cumulative probability of the most likely tokens to sample the
+    /// next token from.
+    pub top_p: f32,
+    pub repeat_penalty: f32,
+    /// The number of preceding tokens to which the repeat penalty applies.
+    pub repeat_penalty_last_n_token_count: usize,
+}
+
+impl Default for InferenceOptions {
+    fn default() -> Self {
+        Self {
+            max_tokens: 75,
+            temperature: 0.8,
+            top_k: 40,
+            top_p: 0.9,
+            repeat_penalty: 1.1,
+            repeat_penalty_last_n_token_count: 64,
+        }
+    }
+}
+
+impl InferenceOptions {
+    fn from_params(params: Option<wasi_llm::InferencingParams>) -> Result<Self, wasi_llm::Error> {
+        let Some(params) = params else {
+            return Ok(Self::default());
+        };
+        let invalid = |message: &str| Err(wasi_llm::Error::InvalidInput(message.to_owned()));
+        if params.max_tokens == 0 {
+            return invalid("max-tokens must be greater than 0");
+        }
+        if !(params.temperature.is_finite() && params.temperature > 0.0) {
+            return invalid("temperature must be greater than 0");
+        }
+        if params.top_k == 0 {
+            return invalid("top-k must be greater than 0");
+        }
+        if !(params.top_p > 0.0 && params.top_p <= 1.0) {
+            return invalid("top-p must be greater than 0 and at most 1");
+        }
+        if !(params.repeat_penalty.is_finite() && params.repeat_penalty > 0.0) {
+            return invalid("repeat-penalty must be greater than 0");
+        }
+        Ok(Self {
+            max_tokens: params.max_tokens as usize,
+            temperature: params.temperature,
+            top_k: params.top_k as usize,
+            top_p: params.top_p,
+            repeat_penalty: params.repeat_penalty,
+            repeat_penalty_last_n_token_count: params.repeat_penalty_last_n_token_count as usize,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn params() -> wasi_llm::InferencingParams {
+        wasi_llm::InferencingParams {
+            max_tokens: 200,
+            repeat_penalty: 1.3,
+            repeat_penalty_last_n_token_count: 32,
+            temperature: 0.5,
+            top_k: 20,
+            top_p: 0.75,
+        }
+    }
+
+    #[test]
+    fn missing_params_use_defaults() {
+        assert_eq!(
+            InferenceOptions::default(),
+            InferenceOptions::from_params(None).unwrap()
+        );
+    }
+
+    #[test]
+    fn params_are_mapped_to_options() {
+        assert_eq!(
+            InferenceOptions {
+                max_tokens: 200,
+                temperature: 0.5,
+                top_k: 20,
+                top_p: 0.75,
+                repeat_penalty: 1.3,
+                repeat_penalty_last_n_token_count: 32,
+            },
+            InferenceOptions::from_params(Some(params())).unwrap()
+        );
+    }
+
+    #[test]
+    fn invalid_params_are_rejected() {
+        let invalid = [
+            wasi_llm::InferencingParams {
+                max_tokens: 0,
+                ..params()
+            },
+            wasi_llm::InferencingParams {
+                temperature: 0.0,
+                ..params()
             },
-            Option::<llm::samplers::llm_samplers::samplers::SampleTemperature>::None,
-        ),
-    );
-    result.builder += (
-        "topp".into(),
-        llm::samplers::llm_samplers::configure::SamplerSlot::new_single(
-            move || Box::new(llm::samplers::llm_samplers::samplers::SampleTopP::default().p(0.9)),
-            Option::<llm::samplers::llm_samplers::samplers::SampleTopP>::None,
-        ),
-    );
-    result.builder += (
-        "topk".into(),
-        llm::samplers::llm_samplers::configure::SamplerSlot::new_single(
-            move || Box::new(llm::samplers::llm_samplers::samplers::SampleTopK::default().k(40)),
-            Option::<llm::samplers::llm_samplers::samplers::SampleTopK>::None,
-        ),
-    );
-    result.builder += (
-        "repetition".into(),
-        llm::samplers::llm_samplers::configure::SamplerSlot::new_chain(
-            move || {
-                Box::new(
-                    llm::samplers::llm_samplers::samplers::SampleRepetition::default()
-                        .penalty(1.1)
-                        .last_n(64),
-                )
+            wasi_llm::InferencingParams {
+                temperature: f32::NAN,
+                ..params()
             },
-            [],
-        ),
-    );
+            wasi_llm::InferencingParams {
+                top_k: 0,
+                ..params()
+            },
+            wasi_llm::InferencingParams {
+                top_p: 1.5,
+                ..params()
+            },
+            wasi_llm::InferencingParams {
+                repeat_penalty: -1.0,
+                ..params()
+            },
+        ];
+        for params in invalid {
+            assert!(
+                matches!(
+                    InferenceOptions::from_params(Some(params)),
+                    Err(wasi_llm::Error::InvalidInput(_))
+                ),
+                "{params:?} should be rejected"
+            );
+        }
+    }
+
+    struct StubBackend;
+
+    #[async_trait]
+    impl LlmBackend for StubBackend {
+        async fn infer(
+            &self,
+            _model: wasi_llm::InferencingModel,
+            _prompt: String,
+            _options: InferenceOptions,
+        ) -> Result<wasi_llm::InferencingResult, wasi_llm::Error> {
+            Ok(wasi_llm::InferencingResult {
+                text: "Hello world".into(),
+                usage: wasi_llm::InferencingUsage {
+                    prompt_token_count: 1,
+                    generated_token_count: 2,
+                },
+            })
+        }
+
+        async fn infer_stream(
+            &self,
+            _model: wasi_llm::InferencingModel,
+            prompt: String,
+            _options: InferenceOptions,
+            tokens: mpsc::UnboundedSender<String>,
+        ) -> Result<(), wasi_llm::Error> {
+            for token in prompt.split_inclusive(' ') {
+                tokens.send(token.to_owned()).unwrap();
+            }
+            Err(wasi_llm::Error::RuntimeError("ran out of tokens".into()))
+        }
+
+        async fn generate_embeddings(
+            &self,
+            _model: wasi_llm::EmbeddingModel,
+            _data: Vec<String>,
+        ) -> Result<Vec<Vec<f32>>, wasi_llm::Error> {
+            Ok(vec![])
+        }
+
+        fn check_model_available(&self, _name: &str) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn engine() -> LlmEngine {
+        let mut engine = LlmComponent::new(Arc::new(StubBackend)).build_data();
+        engine.allowed_models = ["llama2-7b-chat".to_owned()].into();
+        engine
+    }
 
-    result.ensure_default_slots();
-    Arc::new(Mutex::new(result.builder.into_chain()))
+    #[tokio::test]
+    async fn stream_returns_text_then_inference_result() -> anyhow::Result<()> {
+        let mut engine = engine();
+        let stream = wasi_llm::Host::infer_stream(
+            &mut engine,
+            wasi_llm::InferencingModel::Llama2V7bChat,
+            "Hello streaming world".into(),
+            None,
+        )
+        .await?
+        .unwrap();
+
+        let mut text = String::new();
+        let error = loop {
+            match wasi_llm::Host::read_stream(&mut engine, stream).await? {
+                Ok(Some(chunk)) => text.push_str(&chunk),
+                Ok(None) => panic!("stream should end with the inferencing error"),
+                Err(e) => break e,
+            }
+        };
+        assert_eq!("Hello streaming world", text);
+        assert!(
+            matches!(&error, wasi_llm::Error::RuntimeError(message) if message == "ran out of tokens")
+        );
+        // Once inference has finished, the stream just reports that it's done.
+        assert_eq!(
+            None,
+            wasi_llm::Host::read_stream(&mut engine, stream)
+                .await?
+                .unwrap()
+        );
+
+        wasi_llm::Host::close_stream(&mut engine, stream).await?;
+        assert!(matches!(
+            wasi_llm::Host::read_stream(&mut engine, stream).await?,
+            Err(wasi_llm::Error::InvalidInput(_))
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn stream_requires_model_access() -> anyhow::Result<()> {
+        let result = wasi_llm::Host::infer_stream(
+            &mut engine(),
+            wasi_llm::InferencingModel::Llama2V13bChat,
+            "Hello".into(),
+            None,
+        )
+        .await?;
+        assert!(matches!(result, Err(wasi_llm::Error::AccessDenied(_))));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn undeclared_models_are_denied() -> anyhow::Result<()> {
+        let result = wasi_llm::Host::infer(
+            &mut engine(),
+            wasi_llm::InferencingModel::Llama2V13bChat,
+            "Hello".into(),
+            None,
+        )
+        .await?;
+        assert!(
+            matches!(result, Err(wasi_llm::Error::AccessDenied(message)) if message.contains("llama2-13b-chat"))
+        );
+
+        let result = wasi_llm::Host::generate_embeddings(
+            &mut engine(),
+            wasi_llm::EmbeddingModel::AllMiniLmL6V2,
+            vec!["Hello".into()],
+        )
+        .await?;
+        assert!(matches!(result, Err(wasi_llm::Error::AccessDenied(_))));
+        Ok(())
+    }
 }
@@ -0,0 +1,385 @@
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use bytesize::ByteSize;
+use llm::{
+    InferenceFeedback, InferenceParameters, InferenceResponse, InferenceSessionConfig, Model,
+    ModelArchitecture, ModelKVMemoryType, ModelParameters,
+};
+use rand::SeedableRng;
+use spin_core::async_trait;
+use spin_world::llm::{self as wasi_llm};
+use tokio::sync::mpsc;
+
+use crate::{
+    cache::ModelCache, embeddings::EmbeddingModel, model_name, queue::InferenceQueue,
+    InferenceOptions, LlmBackend, EMBEDDING_MODEL_NAME,
+};
+
+#[derive(Default)]
+pub struct LLmOptions {
+    pub model_registry: PathBuf,
+    pub use_gpu: bool,
+    /// The maximum combined size of the models kept loaded between inference calls.
+    pub model_cache_capacity: ByteSize,
+    /// Models in the registry, by the name guests use for them. The Llama 2
+    /// chat models need not be listed if their files are named after them,
+    /// e.g. `llama2-13b-chat`.
+    pub models: HashMap<String, RegistryModel>,
+    pub limits: InferenceLimits,
+}
+
+/// Limits on the inferences run at once.
+#[derive(Clone, Copy, Debug)]
+pub struct InferenceLimits {
+    /// The number of inferences which may run at once.
+    pub parallelism: usize,
+    /// The number of threads shared between the running inferences.
+    pub threads: usize,
+    /// The number of inferences which may wait for a turn to run. Any more
+    /// are rejected with `too-many-requests`.
+    pub queue_size: usize,
+}
+
+impl Default for InferenceLimits {
+    fn default() -> Self {
+        Self {
+            parallelism: 1,
+            threads: num_cpus::get(),
+            queue_size: 16,
+        }
+    }
+}
+
+/// A model file in the model registry.
+#[derive(Clone, Debug)]
+pub struct RegistryModel {
+    /// The path of the model file, relative to the registry.
+    pub file: PathBuf,
+    /// The model's architecture, e.g. `llama`.
+    pub architecture: String,
+}
+
+/// Runs models from a local model registry in-process.
+pub struct LocalBackend {
+    registry: PathBuf,
+    use_gpu: bool,
+    registry_models: HashMap<String, (PathBuf, ModelArchitecture)>,
+    model_cache: ModelCache,
+    embedding_model: tokio::sync::OnceCell<Arc<EmbeddingModel>>,
+    queue: InferenceQueue,
+    threads_per_inference: usize,
+}
+
+impl LocalBackend {
+    /// Creates the backend, checking that every model listed in `options`
+    /// exists in the registry.
+    pub fn new(options: LLmOptions) -> anyhow::Result<Self> {
+        let limits = options.limits;
+        anyhow::ensure!(
+            limits.parallelism > 0,
+            "Inference parallelism must be greater than 0"
+        );
+        anyhow::ensure!(
+            limits.threads >= limits.parallelism,
+            "Inference threads ({}) must be at least the inference parallelism ({})",
+            limits.threads,
+            limits.parallelism
+        );
+
+        let mut registry_models = HashMap::new();
+        for (name, model) in options.models {
+            let architecture = model.architecture.parse().map_err(|_| {
+                anyhow::anyhow!(
+                    "Model '{name}' has unsupported architecture '{}'",
+                    model.architecture
+                )
+            })?;
+            let path = options.model_registry.join(&model.file);
+            anyhow::ensure!(
+                path.is_file(),
+                "Model '{name}' was not found in the model registry: {path:?} does not exist"
+            );
+            registry_models.insert(name, (path, architecture));
+        }
+
+        Ok(Self {
+            registry: options.model_registry,
+            use_gpu: options.use_gpu,
+            registry_models,
+            model_cache: ModelCache::new(options.model_cache_capacity),
+            embedding_model: Default::default(),
+            queue: InferenceQueue::new(limits.parallelism, limits.queue_size),
+            threads_per_inference: limits.threads / limits.parallelism,
+        })
+    }
+
+    /// Returns the path and architecture of a model in the registry.
+    fn registry_model(
+        &self,
+        model: &wasi_llm::InferencingModel,
+    ) -> Result<(PathBuf, ModelArchitecture), wasi_llm::Error> {
+        let name = model_name(model);
+        if let Some((path, architecture)) = self.registry_models.get(name) {
+            return Ok((path.clone(), *architecture));
+        }
+        match model {
+            // The architecture of other models can't be known unless configured.
+            wasi_llm::InferencingModel::Other(_) => Err(wasi_llm::Error::ModelNotSupported),
+            _ => Ok((self.registry.join(name), ModelArchitecture::Llama)),
+        }
+    }
+
+    /// Runs inference on a blocking thread once the queue allows, passing
+    /// each token to `on_token` as it is generated. Inference stops early if
+    /// `on_token` returns false.
+    async fn run(
+        &self,
+        model: wasi_llm::InferencingModel,
+        prompt: String,
+        options: InferenceOptions,
+        mut on_token: impl FnMut(&str) -> bool + Send + 'static,
+    ) -> Result<wasi_llm::InferencingUsage, wasi_llm::Error> {
+        let turn = self.queue.enter().await?;
+        let params = ModelParameters {
+            prefer_mmap: true,
+            context_size: 2048,
+            lora_adapters: None,
+            use_gpu: self.use_gpu,
+            gpu_layers: None,
+            rope_overrides: None,
+            n_gqa: None,
+        };
+
+        let (path, arch) = self.registry_model(&model)?;
+        let model = self
+            .model_cache
+            .get_or_load(&path, move |path| {
+                let progress_fn = |_| {};
+                llm::load_dynamic(
+                    Some(arch),
+                    path,
+                    llm::TokenizerSource::Embedded,
+                    params,
+                    progress_fn,
+                )
+                .map_err(|e| {
+                    wasi_llm::Error::RuntimeError(format!(
+                        "Failed to load model from model registry: {e}"
+                    ))
+                })
+            })
+            .await?;
+        let cfg = InferenceSessionConfig {
+            memory_k_type: ModelKVMemoryType::Float16,
+            memory_v_type: ModelKVMemoryType::Float16,
+            n_batch: 8,
+            n_threads: self.threads_per_inference,
+        };
+
+        #[cfg(debug_assertions)]
+        {
+            terminal::warn!(
+                "\
+                This is a debug build - running inference might be prohibitively slow\n\
+                You may want to consider switching to the release build"
+            )
+        }
+        tokio::task::spawn_blocking(move || {
+            // Keep the turn until inference finishes, even if the caller
+            // stops waiting for it.
+            let _turn = turn;
+            let mut session = Model::start_session(model.as_ref(), cfg);
+            let params = InferenceParameters {
+                sampler: generate_sampler(&options),
+            };
+            let mut rng = rand::rngs::StdRng::from_entropy();
+            let res = session.infer::<Infallible>(
+                model.as_ref(),
+                &mut rng,
+                &llm::InferenceRequest {
+                    prompt: prompt.as_str().into(),
+                    parameters: &params,
+                    play_back_previous_tokens: false,
+                    maximum_token_count: Some(options.max_tokens),
+                },
+                &mut Default::default(),
+                |r| {
+                    if let InferenceResponse::InferredToken(t) = r {
+                        if !on_token(&t) {
+                            return Ok(InferenceFeedback::Halt);
+                        }
+                    }
+                    Ok(InferenceFeedback::Continue)
+                },
+            );
+            let stats = res.map_err(|e| {
+                wasi_llm::Error::RuntimeError(format!("Failure ocurred during inferencing: {e}"))
+            })?;
+            Ok(wasi_llm::InferencingUsage {
+                prompt_token_count: stats.prompt_tokens as u32,
+                generated_token_count: stats.predict_tokens as u32,
+            })
+        })
+        .await
+        .map_err(|e| wasi_llm::Error::RuntimeError(format!("Inferencing task failed: {e}")))?
+    }
+
+    async fn embedding_model(&self) -> Result<Arc<EmbeddingModel>, wasi_llm::Error> {
+        let dir = self.registry.join(EMBEDDING_MODEL_NAME);
+        self.embedding_model
+            .get_or_try_init(|| async move {
+                let model = tokio::task::spawn_blocking(move || EmbeddingModel::load(&dir))
+                    .await
+                    .map_err(|e| {
+                        wasi_llm::Error::RuntimeError(format!("Failed to load model: {e}"))
+                    })?
+                    .map_err(|e| {
+                        wasi_llm::Error::RuntimeError(format!(
+                            "Failed to load model from model registry: {e:#}"
+                        ))
+                    })?;
+                Ok(Arc::new(model))
+            })
+            .await
+            .cloned()
+    }
+}
+
+#[async_trait]
+impl LlmBackend for LocalBackend {
+    async fn infer(
+        &self,
+        model: wasi_llm::InferencingModel,
+        prompt: String,
+        options: InferenceOptions,
+    ) -> Result<wasi_llm::InferencingResult, wasi_llm::Error> {
+        let text = Arc::new(Mutex::new(String::new()));
+        let collected = text.clone();
+        let usage = self
+            .run(model, prompt, options, move |token| {
+                collected.lock().unwrap().push_str(token);
+                true
+            })
+            .await?;
+        let text = std::mem::take(&mut *text.lock().unwrap());
+        Ok(wasi_llm::InferencingResult { text, usage })
+    }
+
+    async fn infer_stream(
+        &self,
+        model: wasi_llm::InferencingModel,
+        prompt: String,
+        options: InferenceOptions,
+        tokens: mpsc::UnboundedSender<String>,
+    ) -> Result<(), wasi_llm::Error> {
+        // Sending fails once the stream is closed, which stops inference.
+        self.run(model, prompt, options, move |token| {
+            tokens.send(token.to_owned()).is_ok()
+        })
+        .await?;
+        Ok(())
+    }
+
+    async fn generate_embeddings(
+        &self,
+        model: wasi_llm::EmbeddingModel,
+        data: Vec<String>,
+    ) -> Result<Vec<Vec<f32>>, wasi_llm::Error> {
+        let wasi_llm::EmbeddingModel::AllMiniLmL6V2 = model else {
+            return Err(wasi_llm::Error::ModelNotSupported);
+        };
+        let model = self.embedding_model().await?;
+        tokio::task::spawn_blocking(move || model.embed(data))
+            .await
+            .map_err(|e| wasi_llm::Error::RuntimeError(format!("Embedding task failed: {e}")))?
+            .map_err(|e| {
+                wasi_llm::Error::RuntimeError(format!("Failed to generate embeddings: {e:#}"))
+            })
+    }
+
+    fn check_model_available(&self, name: &str) -> anyhow::Result<()> {
+        if self.registry_models.contains_key(name) {
+            // Listed models were checked when the backend was created.
+            return Ok(());
+        }
+        let path = self.registry.join(name);
+        let exists = match name {
+            EMBEDDING_MODEL_NAME => path.is_dir(),
+            "llama2-70b-chat" | "llama2-13b-chat" | "llama2-7b-chat" => path.is_file(),
+            _ => anyhow::bail!(
+                "unknown model; models other than the Llama 2 chat models must be listed in the `llm_compute` runtime config"
+            ),
+        };
+        anyhow::ensure!(exists, "{path:?} does not exist");
+        Ok(())
+    }
+}
+
+// Sampling options for picking the next token in the sequence.
+// We start with a default sampler, then add the inference parameters supplied by the request.
+fn generate_sampler(
+    options: &InferenceOptions,
+) -> Arc<Mutex<dyn llm::samplers::llm_samplers::types::Sampler<llm::TokenId, f32>>> {
+    let InferenceOptions {
+        temperature,
+        top_k,
+        top_p,
+        repeat_penalty,
+        repeat_penalty_last_n_token_count,
+        ..
+    } = *options;
+    let mut result = llm::samplers::ConfiguredSamplers {
+        // We are *not* using the default implementation for ConfiguredSamplers here
+        // because the builder already sets values for parameters, which we cannot replace.
+        builder: llm::samplers::llm_samplers::configure::SamplerChainBuilder::default(),
+        ..Default::default()
+    };
+
+    result.builder += (
+        "temperature".into(),
+        llm::samplers::llm_samplers::configure::SamplerSlot::new_single(
+            move || {
+                Box::new(
+                    llm::samplers::llm_samplers::samplers::SampleTemperature::default()
+                        .temperature(temperature),
+                )
+            },
+            Option::<llm::samplers::llm_samplers::samplers::SampleTemperature>::None,
+        ),
+    );
+    result.builder += (
+        "topp".into(),
+        llm::samplers::llm_samplers::configure::SamplerSlot::new_single(
+            move || Box::new(llm::samplers::llm_samplers::samplers::SampleTopP::default().p(top_p)),
+            Option::<llm::samplers::llm_samplers::samplers::SampleTopP>::None,
+        ),
+    );
+    result.builder += (
+        "topk".into(),
+        llm::samplers::llm_samplers::configure::SamplerSlot::new_single(
+            move || Box::new(llm::samplers::llm_samplers::samplers::SampleTopK::default().k(top_k)),
+            Option::<llm::samplers::llm_samplers::samplers::SampleTopK>::None,
+        ),
+    );
+    result.builder += (
+        "repetition".into(),
+        llm::samplers::llm_samplers::configure::SamplerSlot::new_chain(
+            move || {
+                Box::new(
+                    llm::samplers::llm_samplers::samplers::SampleRepetition::default()
+                        .penalty(repeat_penalty)
+                        .last_n(repeat_penalty_last_n_token_count),
+                )
+            },
+            [],
+        ),
+    );
+
+    result.ensure_default_slots();
+    Arc::new(Mutex::new(result.builder.into_chain()))
+}
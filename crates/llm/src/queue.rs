@@ -0,0 +1,78 @@
+use std::sync::Arc;
+
+use spin_world::llm::{self as wasi_llm};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Limits how many inferences run at once, and how many may wait for a turn.
+///
+/// Requests beyond that are rejected with `too-many-requests` rather than
+/// waiting indefinitely.
+pub(crate) struct InferenceQueue {
+    // A permit for each inference which is running or waiting to run.
+    admitted: Arc<Semaphore>,
+    // A permit for each inference which is running.
+    running: Arc<Semaphore>,
+}
+
+/// A turn to run an inference, which ends when this is dropped.
+pub(crate) struct QueueTurn {
+    _admitted: OwnedSemaphorePermit,
+    _running: OwnedSemaphorePermit,
+}
+
+impl InferenceQueue {
+    pub fn new(parallelism: usize, queue_size: usize) -> Self {
+        Self {
+            admitted: Arc::new(Semaphore::new(parallelism + queue_size)),
+            running: Arc::new(Semaphore::new(parallelism)),
+        }
+    }
+
+    /// Waits for a turn to run an inference, or fails immediately if the
+    /// queue is full.
+    pub async fn enter(&self) -> Result<QueueTurn, wasi_llm::Error> {
+        let admitted = self
+            .admitted
+            .clone()
+            .try_acquire_owned()
+            .map_err(|_| wasi_llm::Error::TooManyRequests)?;
+        let running = self
+            .running
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("inference queue semaphore should never be closed");
+        Ok(QueueTurn {
+            _admitted: admitted,
+            _running: running,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn requests_beyond_queue_size_are_rejected() {
+        let queue = InferenceQueue::new(1, 1);
+        let running = queue.enter().await.unwrap();
+
+        // The second request waits for the first to finish...
+        let waiting = queue.enter();
+        tokio::pin!(waiting);
+        assert!(futures::poll!(waiting.as_mut()).is_pending());
+        // ...and the third is turned away.
+        assert!(matches!(
+            queue.enter().await,
+            Err(wasi_llm::Error::TooManyRequests)
+        ));
+
+        // Once the first finishes, the second runs and there's room to wait again.
+        drop(running);
+        let _running = waiting.await.unwrap();
+        let waiting = queue.enter();
+        tokio::pin!(waiting);
+        assert!(futures::poll!(waiting.as_mut()).is_pending());
+    }
+}
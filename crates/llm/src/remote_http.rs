@@ -0,0 +1,323 @@
+use std::collections::HashMap;
+
+use reqwest::{Client, StatusCode};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use spin_core::async_trait;
+use spin_world::llm::{self as wasi_llm};
+
+use crate::{embedding_model_name, model_name, InferenceOptions, LlmBackend};
+
+#[derive(Clone, Default)]
+pub struct RemoteHttpOptions {
+    /// The base URL of the OpenAI-compatible API, e.g. `https://api.openai.com/v1`.
+    pub url: String,
+    /// Sent as a bearer token with every request.
+    pub auth_token: Option<String>,
+    /// The names the service uses for models, by the name guests use for them.
+    /// Models which aren't listed are requested under the guest's name.
+    pub models: HashMap<String, String>,
+}
+
+impl std::fmt::Debug for RemoteHttpOptions {
+    // Leaves out the auth token.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RemoteHttpOptions")
+            .field("url", &self.url)
+            .field("models", &self.models)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Forwards inference and embedding requests to a service implementing the
+/// OpenAI completions and embeddings APIs.
+///
+/// The OpenAI API has no equivalent of the top-k and repeat penalty
+/// inferencing params, so those are not sent.
+pub struct RemoteHttpBackend {
+    client: Client,
+    url: String,
+    auth_token: Option<String>,
+    models: HashMap<String, String>,
+}
+
+impl RemoteHttpBackend {
+    pub fn new(options: RemoteHttpOptions) -> Self {
+        Self {
+            client: Client::new(),
+            url: options.url.trim_end_matches('/').to_owned(),
+            auth_token: options.auth_token,
+            models: options.models,
+        }
+    }
+
+    fn remote_model_name<'a>(&'a self, name: &'a str) -> &'a str {
+        self.models.get(name).map(String::as_str).unwrap_or(name)
+    }
+
+    async fn post<Req: Serialize, Resp: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &Req,
+    ) -> Result<Resp, wasi_llm::Error> {
+        let url = format!("{}/{path}", self.url);
+        let mut request = self.client.post(&url).json(body);
+        if let Some(token) = &self.auth_token {
+            request = request.bearer_auth(token);
+        }
+        let response = request.send().await.map_err(|e| {
+            wasi_llm::Error::RuntimeError(format!("Failed to send request to {url}: {e}"))
+        })?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(match status {
+                StatusCode::BAD_REQUEST | StatusCode::UNPROCESSABLE_ENTITY => {
+                    wasi_llm::Error::InvalidInput(body)
+                }
+                StatusCode::NOT_FOUND => wasi_llm::Error::ModelNotSupported,
+                StatusCode::TOO_MANY_REQUESTS => wasi_llm::Error::TooManyRequests,
+                _ => wasi_llm::Error::RuntimeError(format!("{url} returned {status}: {body}")),
+            });
+        }
+        response
+            .json()
+            .await
+            .map_err(|e| wasi_llm::Error::RuntimeError(format!("Invalid response from {url}: {e}")))
+    }
+}
+
+#[async_trait]
+impl LlmBackend for RemoteHttpBackend {
+    async fn infer(
+        &self,
+        model: wasi_llm::InferencingModel,
+        prompt: String,
+        options: InferenceOptions,
+    ) -> Result<wasi_llm::InferencingResult, wasi_llm::Error> {
+        let request = CompletionRequest {
+            model: self.remote_model_name(model_name(&model)),
+            prompt: &prompt,
+            max_tokens: options.max_tokens,
+            temperature: options.temperature,
+            top_p: options.top_p,
+        };
+        let response: CompletionResponse = self.post("completions", &request).await?;
+        let text = response
+            .choices
+            .into_iter()
+            .next()
+            .map(|choice| choice.text)
+            .ok_or_else(|| wasi_llm::Error::RuntimeError("Response has no completions".into()))?;
+        Ok(wasi_llm::InferencingResult {
+            text,
+            usage: wasi_llm::InferencingUsage {
+                prompt_token_count: response.usage.prompt_tokens,
+                generated_token_count: response.usage.completion_tokens,
+            },
+        })
+    }
+
+    async fn generate_embeddings(
+        &self,
+        model: wasi_llm::EmbeddingModel,
+        data: Vec<String>,
+    ) -> Result<Vec<Vec<f32>>, wasi_llm::Error> {
+        let request = EmbeddingRequest {
+            model: self.remote_model_name(embedding_model_name(&model)),
+            input: &data,
+        };
+        let mut response: EmbeddingResponse = self.post("embeddings", &request).await?;
+        if response.data.len() != data.len() {
+            return Err(wasi_llm::Error::RuntimeError(format!(
+                "Expected {} embeddings but the response has {}",
+                data.len(),
+                response.data.len()
+            )));
+        }
+        response.data.sort_by_key(|embedding| embedding.index);
+        Ok(response
+            .data
+            .into_iter()
+            .map(|embedding| embedding.embedding)
+            .collect())
+    }
+
+    fn check_model_available(&self, _name: &str) -> anyhow::Result<()> {
+        // The service decides which models it serves, and an unknown model
+        // is reported when it is used.
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+struct CompletionRequest<'a> {
+    model: &'a str,
+    prompt: &'a str,
+    max_tokens: usize,
+    temperature: f32,
+    top_p: f32,
+}
+
+#[derive(Deserialize)]
+struct CompletionResponse {
+    choices: Vec<CompletionChoice>,
+    // Not all OpenAI-compatible services report usage.
+    #[serde(default)]
+    usage: CompletionUsage,
+}
+
+#[derive(Default, Deserialize)]
+struct CompletionUsage {
+    prompt_tokens: u32,
+    completion_tokens: u32,
+}
+
+#[derive(Deserialize)]
+struct CompletionChoice {
+    text: String,
+}
+
+#[derive(Serialize)]
+struct EmbeddingRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+}
+
+#[derive(Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<Embedding>,
+}
+
+#[derive(Deserialize)]
+struct Embedding {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+#[cfg(test)]
+mod test {
+    use wiremock::{
+        matchers::{body_partial_json, header, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+
+    // Stands in for the remote service, answering POSTs to `endpoint` which
+    // have the backend's auth token and include `body` with `status` and
+    // `response`.
+    async fn serve(
+        endpoint: &str,
+        body: serde_json::Value,
+        status: u16,
+        response: &str,
+    ) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path(format!("/v1/{endpoint}")))
+            .and(header("authorization", "Bearer s3cr3t"))
+            .and(body_partial_json(body))
+            .respond_with(ResponseTemplate::new(status).set_body_raw(response, "application/json"))
+            .expect(1)
+            .mount(&server)
+            .await;
+        server
+    }
+
+    fn backend(server: &MockServer) -> RemoteHttpBackend {
+        RemoteHttpBackend::new(RemoteHttpOptions {
+            url: format!("{}/v1/", server.uri()),
+            auth_token: Some("s3cr3t".into()),
+            models: [("llama2-7b-chat".to_owned(), "remote-llama".to_owned())].into(),
+        })
+    }
+
+    #[tokio::test]
+    async fn infer_forwards_to_completions() {
+        let server = serve(
+            "completions",
+            serde_json::json!({"model": "remote-llama", "prompt": "Say hello", "max_tokens": 10}),
+            200,
+            r#"{"choices": [{"text": "Hello"}], "usage": {"prompt_tokens": 3, "completion_tokens": 1}}"#,
+        )
+        .await;
+        let options = InferenceOptions {
+            max_tokens: 10,
+            ..Default::default()
+        };
+
+        let result = backend(&server)
+            .infer(
+                wasi_llm::InferencingModel::Llama2V7bChat,
+                "Say hello".into(),
+                options,
+            )
+            .await
+            .unwrap();
+        assert_eq!("Hello", result.text);
+        assert_eq!(3, result.usage.prompt_token_count);
+        assert_eq!(1, result.usage.generated_token_count);
+    }
+
+    #[tokio::test]
+    async fn embeddings_are_returned_in_input_order() {
+        let server = serve(
+            "embeddings",
+            serde_json::json!({"model": "all-minilm-l6-v2", "input": ["a", "b"]}),
+            200,
+            r#"{"data": [{"index": 1, "embedding": [0.5]}, {"index": 0, "embedding": [0.25]}]}"#,
+        )
+        .await;
+
+        let embeddings = backend(&server)
+            .generate_embeddings(
+                wasi_llm::EmbeddingModel::AllMiniLmL6V2,
+                vec!["a".into(), "b".into()],
+            )
+            .await
+            .unwrap();
+        assert_eq!(vec![vec![0.25], vec![0.5]], embeddings);
+    }
+
+    #[tokio::test]
+    async fn error_statuses_are_mapped() {
+        let server = serve(
+            "completions",
+            serde_json::json!({"model": "gpt"}),
+            400,
+            r#"{"error": "prompt too long"}"#,
+        )
+        .await;
+        let result = backend(&server)
+            .infer(
+                wasi_llm::InferencingModel::Other("gpt".into()),
+                "...".into(),
+                Default::default(),
+            )
+            .await;
+        assert!(matches!(result, Err(wasi_llm::Error::InvalidInput(_))));
+
+        let server = serve(
+            "embeddings",
+            serde_json::json!({}),
+            429,
+            r#"{"error": "rate limited"}"#,
+        )
+        .await;
+        let result = backend(&server)
+            .generate_embeddings(wasi_llm::EmbeddingModel::AllMiniLmL6V2, vec!["a".into()])
+            .await;
+        assert!(matches!(result, Err(wasi_llm::Error::TooManyRequests)));
+    }
+
+    #[test]
+    fn debug_leaves_out_auth_token() {
+        let options = RemoteHttpOptions {
+            url: "https://example.com".into(),
+            auth_token: Some("s3cr3t".into()),
+            models: Default::default(),
+        };
+        assert!(!format!("{options:?}").contains("s3cr3t"));
+    }
+}
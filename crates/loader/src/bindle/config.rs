@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-use crate::common::RawVariable;
+use crate::common::{RawSqliteDatabase, RawVariable};
 
 /// Application configuration file format.
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -50,7 +50,9 @@ pub struct RawWasmConfig {
     /// Optional list of key-value stores the component is allowed to use.
     pub key_value_stores: Option<Vec<String>>,
     /// Optional list of SQLite databases the component is allowed to use.
-    pub sqlite_databases: Option<Vec<String>>,
+    pub sqlite_databases: Option<Vec<RawSqliteDatabase>>,
+    /// Optional list of AI models the component is allowed to use.
+    pub ai_models: Option<Vec<String>>,
     /// Environment variables to be mapped inside the Wasm module at runtime.
     pub environment: Option<HashMap<String, String>>,
 }
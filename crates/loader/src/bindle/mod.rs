@@ -14,13 +14,14 @@ mod utils;
 
 use std::path::Path;
 
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, ensure, Context, Result};
 use bindle::Invoice;
 use futures::future;
 use outbound_http::allowed_http_hosts::validate_allowed_http_hosts;
+use serde::Deserialize;
 use spin_manifest::{
-    Application, ApplicationInformation, ApplicationOrigin, CoreComponent, ModuleSource,
-    SpinVersion, WasmConfig,
+    Application, ApplicationInformation, ApplicationOrigin, ApplicationTrigger, CoreComponent,
+    KeyValueConfig, ModuleSource, SpinVersion, TriggerConfig, WasmConfig,
 };
 
 use crate::bindle::{
@@ -70,8 +71,13 @@ async fn prepare(
     let component_triggers = raw
         .components
         .iter()
-        .map(|c| (c.id.clone(), c.trigger.clone()))
-        .collect();
+        .map(|c| {
+            Ok((
+                c.id.clone(),
+                resolve_trigger(&raw.trigger, c.trigger.clone())?,
+            ))
+        })
+        .collect::<Result<_>>()?;
     let components = future::join_all(
         raw.components
             .into_iter()
@@ -97,6 +103,24 @@ async fn prepare(
     })
 }
 
+/// Key-value trigger configs can't be told apart from external ones by their
+/// fields, so they are deserialized as `External` and converted here once the
+/// application trigger type is known.
+fn resolve_trigger(
+    app_trigger: &ApplicationTrigger,
+    trigger: TriggerConfig,
+) -> Result<TriggerConfig> {
+    match (app_trigger, trigger) {
+        (ApplicationTrigger::KeyValue(_), TriggerConfig::External(parameters)) => {
+            let parameters = toml::Value::Table(parameters.into_iter().collect());
+            Ok(TriggerConfig::KeyValue(KeyValueConfig::deserialize(
+                parameters,
+            )?))
+        }
+        (_, trigger) => Ok(trigger),
+    }
+}
+
 fn validate_raw_app_manifest(raw: &RawAppManifest) -> Result<()> {
     raw.components
         .iter()
@@ -131,13 +155,24 @@ async fn core(
     let environment = raw.wasm.environment.unwrap_or_default();
     let allowed_http_hosts = raw.wasm.allowed_http_hosts.unwrap_or_default();
     let key_value_stores = raw.wasm.key_value_stores.unwrap_or_default();
-    let sqlite_databases = raw.wasm.sqlite_databases.unwrap_or_default();
+    let sqlite_databases =
+        crate::common::sqlite_databases(raw.wasm.sqlite_databases.unwrap_or_default())
+            .with_context(|| format!("Invalid sqlite_databases in component {id}"))?;
+    // Migrations directories are not packaged in the bindle.
+    ensure!(
+        sqlite_databases.migrations.is_empty(),
+        "Component {id} declares sqlite migrations, which are not supported for applications loaded from bindle"
+    );
+    let ai_models = raw.wasm.ai_models.unwrap_or_default();
     let wasm = WasmConfig {
         environment,
         mounts,
         allowed_http_hosts,
         key_value_stores,
-        sqlite_databases,
+        sqlite_databases: sqlite_databases.names,
+        sqlite_database_access: sqlite_databases.access,
+        sqlite_database_migrations: sqlite_databases.migrations,
+        ai_models,
     };
     let config = raw.config.unwrap_or_default();
     Ok(CoreComponent {
@@ -1,6 +1,8 @@
-use anyhow::ensure;
+use std::{collections::HashMap, path::PathBuf};
+
+use anyhow::{anyhow, ensure, Result};
 use serde::{Deserialize, Serialize};
-use spin_manifest::Variable;
+use spin_manifest::{SqliteDatabaseAccess, Variable, VariableType};
 
 /// Variable configuration.
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
@@ -15,6 +17,15 @@ pub struct RawVariable {
     /// If set, this variable should be treated as sensitive.
     #[serde(default)]
     pub secret: bool,
+    /// The type of value this variable holds. Defaults to `string`.
+    #[serde(default, rename = "type")]
+    pub value_type: VariableType,
+    /// If set, a regular expression which the value must match.
+    #[serde(default)]
+    pub pattern: Option<String>,
+    /// If set, the only values this variable may take.
+    #[serde(default, rename = "allowed_values")]
+    pub allowed_values: Option<Vec<String>>,
 }
 
 impl TryFrom<RawVariable> for Variable {
@@ -25,9 +36,80 @@ impl TryFrom<RawVariable> for Variable {
             var.required ^ var.default.is_some(),
             "variable should either have `required` set to true OR have a non-empty default value"
         );
+        ensure!(
+            var.allowed_values.as_ref().map_or(true, |v| !v.is_empty()),
+            "`allowed_values` must not be empty"
+        );
+        if let Some(pattern) = &var.pattern {
+            regex::Regex::new(pattern)
+                .map_err(|err| anyhow!("`pattern` is not a valid regular expression: {err}"))?;
+        }
         Ok(Variable {
             default: var.default,
             secret: var.secret,
+            value_type: var.value_type,
+            pattern: var.pattern,
+            allowed_values: var.allowed_values,
         })
     }
 }
+
+/// An entry in a component's `sqlite_databases` list: either the name of a
+/// database, or a table which also sets the component's access to it or its
+/// migrations.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(untagged)]
+pub enum RawSqliteDatabase {
+    /// A database with read-write access.
+    Name(String),
+    /// A database with further settings.
+    Table(RawSqliteDatabaseTable),
+}
+
+/// A sqlite database with settings beyond its name.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub struct RawSqliteDatabaseTable {
+    /// The name of the database.
+    pub name: String,
+    /// The statements the component may execute against the database.
+    #[serde(default)]
+    pub access: SqliteDatabaseAccess,
+    /// The directory of versioned migrations to apply to the database,
+    /// relative to the manifest.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub migrations: Option<PathBuf>,
+}
+
+/// A `sqlite_databases` list split into the database names, any access
+/// restrictions on them, and any migrations directories declared for them.
+#[derive(Debug, Default)]
+pub(crate) struct SqliteDatabases {
+    pub names: Vec<String>,
+    pub access: HashMap<String, SqliteDatabaseAccess>,
+    pub migrations: HashMap<String, PathBuf>,
+}
+
+/// Splits a `sqlite_databases` list into the database names and the settings
+/// declared for them.
+pub(crate) fn sqlite_databases(raw: Vec<RawSqliteDatabase>) -> Result<SqliteDatabases> {
+    let mut databases = SqliteDatabases::default();
+    for database in raw {
+        let (name, access, migrations) = match database {
+            RawSqliteDatabase::Name(name) => (name, SqliteDatabaseAccess::ReadWrite, None),
+            RawSqliteDatabase::Table(t) => (t.name, t.access, t.migrations),
+        };
+        ensure!(
+            !databases.names.contains(&name),
+            "sqlite database '{name}' is listed more than once"
+        );
+        if access != SqliteDatabaseAccess::ReadWrite {
+            databases.access.insert(name.clone(), access);
+        }
+        if let Some(migrations) = migrations {
+            databases.migrations.insert(name.clone(), migrations);
+        }
+        databases.names.push(name);
+    }
+    Ok(databases)
+}
@@ -8,7 +8,7 @@ use serde::{Deserialize, Serialize};
 use spin_manifest::{ApplicationTrigger, TriggerConfig};
 use std::{collections::HashMap, path::PathBuf};
 
-use crate::common::RawVariable;
+use crate::common::{RawSqliteDatabase, RawVariable};
 
 /// Container for any version of the manifest.
 pub type RawAppManifestAnyVersion = RawAppManifestAnyVersionImpl<TriggerConfig>;
@@ -143,7 +143,9 @@ pub struct RawWasmConfig {
     /// Optional list of key-value stores the component is allowed to use.
     pub key_value_stores: Option<Vec<String>>,
     /// Optional list of sqlite databases the component is allowed to use.
-    pub sqlite_databases: Option<Vec<String>>,
+    pub sqlite_databases: Option<Vec<RawSqliteDatabase>>,
+    /// Optional list of AI models the component is allowed to use.
+    pub ai_models: Option<Vec<String>>,
     /// Environment variables to be mapped inside the Wasm module at runtime.
     pub environment: Option<HashMap<String, String>>,
 }
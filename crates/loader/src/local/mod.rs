@@ -23,7 +23,7 @@ use path_absolutize::Absolutize;
 use reqwest::Url;
 use spin_manifest::{
     Application, ApplicationInformation, ApplicationOrigin, ApplicationTrigger, CoreComponent,
-    HttpConfig, ModuleSource, RedisConfig, SpinVersion, TriggerConfig, WasmConfig,
+    HttpConfig, KeyValueConfig, ModuleSource, RedisConfig, SpinVersion, TriggerConfig, WasmConfig,
 };
 use tokio::{fs::File, io::AsyncReadExt};
 
@@ -51,6 +51,28 @@ pub async fn from_file(
     prepare_any_version(manifest, app, base_dst).await
 }
 
+/// Given the path to a spin.toml manifest file, get the migrations directory
+/// declared for each sqlite database, without preparing the application.
+pub async fn sqlite_migrations(app: impl AsRef<Path>) -> Result<HashMap<String, PathBuf>> {
+    let app = absolutize(app)?;
+    let manifest = raw_manifest_from_file(&app).await?;
+    validate_raw_app_manifest(&manifest)?;
+
+    let src = parent_dir(&app)?;
+    let components = manifest
+        .into_v1()
+        .components
+        .into_iter()
+        .map(|c| {
+            let databases =
+                crate::common::sqlite_databases(c.wasm.sqlite_databases.unwrap_or_default())
+                    .with_context(|| format!("Invalid sqlite_databases in component {}", c.id))?;
+            Ok((c.id, resolve_sqlite_migrations(databases.migrations, &src)))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    merge_sqlite_migrations(components.iter().map(|(id, m)| (id.as_str(), m)))
+}
+
 /// Reads the spin.toml file as a raw manifest.
 pub async fn raw_manifest_from_file(app: &impl AsRef<Path>) -> Result<RawAppManifestAnyVersion> {
     async fn from_file(app: &Path) -> anyhow::Result<RawAppManifestAnyVersion> {
@@ -117,6 +139,42 @@ fn error_on_duplicate_ids(components: Vec<RawComponentManifest>) -> Result<()> {
     Ok(())
 }
 
+/// Merges the migrations directories declared by each component, failing if
+/// two components declare different directories for the same database.
+fn merge_sqlite_migrations<'a>(
+    components: impl IntoIterator<Item = (&'a str, &'a HashMap<String, PathBuf>)>,
+) -> Result<HashMap<String, PathBuf>> {
+    let mut merged: HashMap<String, (&str, PathBuf)> = HashMap::new();
+    for (id, migrations) in components {
+        for (name, dir) in migrations {
+            match merged.get(name) {
+                Some((other_id, other_dir)) if other_dir != dir => bail!(
+                    "components {other_id} and {id} declare different migrations for sqlite database '{name}'"
+                ),
+                Some(_) => {}
+                None => {
+                    merged.insert(name.clone(), (id, dir.clone()));
+                }
+            }
+        }
+    }
+    Ok(merged
+        .into_iter()
+        .map(|(name, (_, dir))| (name, dir))
+        .collect())
+}
+
+/// Resolves migrations directories against the directory containing the manifest.
+fn resolve_sqlite_migrations(
+    migrations: HashMap<String, PathBuf>,
+    src: &Path,
+) -> HashMap<String, PathBuf> {
+    migrations
+        .into_iter()
+        .map(|(name, dir)| (name, src.join(dir)))
+        .collect()
+}
+
 /// Validate fields in raw app manifest
 pub fn validate_raw_app_manifest(raw: &RawAppManifestAnyVersion) -> Result<()> {
     let manifest = raw.as_v1();
@@ -164,6 +222,12 @@ async fn prepare(
     .into_iter()
     .collect::<Result<Vec<_>>>()?;
 
+    merge_sqlite_migrations(
+        components
+            .iter()
+            .map(|c| (c.id.as_str(), &c.wasm.sqlite_database_migrations)),
+    )?;
+
     let variables = raw
         .variables
         .into_iter()
@@ -213,20 +277,27 @@ async fn core(
     let mounts = match raw.wasm.files {
         Some(f) => {
             let exclude_files = raw.wasm.exclude_files.unwrap_or_default();
-            assets::prepare_component(&f, src, base_dst, &id, &exclude_files).await?
+            assets::prepare_component(&f, &src, base_dst, &id, &exclude_files).await?
         }
         None => vec![],
     };
     let environment = raw.wasm.environment.unwrap_or_default();
     let allowed_http_hosts = raw.wasm.allowed_http_hosts.unwrap_or_default();
     let key_value_stores = raw.wasm.key_value_stores.unwrap_or_default();
-    let sqlite_databases = raw.wasm.sqlite_databases.unwrap_or_default();
+    let sqlite_databases =
+        crate::common::sqlite_databases(raw.wasm.sqlite_databases.unwrap_or_default())
+            .with_context(|| format!("Invalid sqlite_databases in component {id}"))?;
+    let sqlite_database_migrations = resolve_sqlite_migrations(sqlite_databases.migrations, &src);
+    let ai_models = raw.wasm.ai_models.unwrap_or_default();
     let wasm = WasmConfig {
         environment,
         mounts,
         allowed_http_hosts,
         key_value_stores,
-        sqlite_databases,
+        sqlite_databases: sqlite_databases.names,
+        sqlite_database_access: sqlite_databases.access,
+        sqlite_database_migrations,
+        ai_models,
     };
     let config = raw.config.unwrap_or_default();
     Ok(CoreComponent {
@@ -446,6 +517,9 @@ fn resolve_trigger(
     let tc = match app_trigger {
         ApplicationTrigger::Http(_) => TriggerConfig::Http(HttpConfig::deserialize(partial)?),
         ApplicationTrigger::Redis(_) => TriggerConfig::Redis(RedisConfig::deserialize(partial)?),
+        ApplicationTrigger::KeyValue(_) => {
+            TriggerConfig::KeyValue(KeyValueConfig::deserialize(partial)?)
+        }
         ApplicationTrigger::External(_) => TriggerConfig::External(HashMap::deserialize(partial)?),
     };
     Ok(tc)
@@ -466,6 +540,8 @@ fn info(raw: RawAppInformation, src: impl AsRef<Path>) -> ApplicationInformation
 
 #[cfg(test)]
 mod test {
+    use spin_manifest::SqliteDatabaseAccess;
+
     use super::*;
 
     fn load_test_manifest(app_trigger: &str, comp_trigger: &str) -> RawAppManifestAnyVersion {
@@ -541,6 +617,51 @@ route = "/"
         assert!(matches!(ct, TriggerConfig::Redis(_)));
     }
 
+    #[test]
+    fn can_parse_key_value_trigger() {
+        let m = load_test_manifest(
+            r#"{ type = "key_value" }"#,
+            r#"store = "default"
+            prefix = "cache/""#,
+        );
+
+        let m1 = m.into_v1();
+        let t = m1.info.trigger;
+        let ct = &m1.components[0].trigger;
+        assert!(matches!(t, ApplicationTrigger::KeyValue(_)));
+        assert!(
+            matches!(ct, TriggerConfig::KeyValue(KeyValueConfig { store, prefix }) if store == "default" && prefix == "cache/")
+        );
+    }
+
+    #[test]
+    fn can_parse_sqlite_database_access() {
+        let manifest_toml = r#"
+spin_version = "1"
+name = "test"
+trigger = { type = "http", base = "/" }
+version = "0.0.1"
+
+[[component]]
+id = "test"
+source = "nonexistent.wasm"
+sqlite_databases = ["default", { name = "reports", access = "read_only" }, { name = "orders", access = "dml_only" }]
+[component.trigger]
+route = "/"
+"#;
+
+        let m1 = raw_manifest_from_slice(manifest_toml.as_bytes())
+            .unwrap()
+            .into_v1();
+        let raw = m1.components[0].wasm.sqlite_databases.clone().unwrap();
+        let databases = crate::common::sqlite_databases(raw).unwrap();
+        assert_eq!(vec!["default", "reports", "orders"], databases.names);
+        assert_eq!(2, databases.access.len());
+        assert_eq!(SqliteDatabaseAccess::ReadOnly, databases.access["reports"]);
+        assert_eq!(SqliteDatabaseAccess::DmlOnly, databases.access["orders"]);
+        assert!(databases.migrations.is_empty());
+    }
+
     #[test]
     fn can_parse_unknown_trigger() {
         let m = load_test_manifest(r#"{ type = "pounce" }"#, r#"on = "MY KNEES""#);
@@ -1,9 +1,10 @@
+use crate::common::RawVariable;
 use crate::local::config::{RawDirectoryPlacement, RawFileMount, RawModuleSource};
 
 use super::*;
 use anyhow::Result;
-use spin_manifest::{HttpConfig, HttpExecutor, HttpTriggerConfiguration};
-use std::path::PathBuf;
+use spin_manifest::{HttpConfig, HttpExecutor, HttpTriggerConfiguration, Variable, VariableType};
+use std::{collections::HashMap, path::PathBuf};
 
 fn raw_manifest_from_str(toml: &str) -> Result<RawAppManifestAnyVersion> {
     raw_manifest_from_slice(toml.as_bytes())
@@ -216,6 +217,38 @@ async fn gives_correct_error_when_missing_app_trigger_field() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_typed_variables() -> Result<()> {
+    let vars: HashMap<String, RawVariable> = toml::from_str(
+        r#"
+        port = { default = "8080", type = "integer" }
+        region = { required = true, pattern = "^[a-z]{2}$", allowed_values = ["eu", "us"] }
+        "#,
+    )?;
+
+    let port: Variable = vars["port"].clone().try_into()?;
+    assert_eq!(port.value_type, VariableType::Integer);
+
+    let region: Variable = vars["region"].clone().try_into()?;
+    assert_eq!(region.value_type, VariableType::String);
+    assert_eq!(region.pattern.as_deref(), Some("^[a-z]{2}$"));
+    assert_eq!(
+        region.allowed_values,
+        Some(vec!["eu".to_owned(), "us".to_owned()])
+    );
+
+    let bad_pattern: RawVariable = toml::from_str("required = true\npattern = \"(\"")?;
+    Variable::try_from(bad_pattern).expect_err("invalid pattern should be rejected");
+
+    let no_values: RawVariable = toml::from_str("required = true\nallowed_values = []")?;
+    Variable::try_from(no_values).expect_err("empty allowed values should be rejected");
+
+    let bad_type: Result<RawVariable, _> = toml::from_str("required = true\ntype = \"float\"");
+    bad_type.expect_err("unknown type should be rejected");
+
+    Ok(())
+}
+
 #[test]
 fn test_wagi_executor_with_custom_entrypoint() -> Result<()> {
     const MANIFEST: &str = include_str!("../../tests/wagi-custom-entrypoint.toml");
@@ -299,3 +332,39 @@ async fn test_invalid_url_in_allowed_http_hosts_is_rejected() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_sqlite_migrations_are_resolved_against_manifest() -> Result<()> {
+    const MANIFEST: &str = "tests/sqlite-migrations.toml";
+
+    let expected = absolutize("tests/migrations")?;
+
+    let migrations = sqlite_migrations(MANIFEST).await?;
+    assert_eq!(1, migrations.len());
+    assert_eq!(expected, migrations["default"]);
+
+    let temp_dir = tempfile::tempdir()?;
+    let app = from_file(MANIFEST, Some(temp_dir.path())).await?;
+    let reader = &app.components[1].wasm;
+    assert_eq!(vec!["default", "other"], reader.sqlite_databases);
+    assert_eq!(expected, reader.sqlite_database_migrations["default"]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_conflicting_sqlite_migrations_are_rejected() -> Result<()> {
+    const MANIFEST: &str = "tests/invalid-sqlite-migrations.toml";
+
+    let temp_dir = tempfile::tempdir()?;
+    let app = from_file(MANIFEST, Some(temp_dir.path())).await;
+    let e = app.unwrap_err().to_string();
+    assert!(
+        e.contains("default"),
+        "Expected error to name the sqlite database `default`: {e}"
+    );
+
+    assert!(sqlite_migrations(MANIFEST).await.is_err());
+
+    Ok(())
+}
@@ -98,6 +98,27 @@ pub struct Variable {
     pub default: Option<String>,
     /// If set, this variable's value should be treated as sensitive (e.g. not logged).
     pub secret: bool,
+    /// The type of value this variable holds.
+    pub value_type: VariableType,
+    /// If set, a regular expression which this variable's value must match.
+    pub pattern: Option<String>,
+    /// If set, the only values this variable may take.
+    pub allowed_values: Option<Vec<String>>,
+}
+
+/// The type of value a custom config variable holds.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VariableType {
+    /// Any string.
+    #[default]
+    String,
+    /// A signed 64-bit integer, e.g. `-42`.
+    Integer,
+    /// `true` or `false`.
+    Boolean,
+    /// An absolute URL, e.g. `https://example.com/api`.
+    Url,
 }
 
 /// The location from which an application was loaded.
@@ -127,6 +148,8 @@ pub enum ApplicationTrigger {
     Http(HttpTriggerConfiguration),
     /// Redis trigger type.
     Redis(RedisTriggerConfiguration),
+    /// Key-value change trigger type.
+    KeyValue(KeyValueTriggerConfiguration),
     /// A trigger type that is not built in.
     External(ExternalTriggerConfiguration),
 }
@@ -159,6 +182,9 @@ enum InternalApplicationTriggerSerialised {
     Http(HttpTriggerConfiguration),
     /// Redis trigger type.
     Redis(RedisTriggerConfiguration),
+    /// Key-value change trigger type.
+    #[serde(rename = "key_value")]
+    KeyValue(KeyValueTriggerConfiguration),
 }
 
 impl TryFrom<ApplicationTriggerDeserialised> for ApplicationTrigger {
@@ -174,6 +200,10 @@ impl TryFrom<ApplicationTriggerDeserialised> for ApplicationTrigger {
                 RedisTriggerConfiguration::deserialize(value.parameters)
                     .map_err(|e| Error::InvalidTriggerTypeParameters(e.to_string()))?,
             ),
+            "key_value" => ApplicationTrigger::KeyValue(
+                KeyValueTriggerConfiguration::deserialize(value.parameters)
+                    .map_err(|e| Error::InvalidTriggerTypeParameters(e.to_string()))?,
+            ),
             _ => ApplicationTrigger::External(ExternalTriggerConfiguration {
                 trigger_type: value.trigger_type,
                 parameters: HashMap::deserialize(value.parameters)
@@ -193,6 +223,9 @@ impl From<ApplicationTrigger> for ApplicationTriggerSerialised {
             ApplicationTrigger::Redis(r) => {
                 Self::Internal(InternalApplicationTriggerSerialised::Redis(r))
             }
+            ApplicationTrigger::KeyValue(k) => {
+                Self::Internal(InternalApplicationTriggerSerialised::KeyValue(k))
+            }
             ApplicationTrigger::External(e) => {
                 let ty = e.trigger_type;
                 let mut map = e.parameters;
@@ -245,6 +278,22 @@ impl TryFrom<ApplicationTrigger> for RedisTriggerConfiguration {
     }
 }
 
+/// Key-value change trigger configuration.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct KeyValueTriggerConfiguration {}
+
+impl TryFrom<ApplicationTrigger> for KeyValueTriggerConfiguration {
+    type Error = Error;
+
+    fn try_from(trigger: ApplicationTrigger) -> Result<Self, Self::Error> {
+        match trigger {
+            ApplicationTrigger::KeyValue(key_value) => Ok(key_value),
+            _ => Err(Error::InvalidTriggerType),
+        }
+    }
+}
+
 /// External trigger configuration
 #[derive(Clone, Debug, PartialEq)]
 pub struct ExternalTriggerConfiguration {
@@ -272,6 +321,27 @@ pub struct WasmConfig {
     pub key_value_stores: Vec<String>,
     /// Optional list of sqlite databases the component is allowed to use.
     pub sqlite_databases: Vec<String>,
+    /// Restrictions on the statements the component may execute against its
+    /// sqlite databases. Databases which are not listed allow all statements.
+    pub sqlite_database_access: HashMap<String, SqliteDatabaseAccess>,
+    /// The directory of versioned migrations declared for each of the
+    /// component's sqlite databases, if any.
+    pub sqlite_database_migrations: HashMap<String, PathBuf>,
+    /// Optional list of AI models the component is allowed to use.
+    pub ai_models: Vec<String>,
+}
+
+/// The statements a component may execute against a sqlite database.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SqliteDatabaseAccess {
+    /// Any statement, including those which change the schema.
+    #[default]
+    ReadWrite,
+    /// Only statements which read data.
+    ReadOnly,
+    /// Statements which read or modify data, but not the schema.
+    DmlOnly,
 }
 
 /// Directory mount for the assets of a component.
@@ -402,6 +472,17 @@ impl Default for RedisExecutor {
     }
 }
 
+/// Configuration for the key-value change trigger.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct KeyValueConfig {
+    /// Key-value store to watch for changes.
+    pub store: String,
+    /// Only changes to keys starting with this prefix invoke the component.
+    #[serde(default)]
+    pub prefix: String,
+}
+
 /// Trigger configuration.
 #[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(deny_unknown_fields, rename_all = "camelCase", untagged)]
@@ -412,6 +493,10 @@ pub enum TriggerConfig {
     Redis(RedisConfig),
     /// External trigger configuration
     External(HashMap<String, toml::Value>),
+    /// Key-value change trigger configuration. This follows `External` so
+    /// that an external trigger's parameters are never mistaken for it, which
+    /// means loaders must parse it according to the application trigger type.
+    KeyValue(KeyValueConfig),
 }
 
 impl Default for TriggerConfig {
@@ -441,3 +526,14 @@ impl TryFrom<TriggerConfig> for RedisConfig {
         }
     }
 }
+
+impl TryFrom<TriggerConfig> for KeyValueConfig {
+    type Error = Error;
+
+    fn try_from(trigger: TriggerConfig) -> Result<Self, Self::Error> {
+        match trigger {
+            TriggerConfig::KeyValue(key_value) => Ok(key_value),
+            _ => Err(Error::InvalidTriggerType),
+        }
+    }
+}
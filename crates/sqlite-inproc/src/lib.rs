@@ -1,11 +1,13 @@
 use std::{
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::{Arc, Mutex},
+    time::{Duration, Instant},
 };
 
 use anyhow::Context;
 use async_trait::async_trait;
-use spin_sqlite::Connection;
+use rusqlite::hooks::{AuthAction, AuthContext, Authorization};
+use spin_sqlite::{Connection, DatabaseAccess, Transaction};
 
 #[derive(Debug, Clone)]
 pub enum InProcDatabaseLocation {
@@ -15,34 +17,48 @@ pub enum InProcDatabaseLocation {
 
 /// A connection to a sqlite database
 pub struct InProcConnection {
+    /// The path or URI from which to open further connections to the same
+    /// database, e.g. for transactions.
+    target: PathBuf,
     connection: Arc<Mutex<rusqlite::Connection>>,
 }
 
 impl InProcConnection {
     pub fn new(location: InProcDatabaseLocation) -> Result<Self, spin_world::sqlite::Error> {
-        let connection = {
-            let c = match &location {
-                InProcDatabaseLocation::InMemory => rusqlite::Connection::open_in_memory(),
-                InProcDatabaseLocation::Path(path) => rusqlite::Connection::open(path),
-            }
-            .map_err(|e| spin_world::sqlite::Error::Io(e.to_string()))?;
-            Arc::new(Mutex::new(c))
+        let target = match location {
+            // A named in-memory database with a shared cache can be opened by
+            // several connections, and lives as long as any of them is open.
+            // Shared-cache locking is per table, so queries on a table written
+            // by an open transaction wait until it finishes (see
+            // `execute_query`).
+            InProcDatabaseLocation::InMemory => PathBuf::from(format!(
+                "file:spin-{:016x}?mode=memory&cache=shared",
+                rand::random::<u64>()
+            )),
+            InProcDatabaseLocation::Path(path) => path,
         };
-        Ok(Self { connection })
+        let connection = Arc::new(Mutex::new(open(&target)?));
+        Ok(Self { target, connection })
     }
 }
 
+fn open(target: &Path) -> Result<rusqlite::Connection, spin_world::sqlite::Error> {
+    // The default flags include SQLITE_OPEN_URI, which in-memory targets rely on.
+    rusqlite::Connection::open(target).map_err(|e| spin_world::sqlite::Error::Io(e.to_string()))
+}
+
 #[async_trait]
 impl Connection for InProcConnection {
-    async fn query(
+    async fn query_with_access(
         &self,
         query: &str,
         parameters: Vec<spin_world::sqlite::Value>,
+        access: DatabaseAccess,
     ) -> Result<spin_world::sqlite::QueryResult, spin_world::sqlite::Error> {
         let connection = self.connection.clone();
         let query = query.to_owned();
         // Tell the tokio runtime that we're going to block while making the query
-        tokio::task::spawn_blocking(move || execute_query(&connection, &query, parameters))
+        tokio::task::spawn_blocking(move || execute_query(&connection, &query, parameters, access))
             .await
             .context("internal runtime error")
             .map_err(|e| spin_world::sqlite::Error::Io(e.to_string()))?
@@ -60,43 +76,259 @@ impl Connection for InProcConnection {
         .context("failed to spawn blocking task")?;
         Ok(())
     }
+
+    async fn begin_transaction(
+        &self,
+        access: DatabaseAccess,
+    ) -> Result<Box<dyn Transaction>, spin_world::sqlite::Error> {
+        let target = self.target.clone();
+        let connection = tokio::task::spawn_blocking(move || {
+            let connection = open(&target)?;
+            connection
+                .execute_batch("BEGIN")
+                .map_err(|e| spin_world::sqlite::Error::Io(e.to_string()))?;
+            Ok(connection)
+        })
+        .await
+        .context("internal runtime error")
+        .map_err(|e| spin_world::sqlite::Error::Io(e.to_string()))??;
+        Ok(Box::new(InProcTransaction {
+            connection: Arc::new(Mutex::new(connection)),
+            access,
+        }))
+    }
+
+    async fn backup(&self, destination: &Path) -> anyhow::Result<()> {
+        let target = self.target.clone();
+        let destination = destination.to_owned();
+        // Back up from a separate connection so that queries aren't blocked
+        // on this one while the backup runs.
+        tokio::task::spawn_blocking(move || backup_database(&open(&target)?, &destination))
+            .await
+            .context("failed to spawn blocking task")?
+    }
+
+    async fn restore(&self, source: &Path) -> anyhow::Result<()> {
+        let target = self.target.clone();
+        let source = source.to_owned();
+        tokio::task::spawn_blocking(move || restore_database(&mut open(&target)?, &source))
+            .await
+            .context("failed to spawn blocking task")?
+    }
+}
+
+/// Writes a consistent snapshot of `connection`'s database to `destination`
+/// with SQLite's online backup API. The snapshot is written alongside
+/// `destination` and then moved into place, so `destination` always holds a
+/// complete backup.
+fn backup_database(connection: &rusqlite::Connection, destination: &Path) -> anyhow::Result<()> {
+    let mut partial = destination.as_os_str().to_owned();
+    partial.push(".partial");
+    let _ = std::fs::remove_file(&partial);
+    connection
+        .backup(rusqlite::DatabaseName::Main, &partial, None)
+        .with_context(|| format!("failed to back up database to {}", destination.display()))?;
+    std::fs::rename(&partial, destination)
+        .with_context(|| format!("failed to move backup to {}", destination.display()))
+}
+
+/// Replaces the contents of `connection`'s database with the backup at
+/// `source`. Other connections see the restored contents once it completes.
+fn restore_database(connection: &mut rusqlite::Connection, source: &Path) -> anyhow::Result<()> {
+    // Opening a missing file would create an empty database and restore that.
+    anyhow::ensure!(
+        source.is_file(),
+        "backup {} does not exist",
+        source.display()
+    );
+    connection
+        .restore(
+            rusqlite::DatabaseName::Main,
+            source,
+            None::<fn(rusqlite::backup::Progress)>,
+        )
+        .with_context(|| format!("failed to restore database from {}", source.display()))
+}
+
+/// A transaction on its own dedicated connection, so that it is isolated from
+/// other users of the database until it is committed.
+///
+/// SQLite rolls back any open transaction when its connection is closed, so
+/// dropping an uncommitted `InProcTransaction` rolls it back.
+struct InProcTransaction {
+    connection: Arc<Mutex<rusqlite::Connection>>,
+    access: DatabaseAccess,
+}
+
+impl InProcTransaction {
+    async fn finish(&self, statement: &'static str) -> Result<(), spin_world::sqlite::Error> {
+        let connection = self.connection.clone();
+        tokio::task::spawn_blocking(move || {
+            connection
+                .lock()
+                .unwrap()
+                .execute_batch(statement)
+                .map_err(|e| spin_world::sqlite::Error::Io(e.to_string()))
+        })
+        .await
+        .context("internal runtime error")
+        .map_err(|e| spin_world::sqlite::Error::Io(e.to_string()))?
+    }
+}
+
+#[async_trait]
+impl Transaction for InProcTransaction {
+    async fn query(
+        &self,
+        query: &str,
+        parameters: Vec<spin_world::sqlite::Value>,
+    ) -> Result<spin_world::sqlite::QueryResult, spin_world::sqlite::Error> {
+        let connection = self.connection.clone();
+        let query = query.to_owned();
+        let access = self.access;
+        tokio::task::spawn_blocking(move || execute_query(&connection, &query, parameters, access))
+            .await
+            .context("internal runtime error")
+            .map_err(|e| spin_world::sqlite::Error::Io(e.to_string()))?
+    }
+
+    async fn execute_batch(&self, statements: &str) -> anyhow::Result<()> {
+        let connection = self.connection.clone();
+        let statements = statements.to_owned();
+        let access = self.access;
+        tokio::task::spawn_blocking(move || {
+            let conn = connection.lock().unwrap();
+            if access != DatabaseAccess::ReadWrite {
+                conn.authorizer(Some(move |context: AuthContext<'_>| {
+                    authorize(access, context)
+                }));
+            }
+            let result = conn
+                .execute_batch(&statements)
+                .context("failed to execute batch statements");
+            conn.authorizer(None::<fn(AuthContext<'_>) -> Authorization>);
+            result
+        })
+        .await
+        .context("failed to spawn blocking task")?
+    }
+
+    async fn commit(self: Box<Self>) -> Result<(), spin_world::sqlite::Error> {
+        self.finish("COMMIT").await
+    }
+
+    async fn rollback(self: Box<Self>) -> Result<(), spin_world::sqlite::Error> {
+        self.finish("ROLLBACK").await
+    }
 }
 
+/// How long a query waits for a table locked by a transaction on another
+/// connection to the same in-memory database. This matches the busy timeout
+/// which rusqlite sets for file databases.
+const LOCKED_TIMEOUT: Duration = Duration::from_secs(5);
+
 fn execute_query(
     connection: &Mutex<rusqlite::Connection>,
     query: &str,
     parameters: Vec<spin_world::sqlite::Value>,
+    access: DatabaseAccess,
 ) -> Result<spin_world::sqlite::QueryResult, spin_world::sqlite::Error> {
     let conn = connection.lock().unwrap();
-    let mut statement = conn
-        .prepare_cached(query)
-        .map_err(|e| spin_world::sqlite::Error::Io(e.to_string()))?;
+    // Shared-cache table locks fail immediately rather than invoking the busy
+    // handler, so wait for them here as SQLite would for a file database.
+    let started = Instant::now();
+    loop {
+        match try_execute_query(&conn, query, parameters.clone(), access) {
+            Err(e)
+                if e.sqlite_error_code() == Some(rusqlite::ErrorCode::DatabaseLocked)
+                    && started.elapsed() < LOCKED_TIMEOUT =>
+            {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            result => return result.map_err(convert_error),
+        }
+    }
+}
+
+fn try_execute_query(
+    conn: &rusqlite::Connection,
+    query: &str,
+    parameters: Vec<spin_world::sqlite::Value>,
+    access: DatabaseAccess,
+) -> rusqlite::Result<spin_world::sqlite::QueryResult> {
+    if access == DatabaseAccess::ReadWrite {
+        let mut statement = conn.prepare_cached(query)?;
+        return execute_statement(&mut statement, parameters);
+    }
+
+    // Statements are authorized as they are prepared, so restricted queries
+    // bypass the statement cache, which may hold statements prepared without
+    // the authorizer.
+    conn.authorizer(Some(move |context: AuthContext<'_>| {
+        authorize(access, context)
+    }));
+    let result = conn
+        .prepare(query)
+        .and_then(|mut statement| execute_statement(&mut statement, parameters));
+    conn.authorizer(None::<fn(AuthContext<'_>) -> Authorization>);
+    result
+}
+
+/// Allows only the actions permitted by `access`.
+fn authorize(access: DatabaseAccess, context: AuthContext<'_>) -> Authorization {
+    let allowed = match context.action {
+        AuthAction::Select
+        | AuthAction::Read { .. }
+        | AuthAction::Function { .. }
+        | AuthAction::Recursive => true,
+        AuthAction::Insert { .. }
+        | AuthAction::Update { .. }
+        | AuthAction::Delete { .. }
+        | AuthAction::Transaction { .. }
+        | AuthAction::Savepoint { .. } => access != DatabaseAccess::ReadOnly,
+        _ => access == DatabaseAccess::ReadWrite,
+    };
+    if allowed {
+        Authorization::Allow
+    } else {
+        Authorization::Deny
+    }
+}
+
+fn convert_error(error: rusqlite::Error) -> spin_world::sqlite::Error {
+    match error.sqlite_error_code() {
+        Some(rusqlite::ErrorCode::AuthorizationForStatementDenied) => {
+            spin_world::sqlite::Error::AccessDenied
+        }
+        _ => spin_world::sqlite::Error::Io(error.to_string()),
+    }
+}
+
+fn execute_statement(
+    statement: &mut rusqlite::Statement,
+    parameters: Vec<spin_world::sqlite::Value>,
+) -> rusqlite::Result<spin_world::sqlite::QueryResult> {
     let columns = statement
         .column_names()
         .into_iter()
         .map(ToOwned::to_owned)
         .collect();
-    let rows = statement
-        .query_map(
-            rusqlite::params_from_iter(convert_data(parameters.into_iter())),
-            |row| {
-                let mut values = vec![];
-                for column in 0.. {
-                    let value = row.get::<usize, ValueWrapper>(column);
-                    if let Err(rusqlite::Error::InvalidColumnIndex(_)) = value {
-                        break;
-                    }
-                    let value = value?.0;
-                    values.push(value);
+    let rows = statement.query_map(
+        rusqlite::params_from_iter(convert_data(parameters.into_iter())),
+        |row| {
+            let mut values = vec![];
+            for column in 0.. {
+                let value = row.get::<usize, ValueWrapper>(column);
+                if let Err(rusqlite::Error::InvalidColumnIndex(_)) = value {
+                    break;
                 }
-                Ok(spin_world::sqlite::RowResult { values })
-            },
-        )
-        .map_err(|e| spin_world::sqlite::Error::Io(e.to_string()))?;
-    let rows = rows
-        .into_iter()
-        .map(|r| r.map_err(|e| spin_world::sqlite::Error::Io(e.to_string())))
-        .collect::<Result<_, spin_world::sqlite::Error>>()?;
+                let value = value?.0;
+                values.push(value);
+            }
+            Ok(spin_world::sqlite::RowResult { values })
+        },
+    )?;
+    let rows = rows.collect::<rusqlite::Result<_>>()?;
     Ok(spin_world::sqlite::QueryResult { columns, rows })
 }
 
@@ -129,3 +361,195 @@ impl rusqlite::types::FromSql for ValueWrapper {
         Ok(ValueWrapper(value))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn access_is_enforced() {
+        use DatabaseAccess::*;
+
+        let conn = InProcConnection::new(InProcDatabaseLocation::InMemory).unwrap();
+        conn.query("CREATE TABLE t (x INTEGER)", vec![])
+            .await
+            .unwrap();
+        // Cache a statement prepared without restrictions.
+        conn.query("INSERT INTO t VALUES (1)", vec![])
+            .await
+            .unwrap();
+
+        for (query, access, allowed) in [
+            ("SELECT * FROM t", ReadOnly, true),
+            ("INSERT INTO t VALUES (1)", ReadOnly, false),
+            ("INSERT INTO t VALUES (1)", DmlOnly, true),
+            ("DELETE FROM t", DmlOnly, true),
+            ("CREATE TABLE u (y)", DmlOnly, false),
+            ("PRAGMA user_version = 1", DmlOnly, false),
+            ("CREATE TABLE u (y)", ReadWrite, true),
+        ] {
+            let result = conn.query_with_access(query, vec![], access).await;
+            match result {
+                Ok(_) => assert!(allowed, "{query} should be denied with {access:?}"),
+                Err(spin_world::sqlite::Error::AccessDenied) => {
+                    assert!(!allowed, "{query} should be allowed with {access:?}")
+                }
+                Err(e) => panic!("unexpected error for {query}: {e:?}"),
+            }
+        }
+
+        let tx = conn.begin_transaction(ReadOnly).await.unwrap();
+        assert!(matches!(
+            tx.query("INSERT INTO t VALUES (2)", vec![]).await,
+            Err(spin_world::sqlite::Error::AccessDenied)
+        ));
+        tx.commit().await.unwrap();
+    }
+
+    async fn count(conn: &dyn Connection) -> Result<i64, spin_world::sqlite::Error> {
+        let result = conn.query("SELECT count(*) FROM t", vec![]).await?;
+        match result.rows[0].values[..] {
+            [spin_world::sqlite::Value::Integer(count)] => Ok(count),
+            ref values => panic!("unexpected count {values:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn transactions_commit_and_roll_back() {
+        let dir = tempfile::tempdir().unwrap();
+        for location in [
+            InProcDatabaseLocation::InMemory,
+            InProcDatabaseLocation::Path(dir.path().join("db.sqlite")),
+        ] {
+            let conn = InProcConnection::new(location.clone()).unwrap();
+            conn.query("CREATE TABLE t (x INTEGER)", vec![])
+                .await
+                .unwrap();
+
+            let tx = conn
+                .begin_transaction(DatabaseAccess::ReadWrite)
+                .await
+                .unwrap();
+            tx.query("INSERT INTO t VALUES (1)", vec![]).await.unwrap();
+            tx.commit().await.unwrap();
+            assert_eq!(1, count(&conn).await.unwrap(), "commit in {location:?}");
+
+            let tx = conn
+                .begin_transaction(DatabaseAccess::ReadWrite)
+                .await
+                .unwrap();
+            tx.query("INSERT INTO t VALUES (2)", vec![]).await.unwrap();
+            tx.rollback().await.unwrap();
+            assert_eq!(1, count(&conn).await.unwrap(), "rollback in {location:?}");
+
+            let tx = conn
+                .begin_transaction(DatabaseAccess::ReadWrite)
+                .await
+                .unwrap();
+            tx.query("INSERT INTO t VALUES (3)", vec![]).await.unwrap();
+            drop(tx);
+            assert_eq!(1, count(&conn).await.unwrap(), "drop in {location:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn uncommitted_changes_are_isolated_in_file_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let conn =
+            InProcConnection::new(InProcDatabaseLocation::Path(dir.path().join("db.sqlite")))
+                .unwrap();
+        conn.query("CREATE TABLE t (x INTEGER)", vec![])
+            .await
+            .unwrap();
+
+        let tx = conn
+            .begin_transaction(DatabaseAccess::ReadWrite)
+            .await
+            .unwrap();
+        tx.query("INSERT INTO t VALUES (1)", vec![]).await.unwrap();
+        assert_eq!(0, count(&conn).await.unwrap());
+        tx.commit().await.unwrap();
+        assert_eq!(1, count(&conn).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn uncommitted_changes_are_isolated_in_memory_database() {
+        let conn = Arc::new(InProcConnection::new(InProcDatabaseLocation::InMemory).unwrap());
+        conn.query("CREATE TABLE t (x INTEGER)", vec![])
+            .await
+            .unwrap();
+
+        let tx = conn
+            .begin_transaction(DatabaseAccess::ReadWrite)
+            .await
+            .unwrap();
+        tx.query("INSERT INTO t VALUES (1)", vec![]).await.unwrap();
+
+        // The table is locked by the transaction, so reading it waits for the
+        // transaction to finish rather than failing or seeing its changes.
+        let reader = tokio::spawn({
+            let conn = conn.clone();
+            async move { count(conn.as_ref()).await }
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(!reader.is_finished());
+        tx.commit().await.unwrap();
+        assert_eq!(1, reader.await.unwrap().unwrap());
+    }
+
+    #[tokio::test]
+    async fn concurrent_migrations_are_applied_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = dir.path().join("db.sqlite");
+        let migrations = dir.path().join("migrations");
+        std::fs::create_dir(&migrations).unwrap();
+        for version in 1..=5 {
+            std::fs::write(
+                migrations.join(format!("{version}_create.sql")),
+                format!("CREATE TABLE t{version} (x); INSERT INTO t{version} VALUES (1);"),
+            )
+            .unwrap();
+        }
+
+        let migrators = (0..4).map(|_| {
+            let database = database.clone();
+            let migrations = migrations.clone();
+            tokio::spawn(async move {
+                let conn = InProcConnection::new(InProcDatabaseLocation::Path(database)).unwrap();
+                spin_sqlite::migrations::migrate(&conn, &migrations).await
+            })
+        });
+        let mut applied = 0;
+        for migrator in migrators.collect::<Vec<_>>() {
+            applied += migrator.await.unwrap().unwrap().len();
+        }
+        assert_eq!(5, applied);
+    }
+
+    #[tokio::test]
+    async fn migration_status_does_not_modify_the_database() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("1_create.sql"), "CREATE TABLE t (x);").unwrap();
+        let conn = InProcConnection::new(InProcDatabaseLocation::InMemory).unwrap();
+
+        let status = spin_sqlite::migrations::status(&conn, dir.path())
+            .await
+            .unwrap();
+        assert!(status.applied.is_empty());
+        assert_eq!(1, status.pending.len());
+        let tables = conn
+            .query("SELECT name FROM sqlite_master", vec![])
+            .await
+            .unwrap();
+        assert!(tables.rows.is_empty());
+
+        spin_sqlite::migrations::migrate(&conn, dir.path())
+            .await
+            .unwrap();
+        let status = spin_sqlite::migrations::status(&conn, dir.path())
+            .await
+            .unwrap();
+        assert_eq!(1, status.applied.len());
+        assert!(status.pending.is_empty());
+    }
+}
@@ -1,3 +1,4 @@
+use spin_sqlite::DatabaseAccess;
 use spin_world::sqlite::{self, RowResult};
 
 #[derive(Clone)]
@@ -18,14 +19,16 @@ impl LibsqlClient {
 
 #[async_trait::async_trait]
 impl spin_sqlite::Connection for LibsqlClient {
-    async fn query(
+    async fn query_with_access(
         &self,
         query: &str,
         parameters: Vec<sqlite::Value>,
+        access: DatabaseAccess,
     ) -> Result<sqlite::QueryResult, sqlite::Error> {
+        let client = libsql_client::Client::Http(self.inner.clone());
+        check_access(&client, None, query, access).await?;
         let stmt =
             libsql_client::statement::Statement::with_args(query, &convert_parameters(&parameters));
-        let client = self.inner.clone();
 
         let result = client
             .execute(stmt)
@@ -41,20 +44,233 @@ impl spin_sqlite::Connection for LibsqlClient {
     async fn execute_batch(&self, statements: &str) -> anyhow::Result<()> {
         let client = libsql_client::Client::Http(self.inner.clone());
 
-        // Unfortunately, the libsql library requires that the statements are already split
-        // into individual statement strings which requires us to parse the supplied SQL string.
-        let stmts = sqlparser::parser::Parser::parse_sql(
-            &sqlparser::dialect::SQLiteDialect {},
-            statements,
-        )?
-        .into_iter()
-        .map(|st| st.to_string())
-        .map(libsql_client::Statement::from);
+        let stmts = split_statements(statements)?
+            .into_iter()
+            .map(libsql_client::Statement::from);
 
         let _ = client.batch(stmts).await?;
 
         Ok(())
     }
+
+    async fn begin_transaction(
+        &self,
+        access: DatabaseAccess,
+    ) -> Result<Box<dyn spin_sqlite::Transaction>, sqlite::Error> {
+        let transaction = LibsqlTransaction {
+            inner: self.inner.clone(),
+            // The client tracks the server-side stream for each transaction
+            // by an id of our choosing.
+            id: rand::random(),
+            access,
+            finished: false,
+        };
+        transaction
+            .client()
+            .execute_in_transaction(transaction.id, libsql_client::Statement::from("BEGIN"))
+            .await
+            .map_err(|e| sqlite::Error::Io(e.to_string()))?;
+        Ok(Box::new(transaction))
+    }
+}
+
+/// An interactive transaction on a libSQL server.
+struct LibsqlTransaction {
+    inner: libsql_client::http::Client,
+    id: u64,
+    access: DatabaseAccess,
+    finished: bool,
+}
+
+impl LibsqlTransaction {
+    fn client(&self) -> libsql_client::Client {
+        libsql_client::Client::Http(self.inner.clone())
+    }
+}
+
+#[async_trait::async_trait]
+impl spin_sqlite::Transaction for LibsqlTransaction {
+    async fn query(
+        &self,
+        query: &str,
+        parameters: Vec<sqlite::Value>,
+    ) -> Result<sqlite::QueryResult, sqlite::Error> {
+        check_access(&self.client(), Some(self.id), query, self.access).await?;
+        let stmt =
+            libsql_client::statement::Statement::with_args(query, &convert_parameters(&parameters));
+
+        let result = self
+            .client()
+            .execute_in_transaction(self.id, stmt)
+            .await
+            .map_err(|e| sqlite::Error::Io(e.to_string()))?;
+
+        Ok(sqlite::QueryResult {
+            columns: result.columns,
+            rows: convert_rows(result.rows),
+        })
+    }
+
+    async fn execute_batch(&self, statements: &str) -> anyhow::Result<()> {
+        for statement in split_statements(statements)? {
+            check_access(&self.client(), Some(self.id), &statement, self.access).await?;
+            self.client()
+                .execute_in_transaction(self.id, libsql_client::Statement::from(statement))
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn commit(mut self: Box<Self>) -> Result<(), sqlite::Error> {
+        self.finished = true;
+        self.client()
+            .commit_transaction(self.id)
+            .await
+            .map_err(|e| sqlite::Error::Io(e.to_string()))
+    }
+
+    async fn rollback(mut self: Box<Self>) -> Result<(), sqlite::Error> {
+        self.finished = true;
+        self.client()
+            .rollback_transaction(self.id)
+            .await
+            .map_err(|e| sqlite::Error::Io(e.to_string()))
+    }
+}
+
+impl Drop for LibsqlTransaction {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+        // Roll back in the background; if there is no runtime to do so, the
+        // server will roll back once the stream times out.
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            let client = self.client();
+            let id = self.id;
+            handle.spawn(async move {
+                if let Err(e) = client.rollback_transaction(id).await {
+                    tracing::warn!("Failed to roll back libSQL transaction: {e}");
+                }
+            });
+        }
+    }
+}
+
+/// Splits `statements` into individual statements.
+///
+/// Unfortunately, the libsql library requires that the statements are already
+/// split into individual statement strings which requires us to parse the
+/// supplied SQL string.
+fn split_statements(statements: &str) -> anyhow::Result<Vec<String>> {
+    Ok(
+        sqlparser::parser::Parser::parse_sql(&sqlparser::dialect::SQLiteDialect {}, statements)?
+            .into_iter()
+            .map(|st| st.to_string())
+            .collect(),
+    )
+}
+
+/// Checks that `query` is permitted by `access`.
+///
+/// Unlike the in-process implementation, libSQL servers don't expose an
+/// authorizer, so the statement is inspected before it is sent. Statements
+/// which can't be parsed here are explained by the server (in `transaction`,
+/// if given, so that they can refer to tables created in it) and checked by
+/// the bytecode they compile to.
+async fn check_access(
+    client: &libsql_client::Client,
+    transaction: Option<u64>,
+    query: &str,
+    access: DatabaseAccess,
+) -> Result<(), sqlite::Error> {
+    if access == DatabaseAccess::ReadWrite {
+        return Ok(());
+    }
+    let allowed = match parsed_access(query, access) {
+        Some(allowed) => allowed,
+        // `EXPLAIN` only covers the first of several statements.
+        None if query.trim_end().trim_end_matches(';').contains(';') => false,
+        None => {
+            let explain = libsql_client::Statement::from(format!("EXPLAIN {query}"));
+            let result = match transaction {
+                Some(id) => client.execute_in_transaction(id, explain).await,
+                None => client.execute(explain).await,
+            }
+            .map_err(|e| sqlite::Error::Io(e.to_string()))?;
+            explained_access(
+                &sqlite::QueryResult {
+                    columns: result.columns,
+                    rows: convert_rows(result.rows),
+                },
+                access,
+            )
+        }
+    };
+    if allowed {
+        Ok(())
+    } else {
+        Err(sqlite::Error::AccessDenied)
+    }
+}
+
+/// Whether the statements in `query` are permitted by `access`, or `None` if
+/// `query` can't be parsed.
+fn parsed_access(query: &str, access: DatabaseAccess) -> Option<bool> {
+    use sqlparser::ast::Statement;
+
+    let statements =
+        sqlparser::parser::Parser::parse_sql(&sqlparser::dialect::SQLiteDialect {}, query).ok()?;
+    Some(statements.iter().all(|statement| match statement {
+        Statement::Query(_) => true,
+        Statement::Insert { .. } | Statement::Update { .. } | Statement::Delete { .. } => {
+            access == DatabaseAccess::DmlOnly
+        }
+        _ => false,
+    }))
+}
+
+/// Opcodes which change the schema or other database-wide state.
+const SCHEMA_OPCODES: &[&str] = &[
+    "CreateBtree",
+    "Destroy",
+    "DropIndex",
+    "DropTable",
+    "DropTrigger",
+    "IncrVacuum",
+    "JournalMode",
+    "ParseSchema",
+    "SetCookie",
+    "VCreate",
+    "VDestroy",
+    "Vacuum",
+];
+
+/// Whether the bytecode listed by `EXPLAIN` for a statement is permitted by
+/// `access`.
+fn explained_access(explain: &sqlite::QueryResult, access: DatabaseAccess) -> bool {
+    let column = |name: &str| explain.columns.iter().position(|c| c == name);
+    let (Some(opcode_column), Some(p2_column)) = (column("opcode"), column("p2")) else {
+        return false;
+    };
+    explain.rows.iter().all(|row| {
+        let opcode = match row.values.get(opcode_column) {
+            Some(sqlite::Value::Text(opcode)) => opcode.as_str(),
+            _ => return false,
+        };
+        if SCHEMA_OPCODES.contains(&opcode) {
+            return false;
+        }
+        if access != DatabaseAccess::ReadOnly {
+            return true;
+        }
+        match opcode {
+            // A non-zero P2 begins a write transaction.
+            "Transaction" => matches!(row.values.get(p2_column), Some(sqlite::Value::Integer(0))),
+            "OpenWrite" | "VUpdate" => false,
+            _ => true,
+        }
+    })
 }
 
 fn convert_rows(rows: Vec<libsql_client::Row>) -> Vec<RowResult> {
@@ -92,3 +308,66 @@ fn convert_parameters(parameters: &[sqlite::Value]) -> Vec<libsql_client::Value>
         })
         .collect()
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use DatabaseAccess::*;
+
+    #[test]
+    fn parsed_statements_are_checked() {
+        for (query, access, allowed) in [
+            ("SELECT * FROM t", ReadOnly, true),
+            ("INSERT INTO t VALUES (1)", ReadOnly, false),
+            ("INSERT INTO t VALUES (1)", DmlOnly, true),
+            ("DELETE FROM t", DmlOnly, true),
+            ("CREATE TABLE u (y INTEGER)", DmlOnly, false),
+            ("SELECT 1; DROP TABLE t", ReadOnly, false),
+        ] {
+            assert_eq!(
+                Some(allowed),
+                parsed_access(query, access),
+                "{query} with {access:?}"
+            );
+        }
+        // Valid SQLite, but sqlparser requires column types
+        assert_eq!(None, parsed_access("CREATE TABLE u (y)", DmlOnly));
+    }
+
+    fn explain(opcodes: &[(&str, i64)]) -> sqlite::QueryResult {
+        sqlite::QueryResult {
+            columns: ["addr", "opcode", "p1", "p2"].map(String::from).to_vec(),
+            rows: opcodes
+                .iter()
+                .enumerate()
+                .map(|(addr, (opcode, p2))| RowResult {
+                    values: vec![
+                        sqlite::Value::Integer(addr as i64),
+                        sqlite::Value::Text(opcode.to_string()),
+                        sqlite::Value::Integer(0),
+                        sqlite::Value::Integer(*p2),
+                    ],
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn explained_statements_are_checked() {
+        let read = explain(&[("Init", 0), ("Transaction", 0), ("OpenRead", 2)]);
+        let write = explain(&[("Init", 0), ("Transaction", 1), ("OpenWrite", 2)]);
+        let schema = explain(&[("Init", 0), ("Transaction", 1), ("CreateBtree", 0)]);
+
+        assert!(explained_access(&read, ReadOnly));
+        assert!(!explained_access(&write, ReadOnly));
+        assert!(explained_access(&write, DmlOnly));
+        assert!(!explained_access(&schema, DmlOnly));
+        assert!(!explained_access(
+            &sqlite::QueryResult {
+                columns: vec![],
+                rows: vec![]
+            },
+            DmlOnly
+        ));
+    }
+}
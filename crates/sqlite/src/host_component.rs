@@ -1,6 +1,6 @@
 use std::sync::Arc;
 
-use crate::{ConnectionsStore, SqliteDispatch, DATABASES_KEY};
+use crate::{ConnectionsStore, SqliteDispatch, DATABASES_KEY, DATABASE_ACCESS_KEY};
 use anyhow::anyhow;
 use spin_app::{AppComponent, DynamicHostComponent};
 use spin_core::HostComponent;
@@ -64,7 +64,14 @@ impl DynamicHostComponent for SqliteComponent {
         let allowed_databases = component
             .get_metadata(crate::DATABASES_KEY)?
             .unwrap_or_default();
-        data.component_init(allowed_databases, (self.init_connections_store)(component));
+        let database_access = component
+            .get_metadata(DATABASE_ACCESS_KEY)?
+            .unwrap_or_default();
+        data.component_init(
+            allowed_databases,
+            database_access,
+            (self.init_connections_store)(component),
+        );
         Ok(())
     }
 
@@ -1,12 +1,27 @@
 mod host_component;
+pub mod migrations;
 
+use anyhow::Context;
 use spin_app::{async_trait, MetadataKey};
-use spin_key_value::table;
-use std::{collections::HashSet, sync::Arc};
+use spin_core::table;
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 pub use host_component::SqliteComponent;
 
 pub const DATABASES_KEY: MetadataKey<HashSet<String>> = MetadataKey::new("databases");
+pub const DATABASE_ACCESS_KEY: MetadataKey<HashMap<String, DatabaseAccess>> =
+    MetadataKey::new("database_access");
+/// The directory of versioned migrations declared for each database
+pub const DATABASE_MIGRATIONS_KEY: MetadataKey<HashMap<String, PathBuf>> =
+    MetadataKey::new("database_migrations");
+
+/// The statements a component may execute against a database, as declared
+/// in its manifest
+pub use spin_manifest::SqliteDatabaseAccess as DatabaseAccess;
 
 /// A store of connections for all accessible databases for an application
 #[async_trait]
@@ -27,15 +42,107 @@ pub trait Connection: Send + Sync {
         &self,
         query: &str,
         parameters: Vec<spin_world::sqlite::Value>,
+    ) -> Result<spin_world::sqlite::QueryResult, spin_world::sqlite::Error> {
+        self.query_with_access(query, parameters, DatabaseAccess::ReadWrite)
+            .await
+    }
+
+    /// Execute a query, failing with `Error::AccessDenied` if it isn't
+    /// permitted by `access`.
+    async fn query_with_access(
+        &self,
+        query: &str,
+        parameters: Vec<spin_world::sqlite::Value>,
+        access: DatabaseAccess,
     ) -> Result<spin_world::sqlite::QueryResult, spin_world::sqlite::Error>;
 
     async fn execute_batch(&self, statements: &str) -> anyhow::Result<()>;
+
+    /// Begin a transaction which is isolated from other queries on this
+    /// connection until it is committed. Queries in the transaction are
+    /// restricted by `access`.
+    async fn begin_transaction(
+        &self,
+        access: DatabaseAccess,
+    ) -> Result<Box<dyn Transaction>, spin_world::sqlite::Error>;
+
+    /// Write a consistent snapshot of the database to `destination` without
+    /// blocking other users of the database.
+    async fn backup(&self, _destination: &Path) -> anyhow::Result<()> {
+        anyhow::bail!("backups are not supported for this database")
+    }
+
+    /// Replace the contents of the database with a snapshot written by
+    /// [`Connection::backup`].
+    async fn restore(&self, _source: &Path) -> anyhow::Result<()> {
+        anyhow::bail!("restoring backups is not supported for this database")
+    }
+}
+
+/// A transaction begun with [`Connection::begin_transaction`]
+///
+/// Implementations must roll the transaction back if it is dropped without
+/// being committed.
+#[async_trait]
+pub trait Transaction: Send + Sync {
+    async fn query(
+        &self,
+        query: &str,
+        parameters: Vec<spin_world::sqlite::Value>,
+    ) -> Result<spin_world::sqlite::QueryResult, spin_world::sqlite::Error>;
+
+    /// Execute one or more statements, which may not take parameters.
+    async fn execute_batch(&self, statements: &str) -> anyhow::Result<()>;
+
+    async fn commit(self: Box<Self>) -> Result<(), spin_world::sqlite::Error>;
+
+    async fn rollback(self: Box<Self>) -> Result<(), spin_world::sqlite::Error>;
+}
+
+/// A statement to execute with [`execute_statements`]
+#[derive(Clone, Debug)]
+pub enum Statement {
+    /// One or more statements without parameters, e.g. the contents of a SQL
+    /// file
+    Batch(String),
+    /// A single statement and its parameters
+    Query(String, Vec<spin_world::sqlite::Value>),
+}
+
+/// Execute `statements` in order in a single transaction, so that if any of
+/// them fails, none of them take effect.
+pub async fn execute_statements(
+    connection: &dyn Connection,
+    statements: &[Statement],
+) -> anyhow::Result<()> {
+    let transaction = connection
+        .begin_transaction(DatabaseAccess::ReadWrite)
+        .await
+        .context("failed to begin transaction")?;
+    for statement in statements {
+        match statement {
+            Statement::Batch(sql) => transaction.execute_batch(sql).await?,
+            Statement::Query(sql, parameters) => {
+                transaction
+                    .query(sql, parameters.clone())
+                    .await
+                    .with_context(|| format!("failed to execute statement: '{sql}'"))?;
+            }
+        }
+    }
+    // Dropping the transaction on error rolls it back.
+    transaction
+        .commit()
+        .await
+        .context("failed to commit transaction")
 }
 
 /// An implementation of the SQLite host
 pub struct SqliteDispatch {
     allowed_databases: HashSet<String>,
-    connections: table::Table<Arc<dyn Connection>>,
+    database_access: HashMap<String, DatabaseAccess>,
+    connections: table::Table<(Arc<dyn Connection>, DatabaseAccess)>,
+    transactions: table::Table<Box<dyn Transaction>>,
     connections_store: Arc<dyn ConnectionsStore>,
 }
 
@@ -43,7 +150,9 @@ impl SqliteDispatch {
     pub fn new(connections_store: Arc<dyn ConnectionsStore>) -> Self {
         Self {
             connections: table::Table::new(256),
+            transactions: table::Table::new(256),
             allowed_databases: HashSet::new(),
+            database_access: HashMap::new(),
             connections_store,
         }
     }
@@ -52,20 +161,32 @@ impl SqliteDispatch {
     pub fn component_init(
         &mut self,
         allowed_databases: HashSet<String>,
+        database_access: HashMap<String, DatabaseAccess>,
         connections_store: Arc<dyn ConnectionsStore>,
     ) {
         self.allowed_databases = allowed_databases;
+        self.database_access = database_access;
         self.connections_store = connections_store;
     }
 
     fn get_connection(
         &self,
         connection: spin_world::sqlite::Connection,
-    ) -> Result<&Arc<dyn Connection>, spin_world::sqlite::Error> {
+    ) -> Result<&(Arc<dyn Connection>, DatabaseAccess), spin_world::sqlite::Error> {
         self.connections
             .get(connection)
             .ok_or(spin_world::sqlite::Error::InvalidConnection)
     }
+
+    fn get_transaction(
+        &self,
+        transaction: spin_world::sqlite::Transaction,
+    ) -> Result<&dyn Transaction, spin_world::sqlite::Error> {
+        self.transactions
+            .get(transaction)
+            .map(|t| t.as_ref())
+            .ok_or(spin_world::sqlite::Error::InvalidConnection)
+    }
 }
 
 #[async_trait]
@@ -77,6 +198,11 @@ impl spin_world::sqlite::Host for SqliteDispatch {
         if !self.allowed_databases.contains(&database) {
             return Ok(Err(spin_world::sqlite::Error::AccessDenied));
         }
+        let access = self
+            .database_access
+            .get(&database)
+            .copied()
+            .unwrap_or_default();
         Ok(self
             .connections_store
             .get_connection(&database)
@@ -84,7 +210,7 @@ impl spin_world::sqlite::Host for SqliteDispatch {
             .and_then(|conn| conn.ok_or(spin_world::sqlite::Error::NoSuchDatabase))
             .and_then(|conn| {
                 self.connections
-                    .push(conn)
+                    .push((conn, access))
                     .map_err(|()| spin_world::sqlite::Error::DatabaseFull)
             }))
     }
@@ -95,15 +221,65 @@ impl spin_world::sqlite::Host for SqliteDispatch {
         query: String,
         parameters: Vec<spin_world::sqlite::Value>,
     ) -> anyhow::Result<Result<spin_world::sqlite::QueryResult, spin_world::sqlite::Error>> {
-        let conn = match self.get_connection(connection) {
+        let (conn, access) = match self.get_connection(connection) {
             Ok(c) => c,
             Err(err) => return Ok(Err(err)),
         };
-        Ok(conn.query(&query, parameters).await)
+        Ok(conn.query_with_access(&query, parameters, *access).await)
     }
 
     async fn close(&mut self, connection: spin_world::sqlite::Connection) -> anyhow::Result<()> {
         let _ = self.connections.remove(connection);
         Ok(())
     }
+
+    async fn begin_transaction(
+        &mut self,
+        connection: spin_world::sqlite::Connection,
+    ) -> anyhow::Result<Result<spin_world::sqlite::Transaction, spin_world::sqlite::Error>> {
+        let (conn, access) = match self.get_connection(connection) {
+            Ok(c) => c.clone(),
+            Err(err) => return Ok(Err(err)),
+        };
+        Ok(async {
+            let transaction = conn.begin_transaction(access).await?;
+            self.transactions
+                .push(transaction)
+                .map_err(|()| spin_world::sqlite::Error::DatabaseFull)
+        }
+        .await)
+    }
+
+    async fn execute_in_transaction(
+        &mut self,
+        transaction: spin_world::sqlite::Transaction,
+        query: String,
+        parameters: Vec<spin_world::sqlite::Value>,
+    ) -> anyhow::Result<Result<spin_world::sqlite::QueryResult, spin_world::sqlite::Error>> {
+        let transaction = match self.get_transaction(transaction) {
+            Ok(t) => t,
+            Err(err) => return Ok(Err(err)),
+        };
+        Ok(transaction.query(&query, parameters).await)
+    }
+
+    async fn commit(
+        &mut self,
+        transaction: spin_world::sqlite::Transaction,
+    ) -> anyhow::Result<Result<(), spin_world::sqlite::Error>> {
+        match self.transactions.remove(transaction) {
+            Some(transaction) => Ok(transaction.commit().await),
+            None => Ok(Err(spin_world::sqlite::Error::InvalidConnection)),
+        }
+    }
+
+    async fn rollback(
+        &mut self,
+        transaction: spin_world::sqlite::Transaction,
+    ) -> anyhow::Result<Result<(), spin_world::sqlite::Error>> {
+        match self.transactions.remove(transaction) {
+            Some(transaction) => Ok(transaction.rollback().await),
+            None => Ok(Err(spin_world::sqlite::Error::InvalidConnection)),
+        }
+    }
 }
@@ -0,0 +1,227 @@
+//! Versioned migrations for SQLite databases.
+//!
+//! A migrations directory contains `.sql` files named `<version>_<description>.sql`,
+//! e.g. `0001_create_users.sql`. Pending migrations are applied in version order,
+//! each in its own transaction, and recorded in the `spin_migrations` table so that
+//! every migration runs exactly once.
+
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+use spin_world::sqlite::Value;
+
+use crate::{execute_statements, Connection, Statement};
+
+/// The table in which applied migrations are recorded.
+pub const MIGRATIONS_TABLE: &str = "spin_migrations";
+
+/// A migration script in a migrations directory.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Migration {
+    pub version: i64,
+    /// The file name of the migration script.
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// A migration which has been recorded as applied to a database.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AppliedMigration {
+    pub version: i64,
+    pub name: String,
+    pub applied_at: String,
+}
+
+/// The state of a database relative to a migrations directory.
+#[derive(Clone, Debug, Default)]
+pub struct MigrationStatus {
+    pub applied: Vec<AppliedMigration>,
+    pub pending: Vec<Migration>,
+}
+
+/// Load the migration scripts in `dir`, ordered by version.
+pub fn load_migrations(dir: &Path) -> Result<Vec<Migration>> {
+    let entries = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read migrations directory {}", dir.display()))?;
+
+    let mut migrations = vec![];
+    for entry in entries {
+        let path = entry?.path();
+        if !path.is_file() || path.extension() != Some("sql".as_ref()) {
+            continue;
+        }
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .with_context(|| format!("Invalid migration file name {}", path.display()))?
+            .to_owned();
+        let version = parse_version(&name)?;
+        migrations.push(Migration {
+            version,
+            name,
+            path,
+        });
+    }
+    migrations.sort_by_key(|m| m.version);
+
+    for pair in migrations.windows(2) {
+        if pair[0].version == pair[1].version {
+            bail!(
+                "Migrations '{}' and '{}' have the same version {}",
+                pair[0].name,
+                pair[1].name,
+                pair[0].version
+            );
+        }
+    }
+
+    Ok(migrations)
+}
+
+fn parse_version(name: &str) -> Result<i64> {
+    name.split_once('_')
+        .and_then(|(version, _)| version.parse().ok())
+        .with_context(|| {
+            format!(
+                "Migration file name '{name}' must start with a version number followed by '_', e.g. '0001_create_users.sql'"
+            )
+        })
+}
+
+/// Get the migrations which have been applied to a database, ordered by version.
+///
+/// This does not modify the database: if the migrations table does not exist,
+/// no migrations have been applied.
+pub async fn applied_migrations(conn: &dyn Connection) -> Result<Vec<AppliedMigration>> {
+    let tables = conn
+        .query(
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name = ?",
+            vec![Value::Text(MIGRATIONS_TABLE.to_owned())],
+        )
+        .await
+        .context("Failed to look up migrations table")?;
+    if tables.rows.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let result = conn
+        .query(
+            &format!("SELECT version, name, applied_at FROM {MIGRATIONS_TABLE} ORDER BY version"),
+            vec![],
+        )
+        .await
+        .context("Failed to query applied migrations")?;
+
+    result
+        .rows
+        .into_iter()
+        .map(|row| match row.values.as_slice() {
+            [Value::Integer(version), Value::Text(name), Value::Text(applied_at)] => {
+                Ok(AppliedMigration {
+                    version: *version,
+                    name: name.clone(),
+                    applied_at: applied_at.clone(),
+                })
+            }
+            _ => bail!("Unexpected row in {MIGRATIONS_TABLE} table"),
+        })
+        .collect()
+}
+
+/// Compare the migrations applied to a database with those in `dir`.
+pub async fn status(conn: &dyn Connection, dir: &Path) -> Result<MigrationStatus> {
+    let applied = applied_migrations(conn).await?;
+    let applied_versions: HashSet<_> = applied.iter().map(|m| m.version).collect();
+    let pending = load_migrations(dir)?
+        .into_iter()
+        .filter(|m| !applied_versions.contains(&m.version))
+        .collect();
+    Ok(MigrationStatus { applied, pending })
+}
+
+/// Apply the pending migrations in `dir` to a database, returning the
+/// migrations that were applied.
+///
+/// If another process is migrating the same database, this waits for each
+/// migration it is applying and skips those it has applied.
+pub async fn migrate(conn: &dyn Connection, dir: &Path) -> Result<Vec<Migration>> {
+    conn.query(
+        &format!(
+            "CREATE TABLE IF NOT EXISTS {MIGRATIONS_TABLE} (version INTEGER PRIMARY KEY, name TEXT NOT NULL, applied_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP)"
+        ),
+        vec![],
+    )
+    .await
+    .context("Failed to create migrations table")?;
+
+    let pending = status(conn, dir).await?.pending;
+    let mut applied = vec![];
+    for migration in pending {
+        let sql = std::fs::read_to_string(&migration.path)
+            .with_context(|| format!("Failed to read migration {}", migration.path.display()))?;
+        // The migration is recorded first, in the same transaction, so that
+        // a concurrent migrator waits for this one to finish and then fails
+        // to record it again.
+        let statements = [
+            Statement::Query(
+                format!("INSERT INTO {MIGRATIONS_TABLE} (version, name) VALUES (?, ?)"),
+                vec![
+                    Value::Integer(migration.version),
+                    Value::Text(migration.name.clone()),
+                ],
+            ),
+            Statement::Batch(sql),
+        ];
+        match execute_statements(conn, &statements).await {
+            Ok(()) => applied.push(migration),
+            // Another process applied it in the meantime.
+            Err(_) if is_applied(conn, migration.version).await? => {}
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("Failed to apply migration '{}'", migration.name))
+            }
+        }
+    }
+    Ok(applied)
+}
+
+async fn is_applied(conn: &dyn Connection, version: i64) -> Result<bool> {
+    Ok(applied_migrations(conn)
+        .await?
+        .iter()
+        .any(|m| m.version == version))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn versions_are_parsed_from_file_names() {
+        assert_eq!(1, parse_version("0001_create_users.sql").unwrap());
+        assert_eq!(20230701, parse_version("20230701_add_index.sql").unwrap());
+        assert!(parse_version("create_users.sql").is_err());
+        assert!(parse_version("0001.sql").is_err());
+    }
+
+    #[test]
+    fn migrations_are_loaded_in_version_order() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        std::fs::write(dir.path().join("10_second.sql"), "")?;
+        std::fs::write(dir.path().join("2_first.sql"), "")?;
+        std::fs::write(dir.path().join("README.md"), "")?;
+
+        let names: Vec<_> = load_migrations(dir.path())?
+            .into_iter()
+            .map(|m| m.name)
+            .collect();
+        assert_eq!(vec!["2_first.sql", "10_second.sql"], names);
+
+        std::fs::write(dir.path().join("0002_duplicate.sql"), "")?;
+        assert!(load_migrations(dir.path()).is_err());
+        Ok(())
+    }
+}
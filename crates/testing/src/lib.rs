@@ -57,6 +57,12 @@ pub struct RedisTestConfig {
     redis_channel: String,
 }
 
+#[derive(Default)]
+pub struct KeyValueTestConfig {
+    module_path: Option<PathBuf>,
+    store: String,
+}
+
 impl HttpTestConfig {
     pub fn module_path(&mut self, path: impl Into<PathBuf>) -> &mut Self {
         init_tracing();
@@ -155,6 +161,46 @@ impl RedisTestConfig {
     }
 }
 
+impl KeyValueTestConfig {
+    pub fn module_path(&mut self, path: impl Into<PathBuf>) -> &mut Self {
+        init_tracing();
+        self.module_path = Some(path.into());
+        self
+    }
+
+    pub fn test_program(&mut self, name: impl AsRef<Path>) -> &mut Self {
+        self.module_path(Path::new(TEST_PROGRAM_PATH).join(name))
+    }
+
+    pub fn build_loader(&self) -> impl Loader {
+        TestLoader {
+            module_path: self.module_path.clone().expect("module path to be set"),
+            trigger_type: "key_value".into(),
+            app_trigger_metadata: json!({}),
+            trigger_config: json!({
+                "component": "test-component",
+                "store": self.store,
+            }),
+        }
+    }
+
+    pub async fn build_trigger<Executor: TriggerExecutor>(&mut self, store: &str) -> Executor
+    where
+        Executor::TriggerConfig: DeserializeOwned,
+    {
+        self.store = store.into();
+
+        TriggerExecutorBuilder::new(self.build_loader())
+            .build(
+                TEST_APP_URI.to_string(),
+                RuntimeConfig::default(),
+                HostComponentInitData::default(),
+            )
+            .await
+            .unwrap()
+    }
+}
+
 const TEST_APP_URI: &str = "spin-test:";
 
 struct TestLoader {
@@ -54,6 +54,8 @@ pub struct HttpTrigger {
     base: String,
     // Component ID -> component trigger config
     component_trigger_configs: HashMap<String, HttpTriggerConfig>,
+    // Bearer token required to reload config; the endpoint is disabled if unset.
+    reload_config_token: Option<String>,
 }
 
 #[derive(Args)]
@@ -69,6 +71,11 @@ pub struct CliArgs {
     /// The path to the certificate key to use for https, if this is not set, normal http will be used. The key should be in PKCS#8 format
     #[clap(long, env = "SPIN_TLS_KEY", requires = "tls-cert")]
     pub tls_key: Option<PathBuf>,
+
+    /// Enables the `/.well-known/spin/reload-config` endpoint, which reloads
+    /// variable providers for POST requests with this bearer token
+    #[clap(long, env = "SPIN_RELOAD_CONFIG_TOKEN", hide_env_values = true)]
+    pub reload_config_token: Option<String>,
 }
 
 impl CliArgs {
@@ -135,11 +142,13 @@ impl TriggerExecutor for HttpTrigger {
             router,
             base,
             component_trigger_configs,
+            reload_config_token: None,
         })
     }
 
-    async fn run(self, config: Self::RunConfig) -> Result<()> {
+    async fn run(mut self, config: Self::RunConfig) -> Result<()> {
         let listen_addr = config.address;
+        self.reload_config_token = config.reload_config_token.clone();
         let tls = config.into_tls_config();
 
         // Print startup messages
@@ -206,6 +215,7 @@ impl HttpTrigger {
             return match well_known {
                 "health" => Ok(Response::new(Body::from("OK"))),
                 "info" => self.app_info(),
+                "reload-config" => self.reload_config(&req),
                 _ => Self::not_found(),
             };
         }
@@ -268,6 +278,42 @@ impl HttpTrigger {
             .body(body.into())?)
     }
 
+    /// Reloads variable providers from the runtime config. The endpoint only
+    /// exists if a reload token was configured, and only accepts POST requests
+    /// which present it as a bearer token.
+    fn reload_config(&self, req: &Request<Body>) -> Result<Response<Body>> {
+        let Some(token) = &self.reload_config_token else {
+            return Self::not_found();
+        };
+        if req.method() != http::Method::POST {
+            return Ok(Response::builder()
+                .status(StatusCode::METHOD_NOT_ALLOWED)
+                .header("allow", "POST")
+                .body(Body::empty())?);
+        }
+        let presented = req
+            .headers()
+            .get(http::header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        let authorized = matches!(presented, Some(presented) if constant_time_eq(presented, token));
+        if !authorized {
+            return Ok(Response::builder()
+                .status(StatusCode::UNAUTHORIZED)
+                .header("www-authenticate", "Bearer")
+                .body(Body::empty())?);
+        }
+        match self.engine.reload_config() {
+            Ok(()) => Ok(Response::new(Body::from("OK"))),
+            Err(err) => {
+                // Provider errors may describe secrets or their stores, so
+                // they're only logged.
+                log::error!("Error reloading config: {:?}", err);
+                Self::internal_error(None)
+            }
+        }
+    }
+
     /// Creates an HTTP 500 response.
     fn internal_error(body: Option<&str>) -> Result<Response<Body>> {
         let body = match body {
@@ -367,6 +413,15 @@ fn parse_listen_addr(addr: &str) -> anyhow::Result<SocketAddr> {
     addrs.into_iter().next().context("couldn't resolve address")
 }
 
+// Compares secrets without revealing how much of them matched through timing.
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
 fn set_req_uri(req: &mut Request<Body>, scheme: Scheme) -> Result<()> {
     const DEFAULT_HOST: &str = "localhost";
 
@@ -647,6 +702,68 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_reload_config() -> Result<()> {
+        let mut trigger: HttpTrigger = spin_testing::HttpTestConfig::default()
+            .test_program("rust-http-test.wasm")
+            .http_spin_trigger("/test")
+            .build_trigger()
+            .await;
+
+        let reload_request = |method, token: Option<&str>| {
+            let mut builder = http::Request::builder()
+                .method(method)
+                .uri("http://localhost:3000/.well-known/spin/reload-config");
+            if let Some(token) = token {
+                builder = builder.header("authorization", format!("Bearer {token}"));
+            }
+            builder.body(Body::empty()).unwrap()
+        };
+
+        // The endpoint is disabled unless a token is configured.
+        let res = trigger
+            .handle(
+                reload_request("POST", Some("secret")),
+                Scheme::HTTP,
+                test_socket_addr(),
+            )
+            .await?;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        trigger.reload_config_token = Some("secret".into());
+
+        let res = trigger
+            .handle(
+                reload_request("POST", Some("secret")),
+                Scheme::HTTP,
+                test_socket_addr(),
+            )
+            .await?;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let res = trigger
+            .handle(
+                reload_request("GET", Some("secret")),
+                Scheme::HTTP,
+                test_socket_addr(),
+            )
+            .await?;
+        assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+
+        for token in [None, Some("wrong"), Some("secre")] {
+            let res = trigger
+                .handle(
+                    reload_request("POST", token),
+                    Scheme::HTTP,
+                    test_socket_addr(),
+                )
+                .await?;
+            assert_eq!(res.status(), StatusCode::UNAUTHORIZED, "{token:?}");
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn parse_listen_addr_prefers_ipv4() {
         let addr = parse_listen_addr("localhost:12345").unwrap();
@@ -0,0 +1,131 @@
+//! Implementation for the Spin key-value change trigger.
+
+mod spin;
+
+use std::collections::HashSet;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use spin_core::async_trait;
+use spin_key_value::StoreEvent;
+use spin_trigger::{cli::NoArgs, TriggerAppEngine, TriggerExecutor};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::spin::SpinKeyValueExecutor;
+
+pub(crate) type RuntimeData = ();
+pub(crate) type Store = spin_core::Store<RuntimeData>;
+
+/// The Spin key-value change trigger.
+pub struct KeyValueTrigger {
+    engine: TriggerAppEngine<Self>,
+    // Stores and key prefixes watched by each component
+    subscriptions: Vec<KeyValueTriggerConfig>,
+}
+
+/// Key-value change trigger configuration.
+///
+/// Changes the component itself makes to matching keys are delivered to it like any other, so a component which
+/// writes to the keys it watches must take care not to trigger itself indefinitely.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct KeyValueTriggerConfig {
+    /// Component ID to invoke
+    pub component: String,
+    /// Store to watch for changes
+    pub store: String,
+    /// Only changes to keys starting with this prefix are delivered
+    #[serde(default)]
+    pub prefix: String,
+}
+
+impl KeyValueTriggerConfig {
+    fn matches(&self, event: &StoreEvent) -> bool {
+        event.store == self.store && event.key.starts_with(&self.prefix)
+    }
+}
+
+#[async_trait]
+impl TriggerExecutor for KeyValueTrigger {
+    const TRIGGER_TYPE: &'static str = "key_value";
+    type RuntimeData = RuntimeData;
+    type TriggerConfig = KeyValueTriggerConfig;
+    type RunConfig = NoArgs;
+
+    async fn new(engine: TriggerAppEngine<Self>) -> Result<Self> {
+        let subscriptions = engine
+            .trigger_configs()
+            .map(|(_, config)| config.clone())
+            .collect();
+
+        Ok(Self {
+            engine,
+            subscriptions,
+        })
+    }
+
+    /// Run the key-value trigger indefinitely.
+    async fn run(self, _config: Self::RunConfig) -> Result<()> {
+        for subscription in &self.subscriptions {
+            tracing::info!(
+                "Subscribing component {:?} to changes in store {:?} with key prefix {:?}",
+                subscription.component,
+                subscription.store,
+                subscription.prefix
+            );
+        }
+
+        let stores = self
+            .subscriptions
+            .iter()
+            .map(|subscription| subscription.store.as_str())
+            .collect::<HashSet<_>>();
+        let mut events = self.engine.subscribe_key_value_stores(stores).await?;
+
+        loop {
+            match events.recv().await {
+                Ok(event) => self.handle(event).await,
+                Err(RecvError::Lagged(count)) => {
+                    tracing::warn!("Dropped {count} key-value change events which could not be handled in time");
+                }
+                Err(RecvError::Closed) => break Ok(()),
+            }
+        }
+    }
+}
+
+impl KeyValueTrigger {
+    // Handle the event by invoking each subscribed component in turn.
+    async fn handle(&self, event: StoreEvent) {
+        tracing::info!(
+            "Received {:?} event for key {:?} in store {:?}",
+            event.kind,
+            event.key,
+            event.store
+        );
+
+        for subscription in self.subscriptions.iter().filter(|s| s.matches(&event)) {
+            let component_id = &subscription.component;
+            tracing::trace!("Executing key-value component {component_id:?}");
+            let executor = SpinKeyValueExecutor;
+            if let Err(e) = executor.execute(&self.engine, component_id, &event).await {
+                tracing::error!("Component {component_id:?} failed to handle change: {e:?}");
+            }
+        }
+    }
+}
+
+/// The key-value executor trait.
+/// All key-value executors must implement this trait.
+#[async_trait]
+pub(crate) trait KeyValueExecutor: Clone + Send + Sync + 'static {
+    async fn execute(
+        &self,
+        engine: &TriggerAppEngine<KeyValueTrigger>,
+        component_id: &str,
+        event: &StoreEvent,
+    ) -> Result<()>;
+}
+
+#[cfg(test)]
+mod tests;
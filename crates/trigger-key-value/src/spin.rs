@@ -0,0 +1,69 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use spin_core::Instance;
+use spin_key_value::{StoreEvent, StoreEventKind};
+use spin_trigger::{EitherInstance, TriggerAppEngine};
+use spin_world::key_value_trigger::exports::fermyon::spin::inbound_key_value::{
+    ChangeEvent, ChangeKind,
+};
+
+use crate::{KeyValueExecutor, KeyValueTrigger, Store};
+
+#[derive(Clone)]
+pub struct SpinKeyValueExecutor;
+
+#[async_trait]
+impl KeyValueExecutor for SpinKeyValueExecutor {
+    async fn execute(
+        &self,
+        engine: &TriggerAppEngine<KeyValueTrigger>,
+        component_id: &str,
+        event: &StoreEvent,
+    ) -> Result<()> {
+        tracing::trace!("Executing request using the Spin executor for component {component_id}");
+
+        let (instance, store) = engine.prepare_instance(component_id).await?;
+        let EitherInstance::Component(instance) = instance else {
+            unreachable!()
+        };
+
+        match Self::execute_impl(store, instance, event).await {
+            Ok(()) => {
+                tracing::trace!("Request finished OK");
+                Ok(())
+            }
+            Err(e) => {
+                tracing::trace!("Request finished with error {e}");
+                Err(e)
+            }
+        }
+    }
+}
+
+impl SpinKeyValueExecutor {
+    pub async fn execute_impl(
+        mut store: Store,
+        instance: Instance,
+        event: &StoreEvent,
+    ) -> Result<()> {
+        let func = instance
+            .exports(&mut store)
+            .instance("fermyon:spin/inbound-key-value")
+            .ok_or_else(|| anyhow!("no fermyon:spin/inbound-key-value instance found"))?
+            .typed_func::<(ChangeEvent,), (Result<(), String>,)>("handle-change")?;
+
+        let event = ChangeEvent {
+            store: event.store.clone(),
+            key: event.key.clone(),
+            kind: match event.kind {
+                StoreEventKind::Set => ChangeKind::Set,
+                StoreEventKind::Delete => ChangeKind::Delete,
+            },
+        };
+
+        match func.call_async(store, (event,)).await? {
+            (Ok(()),) => Ok(()),
+            (Err(e),) => Err(anyhow!("`handle-change` returned an error: {e}")),
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use super::*;
+use anyhow::Result;
+use spin_key_value::StoreEventKind;
+use spin_testing::{tokio, KeyValueTestConfig};
+
+fn event(store: &str, key: &str) -> StoreEvent {
+    StoreEvent {
+        store: store.to_owned(),
+        key: key.to_owned(),
+        kind: StoreEventKind::Set,
+    }
+}
+
+#[test]
+fn subscriptions_match_store_and_prefix() {
+    let config = KeyValueTriggerConfig {
+        component: "invalidator".to_owned(),
+        store: "default".to_owned(),
+        prefix: "cache/".to_owned(),
+    };
+
+    assert!(config.matches(&event("default", "cache/foo")));
+    assert!(!config.matches(&event("default", "other/foo")));
+    assert!(!config.matches(&event("other", "cache/foo")));
+
+    let everything = KeyValueTriggerConfig {
+        prefix: String::new(),
+        ..config
+    };
+    assert!(everything.matches(&event("default", "other/foo")));
+}
+
+#[tokio::test]
+async fn test_handle_change() -> Result<()> {
+    let trigger: KeyValueTrigger = KeyValueTestConfig::default()
+        .test_program("key-value-rust.wasm")
+        .build_trigger("default")
+        .await;
+
+    SpinKeyValueExecutor
+        .execute(
+            &trigger.engine,
+            "test-component",
+            &event("default", "hello"),
+        )
+        .await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_handle_change_error() {
+    let trigger: KeyValueTrigger = KeyValueTestConfig::default()
+        .test_program("key-value-rust.wasm")
+        .build_trigger("default")
+        .await;
+
+    let err = SpinKeyValueExecutor
+        .execute(
+            &trigger.engine,
+            "test-component",
+            &event("default", "fail/hello"),
+        )
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("fail/hello"), "{err:#}");
+}
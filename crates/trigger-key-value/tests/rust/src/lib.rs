@@ -0,0 +1,15 @@
+wit_bindgen::generate!("key-value-trigger" in "../../../../wit/preview2");
+use exports::fermyon::spin::inbound_key_value::{self, ChangeEvent};
+
+struct SpinKeyValue;
+export_key_value_trigger!(SpinKeyValue);
+
+impl inbound_key_value::InboundKeyValue for SpinKeyValue {
+    fn handle_change(event: ChangeEvent) -> Result<(), String> {
+        println!("Change: {event:?}");
+        if event.key.starts_with("fail/") {
+            return Err(format!("failed to handle {:?}", event.key));
+        }
+        Ok(())
+    }
+}
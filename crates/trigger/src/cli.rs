@@ -1,6 +1,7 @@
 use std::path::PathBuf;
 
 use anyhow::{Context, Result};
+use bytesize::ByteSize;
 use clap::{Args, IntoApp, Parser};
 use serde::de::DeserializeOwned;
 use spin_app::Loader;
@@ -10,7 +11,7 @@ use crate::runtime_config::sqlite::SqlitePersistenceMessageHook;
 use crate::stdio::StdioLoggingTriggerHooks;
 use crate::{
     loader::TriggerLoader,
-    runtime_config::{key_value::KeyValuePersistenceMessageHook, RuntimeConfig},
+    runtime_config::{key_value::KeyValuePersistenceMessageHook, llm::LlmDevice, RuntimeConfig},
     stdio::FollowComponents,
 };
 use crate::{TriggerExecutor, TriggerExecutorBuilder};
@@ -115,6 +116,21 @@ where
     #[clap(long = "sqlite")]
     sqlite_statements: Vec<String>,
 
+    /// The directory containing the LLM models used for inference. Defaults
+    /// to the application state directory.
+    #[clap(long = "llm-model-registry")]
+    llm_model_registry: Option<PathBuf>,
+
+    /// The device on which to run LLM inference. Defaults to the GPU.
+    #[clap(value_enum, long = "llm-device")]
+    llm_device: Option<LlmDevice>,
+
+    /// The maximum combined size of the LLM models to keep loaded in memory
+    /// between inference requests, e.g. "8GiB". The most recently used model
+    /// is always kept. Defaults to 16GiB.
+    #[clap(long = "llm-model-cache-size")]
+    llm_model_cache_size: Option<ByteSize>,
+
     #[clap(long = "help-args-only", hide = true)]
     pub help_args_only: bool,
 }
@@ -143,32 +159,27 @@ where
         let working_dir = std::env::var(SPIN_WORKING_DIR).context(SPIN_WORKING_DIR)?;
         let locked_url = std::env::var(SPIN_LOCKED_URL).context(SPIN_LOCKED_URL)?;
 
-        let init_data = crate::HostComponentInitData::new(
-            &*self.key_values,
-            &*self.sqlite_statements,
-            spin_llm::LLmOptions {
-                model_registry: PathBuf::from(".spin"),
-                use_gpu: true,
-            },
-        );
+        let init_data =
+            crate::HostComponentInitData::new(&*self.key_values, &*self.sqlite_statements);
 
         let loader = TriggerLoader::new(working_dir, self.allow_transient_write);
         let executor = self.build_executor(loader, locked_url, init_data).await?;
 
         let run_fut = executor.run(self.run_config);
 
-        let (abortable, abort_handle) = futures::future::abortable(run_fut);
-        ctrlc::set_handler(move || abort_handle.abort())?;
-        match abortable.await {
-            Ok(Ok(())) => {
-                tracing::info!("Trigger executor shut down: exiting");
-                Ok(())
-            }
-            Ok(Err(err)) => {
-                tracing::error!("Trigger executor failed");
-                Err(err)
-            }
-            Err(_aborted) => {
+        tokio::select! {
+            res = run_fut => match res {
+                Ok(()) => {
+                    tracing::info!("Trigger executor shut down: exiting");
+                    Ok(())
+                }
+                Err(err) => {
+                    tracing::error!("Trigger executor failed");
+                    Err(err)
+                }
+            },
+            res = shutdown_requested() => {
+                res?;
                 tracing::info!("User requested shutdown: exiting");
                 Ok(())
             }
@@ -191,6 +202,7 @@ where
         builder.hooks(StdioLoggingTriggerHooks::new(self.follow_components()));
         builder.hooks(KeyValuePersistenceMessageHook);
         builder.hooks(SqlitePersistenceMessageHook);
+        builder.reload_config_on_hangup();
 
         builder.build(locked_url, runtime_config, init_data).await
     }
@@ -204,6 +216,15 @@ where
         if let Some(log_dir) = &self.log {
             config.set_log_dir(log_dir);
         }
+        if let Some(registry) = &self.llm_model_registry {
+            config.set_llm_model_registry(registry);
+        }
+        if let Some(device) = self.llm_device {
+            config.set_llm_device(device);
+        }
+        if let Some(capacity) = self.llm_model_cache_size {
+            config.set_llm_model_cache_capacity(capacity);
+        }
         if let Some(config_file) = &self.runtime_config_file {
             config.merge_config_file(config_file)?;
         }
@@ -233,6 +254,23 @@ where
     }
 }
 
+// Completes when the process is interrupted or asked to terminate. SIGHUP is
+// not included here; it reloads variable providers instead.
+async fn shutdown_requested() -> Result<()> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut terminate = signal(SignalKind::terminate())?;
+        tokio::select! {
+            res = tokio::signal::ctrl_c() => res?,
+            _ = terminate.recv() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c().await?;
+    Ok(())
+}
+
 const SLOTH_WARNING_DELAY_MILLIS: u64 = 1250;
 
 fn warn_if_wasm_build_slothful() -> sloth::SlothGuard {
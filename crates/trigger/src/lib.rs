@@ -4,7 +4,7 @@ pub mod locked;
 mod runtime_config;
 mod stdio;
 
-use std::{collections::HashMap, marker::PhantomData, path::PathBuf};
+use std::{collections::HashMap, marker::PhantomData, path::PathBuf, sync::Arc};
 
 use anyhow::{anyhow, Context, Result};
 pub use async_trait::async_trait;
@@ -16,7 +16,10 @@ use spin_core::{
     Config, Engine, EngineBuilder, Instance, InstancePre, ModuleInstance, ModuleInstancePre, Store,
     StoreBuilder, WasiVersion,
 };
+use spin_key_value::{DelegatingStoreManager, StoreEvent};
+use tokio::sync::broadcast;
 
+use crate::runtime_config::config_provider::ConfigProviderReloader;
 pub use crate::runtime_config::RuntimeConfig;
 
 pub enum EitherInstancePre<T> {
@@ -66,6 +69,7 @@ pub struct TriggerExecutorBuilder<Executor: TriggerExecutor> {
     config: Config,
     hooks: Vec<Box<dyn TriggerHooks>>,
     disable_default_host_components: bool,
+    reload_config_on_hangup: bool,
     _phantom: PhantomData<Executor>,
 }
 
@@ -77,6 +81,7 @@ impl<Executor: TriggerExecutor> TriggerExecutorBuilder<Executor> {
             config: Default::default(),
             hooks: Default::default(),
             disable_default_host_components: false,
+            reload_config_on_hangup: false,
             _phantom: PhantomData,
         }
     }
@@ -98,6 +103,13 @@ impl<Executor: TriggerExecutor> TriggerExecutorBuilder<Executor> {
         self
     }
 
+    /// Reload variable providers from the runtime config files when the
+    /// process receives SIGHUP. This has no effect on Windows.
+    pub fn reload_config_on_hangup(&mut self) -> &mut Self {
+        self.reload_config_on_hangup = true;
+        self
+    }
+
     pub async fn build(
         mut self,
         app_uri: String,
@@ -107,6 +119,9 @@ impl<Executor: TriggerExecutor> TriggerExecutorBuilder<Executor> {
     where
         Executor::TriggerConfig: DeserializeOwned,
     {
+        let mut key_value_stores = None;
+        let mut sqlite_databases = None;
+        let mut config_providers = None;
         let engine = {
             let mut builder = Engine::builder(&self.config)?;
 
@@ -114,31 +129,33 @@ impl<Executor: TriggerExecutor> TriggerExecutorBuilder<Executor> {
                 builder.add_host_component(outbound_redis::OutboundRedisComponent)?;
                 builder.add_host_component(outbound_pg::OutboundPg::default())?;
                 builder.add_host_component(outbound_mysql::OutboundMysql::default())?;
-                builder.add_host_component(spin_llm::LlmComponent::new(
-                    init_data.llm.model_registry,
-                    init_data.llm.use_gpu,
-                ))?;
-                self.loader.add_dynamic_host_component(
-                    &mut builder,
+                let (key_value_component, stores) =
                     runtime_config::key_value::build_key_value_component(
                         &runtime_config,
                         &init_data.kv,
                     )
-                    .await?,
-                )?;
-                self.loader.add_dynamic_host_component(
-                    &mut builder,
-                    runtime_config::sqlite::build_component(&runtime_config, &init_data.sqlite)
-                        .await?,
-                )?;
+                    .await?;
+                key_value_stores = Some(stores);
+                self.loader
+                    .add_dynamic_host_component(&mut builder, key_value_component)?;
+                let (sqlite_component, databases) =
+                    runtime_config::sqlite::build_component(&runtime_config).await?;
+                sqlite_databases = Some(databases);
+                self.loader
+                    .add_dynamic_host_component(&mut builder, sqlite_component)?;
                 self.loader.add_dynamic_host_component(
                     &mut builder,
                     outbound_http::OutboundHttpComponent,
                 )?;
                 self.loader.add_dynamic_host_component(
                     &mut builder,
-                    spin_config::ConfigHostComponent::new(runtime_config.config_providers()),
+                    runtime_config::llm::build_component(&runtime_config)?,
                 )?;
+                let config_component =
+                    spin_config::ConfigHostComponent::new(runtime_config.config_providers()?);
+                config_providers = Some(config_component.providers());
+                self.loader
+                    .add_dynamic_host_component(&mut builder, config_component)?;
             }
 
             Executor::configure_engine(&mut builder)?;
@@ -153,17 +170,79 @@ impl<Executor: TriggerExecutor> TriggerExecutorBuilder<Executor> {
             .iter_mut()
             .try_for_each(|h| h.app_loaded(app.borrowed(), &runtime_config))?;
 
+        if let Some(databases) = &sqlite_databases {
+            runtime_config::sqlite::init_databases(app.borrowed(), databases, &init_data.sqlite)
+                .await?;
+        }
+
+        if let Some(providers) = &config_providers {
+            validate_variables(app.borrowed(), providers.clone()).await?;
+        }
+
+        let config_reloader = config_providers
+            .map(|providers| Arc::new(ConfigProviderReloader::new(runtime_config, providers)));
+        if self.reload_config_on_hangup {
+            if let Some(reloader) = &config_reloader {
+                reload_config_on_hangup(reloader.clone())?;
+            }
+        }
+
         // Run trigger executor
-        Executor::new(TriggerAppEngine::new(engine, app_name, app, self.hooks).await?).await
+        Executor::new(
+            TriggerAppEngine::new(
+                engine,
+                app_name,
+                app,
+                self.hooks,
+                key_value_stores,
+                config_reloader,
+            )
+            .await?,
+        )
+        .await
     }
 }
 
+// Checks that variables with a declared type or constraints resolve to valid
+// values, so that misconfiguration is reported before the app starts.
+async fn validate_variables(app: &App<'_>, providers: spin_config::ProviderChain) -> Result<()> {
+    let mut resolver =
+        spin_config::Resolver::new(app.variables().map(|(key, var)| (key.clone(), var.clone())))?;
+    resolver.set_providers(providers);
+    resolver
+        .validate_variables()
+        .await
+        .context("Application variables are not valid")
+}
+
+// Reloads variable providers whenever the process receives SIGHUP.
+#[cfg(unix)]
+fn reload_config_on_hangup(reloader: Arc<ConfigProviderReloader>) -> Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangups =
+        signal(SignalKind::hangup()).context("Failed to listen for the SIGHUP signal")?;
+    tokio::spawn(async move {
+        while hangups.recv().await.is_some() {
+            match reloader.reload() {
+                Ok(()) => tracing::info!("Reloaded variable providers from runtime config"),
+                Err(err) => tracing::error!("Failed to reload variable providers: {err:?}"),
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn reload_config_on_hangup(_reloader: Arc<ConfigProviderReloader>) -> Result<()> {
+    Ok(())
+}
+
 /// Initialization data for host components.
 #[derive(Default)] // TODO: the implementation of Default is only for tests - would like to get rid of
 pub struct HostComponentInitData {
     kv: Vec<(String, String)>,
     sqlite: Vec<String>,
-    llm: spin_llm::LLmOptions,
 }
 
 impl HostComponentInitData {
@@ -173,12 +252,10 @@ impl HostComponentInitData {
     pub fn new(
         key_value_init_values: impl Into<Vec<(String, String)>>,
         sqlite_init_statements: impl Into<Vec<String>>,
-        llm: spin_llm::LLmOptions,
     ) -> Self {
         Self {
             kv: key_value_init_values.into(),
             sqlite: sqlite_init_statements.into(),
-            llm,
         }
     }
 }
@@ -197,6 +274,10 @@ pub struct TriggerAppEngine<Executor: TriggerExecutor> {
     trigger_configs: Vec<Executor::TriggerConfig>,
     // Map of {Component ID -> InstancePre} for each component.
     component_instance_pres: HashMap<String, EitherInstancePre<Executor::RuntimeData>>,
+    // The key-value stores available to components, if the default host components are enabled.
+    key_value_stores: Option<Arc<DelegatingStoreManager>>,
+    // Reloads variable providers, if the default host components are enabled.
+    config_reloader: Option<Arc<ConfigProviderReloader>>,
 }
 
 impl<Executor: TriggerExecutor> TriggerAppEngine<Executor> {
@@ -207,6 +288,8 @@ impl<Executor: TriggerExecutor> TriggerAppEngine<Executor> {
         app_name: String,
         app: OwnedApp,
         hooks: Vec<Box<dyn TriggerHooks>>,
+        key_value_stores: Option<Arc<DelegatingStoreManager>>,
+        config_reloader: Option<Arc<ConfigProviderReloader>>,
     ) -> Result<Self>
     where
         <Executor as TriggerExecutor>::TriggerConfig: DeserializeOwned,
@@ -242,6 +325,8 @@ impl<Executor: TriggerExecutor> TriggerAppEngine<Executor> {
             hooks,
             trigger_configs: trigger_configs.into_values().collect(),
             component_instance_pres,
+            key_value_stores,
+            config_reloader,
         })
     }
 
@@ -320,6 +405,34 @@ impl<Executor: TriggerExecutor> TriggerAppEngine<Executor> {
         Ok((instance, store))
     }
 
+    /// Subscribes to changes made to the named key-value stores, whether by
+    /// components of this app or, where the store supports it, other processes.
+    pub async fn subscribe_key_value_stores(
+        &self,
+        names: impl IntoIterator<Item = &str>,
+    ) -> Result<broadcast::Receiver<StoreEvent>> {
+        let stores = self
+            .key_value_stores
+            .as_ref()
+            .context("Key-value stores are not available to this trigger")?;
+        stores
+            .subscribe(names)
+            .await
+            .context("Failed to watch key-value stores for changes")
+    }
+
+    /// Reloads the runtime config files and rebuilds the variable providers
+    /// from them. Variables are resolved using the new providers from then on.
+    pub fn reload_config(&self) -> Result<()> {
+        let reloader = self
+            .config_reloader
+            .as_ref()
+            .context("Variables are not available to this trigger")?;
+        reloader
+            .reload()
+            .context("Failed to reload variable providers from runtime config")
+    }
+
     pub fn get_component(&self, component_id: &str) -> Result<AppComponent> {
         self.app().get_component(component_id).with_context(|| {
             format!(
@@ -13,11 +13,12 @@ use spin_app::{
     MetadataKey,
 };
 use spin_key_value::KEY_VALUE_STORES_KEY;
+use spin_llm::AI_MODELS_KEY;
 use spin_manifest::{
     Application, ApplicationInformation, ApplicationOrigin, ApplicationTrigger, CoreComponent,
-    HttpConfig, HttpTriggerConfiguration, RedisConfig, TriggerConfig,
+    HttpConfig, HttpTriggerConfiguration, KeyValueConfig, RedisConfig, TriggerConfig, VariableType,
 };
-use spin_sqlite::DATABASES_KEY;
+use spin_sqlite::{DATABASES_KEY, DATABASE_ACCESS_KEY, DATABASE_MIGRATIONS_KEY};
 
 pub const NAME_KEY: MetadataKey = MetadataKey::new("name");
 pub const VERSION_KEY: MetadataKey = MetadataKey::new("version");
@@ -83,6 +84,14 @@ impl LockedAppBuilder {
                     locked::Variable {
                         default: var.default,
                         secret: var.secret,
+                        value_type: match var.value_type {
+                            VariableType::String => locked::VariableType::String,
+                            VariableType::Integer => locked::VariableType::Integer,
+                            VariableType::Boolean => locked::VariableType::Boolean,
+                            VariableType::Url => locked::VariableType::Url,
+                        },
+                        pattern: var.pattern,
+                        allowed_values: var.allowed_values,
                     },
                 ))
             })
@@ -112,6 +121,11 @@ impl LockedAppBuilder {
                         trigger_type = "redis";
                         builder.string("channel", channel);
                     },
+                    (ApplicationTrigger::KeyValue(_), TriggerConfig::KeyValue(KeyValueConfig{ store, prefix })) => {
+                        trigger_type = "key_value";
+                        builder.string("store", store);
+                        builder.string("prefix", prefix);
+                    },
                     (ApplicationTrigger::External(c), TriggerConfig::External(t)) => {
                         trigger_type = c.trigger_type();
                         for (key, value) in &t {
@@ -143,12 +157,23 @@ impl LockedAppBuilder {
     fn build_component(&self, component: CoreComponent) -> Result<LockedComponent> {
         let id = component.id;
 
-        let metadata = ValuesMapBuilder::new()
+        let mut metadata = ValuesMapBuilder::new();
+        metadata
             .string_option(DESCRIPTION_KEY, component.description)
             .string_array(ALLOWED_HTTP_HOSTS_KEY, component.wasm.allowed_http_hosts)
             .string_array(KEY_VALUE_STORES_KEY, component.wasm.key_value_stores)
             .string_array(DATABASES_KEY, component.wasm.sqlite_databases)
-            .take();
+            .string_array(AI_MODELS_KEY, component.wasm.ai_models);
+        if !component.wasm.sqlite_database_access.is_empty() {
+            metadata.serializable(DATABASE_ACCESS_KEY, component.wasm.sqlite_database_access)?;
+        }
+        if !component.wasm.sqlite_database_migrations.is_empty() {
+            metadata.serializable(
+                DATABASE_MIGRATIONS_KEY,
+                component.wasm.sqlite_database_migrations,
+            )?;
+        }
+        let metadata = metadata.build();
 
         let source = {
             let path = match component.source {
@@ -223,6 +248,7 @@ mod tests {
 
         [variables]
         test_var = { default = "test-val" }
+        port = { default = "8080", type = "integer" }
 
         [[component]]
         id = "test-component"
@@ -261,6 +287,10 @@ mod tests {
         let locked = build_locked_app(app, tempdir.path()).unwrap();
         assert_eq!(locked.metadata["name"], "test-app");
         assert!(locked.variables.contains_key("test_var"));
+        assert_eq!(
+            locked.variables["port"].value_type,
+            locked::VariableType::Integer
+        );
         assert_eq!(locked.triggers[0].trigger_config["route"], "/");
 
         let component = &locked.components[0];
@@ -1,21 +1,27 @@
 pub mod config_provider;
 pub mod key_value;
+pub mod llm;
 pub mod sqlite;
 
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fs,
+    future::Future,
     path::{Path, PathBuf},
     sync::Arc,
+    time::Duration,
 };
 
 use anyhow::{Context, Result};
+use bytesize::ByteSize;
 use serde::Deserialize;
+use spin_key_value_sqlite::KeyValueSqlite;
 use spin_sqlite::Connection;
 
 use self::{
     config_provider::{ConfigProvider, ConfigProviderOpts},
     key_value::{KeyValueStore, KeyValueStoreOpts},
+    llm::{LlmComputeOpts, LlmDevice, LlmOverrides},
     sqlite::SqliteDatabaseOpts,
 };
 
@@ -26,11 +32,12 @@ const DEFAULT_SQLITE_DB_FILENAME: &str = "sqlite.db";
 
 /// RuntimeConfig allows multiple sources of runtime configuration to be
 /// queried uniformly.
-#[derive(Debug, Default)]
+#[derive(Clone, Debug, Default)]
 pub struct RuntimeConfig {
     local_app_dir: Option<PathBuf>,
     files: Vec<RuntimeConfigOpts>,
     overrides: RuntimeConfigOpts,
+    llm_overrides: LlmOverrides,
 }
 
 impl RuntimeConfig {
@@ -47,26 +54,40 @@ impl RuntimeConfig {
     /// Load a runtime config file from the given path. Options specified in a
     /// later-loaded file take precedence over any earlier-loaded files.
     pub fn merge_config_file(&mut self, path: impl Into<PathBuf>) -> Result<()> {
-        let path = path.into();
-        let bytes = fs::read(&path)
-            .with_context(|| format!("Failed to load runtime config file {path:?}"))?;
-        let mut opts: RuntimeConfigOpts = toml::from_slice(&bytes)
-            .with_context(|| format!("Failed to parse runtime config file {path:?}"))?;
-        opts.file_path = Some(path);
+        let opts = load_config_file(path.into())?;
         self.files.push(opts);
         Ok(())
     }
 
+    /// Load the runtime config files again, to pick up any changes to them.
+    /// If any file fails to load, the previously loaded options are kept.
+    pub fn reload_config_files(&mut self) -> Result<()> {
+        let files = self
+            .files
+            .iter()
+            .map(|opts| {
+                let path = opts
+                    .file_path
+                    .clone()
+                    .expect("config file path should be set");
+                load_config_file(path)
+            })
+            .collect::<Result<_>>()?;
+        self.files = files;
+        Ok(())
+    }
+
     /// Return a Vec of configured [`spin_config::Provider`]s.
-    pub fn config_providers(&self) -> Vec<ConfigProvider> {
-        let default_provider = ConfigProviderOpts::default_provider_opts(self).build_provider();
+    pub fn config_providers(&self) -> Result<Vec<ConfigProvider>> {
+        let default_provider = ConfigProviderOpts::default_provider_opts(self)
+            .build_provider(&RuntimeConfigOpts::default())?;
         let mut providers: Vec<ConfigProvider> = vec![default_provider];
-        providers.extend(self.opts_layers().flat_map(|opts| {
-            opts.config_providers
-                .iter()
-                .map(|opts| opts.build_provider())
-        }));
-        providers
+        for opts in self.opts_layers() {
+            for provider in &opts.config_providers {
+                providers.push(provider.build_provider(opts)?);
+            }
+        }
+        Ok(providers)
     }
 
     /// Return an iterator of named configured [`KeyValueStore`]s.
@@ -128,6 +149,69 @@ impl RuntimeConfig {
         Ok(databases.into_iter())
     }
 
+    /// Return the periodic backup configured for each named SQLite database,
+    /// if any.
+    pub fn sqlite_backups(&self) -> Result<HashMap<String, PeriodicBackup>> {
+        let mut seen = HashSet::new();
+        let mut backups = HashMap::new();
+        for opts in self.opts_layers() {
+            for (name, database) in &opts.sqlite_databases {
+                if seen.insert(name.to_owned()) {
+                    if let Some(backup) = database
+                        .periodic_backup(opts)
+                        .with_context(|| format!("Invalid backup for SQLite database '{name}'"))?
+                    {
+                        backups.insert(name.to_owned(), backup);
+                    }
+                }
+            }
+        }
+        Ok(backups)
+    }
+
+    /// Return the Spin key-value store with the given name, or `None` if no
+    /// such store is configured or it is not a `spin` store.
+    pub fn spin_key_value_store(&self, name: &str) -> Result<Option<KeyValueSqlite>> {
+        let configured = self
+            .opts_layers()
+            .find_map(|opts| Some((opts.key_value_stores.get(name)?, opts)));
+        match configured {
+            Some((KeyValueStoreOpts::Spin(store), opts)) => store.build_sqlite(opts).map(Some),
+            Some(_) => Ok(None),
+            None if name == "default" => match KeyValueStoreOpts::default_store_opts(self) {
+                KeyValueStoreOpts::Spin(store) => {
+                    store.build_sqlite(&RuntimeConfigOpts::default()).map(Some)
+                }
+                _ => Ok(None),
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// Return the periodic backup configured for each named Spin key-value
+    /// store, if any, along with the store to back up.
+    pub fn key_value_backups(&self) -> Result<HashMap<String, (KeyValueSqlite, PeriodicBackup)>> {
+        let mut seen = HashSet::new();
+        let mut backups = HashMap::new();
+        for opts in self.opts_layers() {
+            for (name, store) in &opts.key_value_stores {
+                if !seen.insert(name.to_owned()) {
+                    continue;
+                }
+                let KeyValueStoreOpts::Spin(store) = store else {
+                    continue;
+                };
+                if let Some(backup) = store
+                    .periodic_backup(opts)
+                    .with_context(|| format!("Invalid backup for key-value store '{name}'"))?
+                {
+                    backups.insert(name.to_owned(), (store.build_sqlite(opts)?, backup));
+                }
+            }
+        }
+        Ok(backups)
+    }
+
     /// Set the state dir, overriding any other runtime config source.
     pub fn set_state_dir(&mut self, state_dir: impl Into<String>) {
         self.overrides.state_dir = Some(state_dir.into());
@@ -154,6 +238,24 @@ impl RuntimeConfig {
         self.overrides.log_dir = Some(log_dir.into());
     }
 
+    /// Set the LLM model registry directory, overriding any other runtime
+    /// config source.
+    pub fn set_llm_model_registry(&mut self, registry: impl Into<PathBuf>) {
+        self.llm_overrides.model_registry = Some(registry.into());
+    }
+
+    /// Set the device to run LLM inference on, overriding any other runtime
+    /// config source.
+    pub fn set_llm_device(&mut self, device: LlmDevice) {
+        self.llm_overrides.device = Some(device);
+    }
+
+    /// Set the maximum combined size of the LLM models kept loaded between
+    /// inference calls.
+    pub fn set_llm_model_cache_capacity(&mut self, capacity: ByteSize) {
+        self.llm_overrides.model_cache_capacity = Some(capacity);
+    }
+
     /// Return the log dir if set.
     pub fn log_dir(&self) -> Option<PathBuf> {
         if let Some(path) = self.find_opt(|opts| &opts.log_dir) {
@@ -188,7 +290,7 @@ impl RuntimeConfig {
     }
 }
 
-#[derive(Debug, Default, Deserialize)]
+#[derive(Clone, Debug, Default, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct RuntimeConfigOpts {
     #[serde(default)]
@@ -206,10 +308,87 @@ pub struct RuntimeConfigOpts {
     #[serde(rename = "sqlite_database", default)]
     pub sqlite_databases: HashMap<String, SqliteDatabaseOpts>,
 
+    #[serde(default)]
+    pub llm_compute: Option<LlmComputeOpts>,
+
     #[serde(skip)]
     pub file_path: Option<PathBuf>,
 }
 
+/// Holds deserialized options from the `backup` table of a Spin-managed
+/// `[sqlite_database.<name>]` or `[key_value_store.<name>]` section.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BackupOpts {
+    /// The file to write backups to. Each backup replaces the previous one.
+    pub path: PathBuf,
+    /// How often to take a backup.
+    pub interval_seconds: u64,
+}
+
+impl BackupOpts {
+    fn resolve(
+        &self,
+        database_path: Option<&Path>,
+        config_opts: &RuntimeConfigOpts,
+    ) -> Result<PeriodicBackup> {
+        anyhow::ensure!(
+            database_path.is_some(),
+            "in-memory databases cannot be backed up; set `path` to back up this database"
+        );
+        anyhow::ensure!(
+            self.interval_seconds > 0,
+            "backup `interval_seconds` must be greater than zero"
+        );
+        Ok(PeriodicBackup {
+            destination: resolve_config_path(&self.path, config_opts)?,
+            interval: Duration::from_secs(self.interval_seconds),
+        })
+    }
+}
+
+/// A backup to take periodically while a trigger is running.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PeriodicBackup {
+    pub destination: PathBuf,
+    pub interval: Duration,
+}
+
+impl PeriodicBackup {
+    /// Spawns a task which calls `backup` with the destination path every
+    /// interval, logging any failures. The first backup is taken one interval
+    /// after the task starts.
+    pub(crate) fn spawn<F, Fut>(self, description: String, backup: F)
+    where
+        F: Fn(PathBuf) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(self.interval);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            // The first tick completes immediately.
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                let backup_done = backup(self.destination.clone());
+                match backup_done.await {
+                    Ok(()) => tracing::info!("Backed up {description} to {:?}", self.destination),
+                    Err(err) => tracing::warn!("Failed to back up {description}: {err:?}"),
+                }
+            }
+        });
+    }
+}
+
+fn load_config_file(path: PathBuf) -> Result<RuntimeConfigOpts> {
+    let bytes =
+        fs::read(&path).with_context(|| format!("Failed to load runtime config file {path:?}"))?;
+    let mut opts: RuntimeConfigOpts = toml::from_slice(&bytes)
+        .with_context(|| format!("Failed to parse runtime config file {path:?}"))?;
+    opts.file_path = Some(path);
+    Ok(opts)
+}
+
 fn resolve_config_path(path: &Path, config_opts: &RuntimeConfigOpts) -> Result<PathBuf> {
     if path.is_absolute() {
         return Ok(path.to_owned());
@@ -310,7 +489,7 @@ mod tests {
         let mut config = RuntimeConfig::new(None);
 
         // One default provider
-        assert_eq!(config.config_providers().len(), 1);
+        assert_eq!(config.config_providers()?.len(), 1);
 
         merge_config_toml(
             &mut config,
@@ -322,7 +501,75 @@ mod tests {
                 mount = "root"
             },
         );
-        assert_eq!(config.config_providers().len(), 2);
+        assert_eq!(config.config_providers()?.len(), 2);
+
+        merge_config_toml(
+            &mut config,
+            toml! {
+                [[config_provider]]
+                type = "vault"
+                url = "http://vault"
+                mount = "root"
+                cache_ttl_seconds = 60
+                approle = { role_id = "role", secret_id = "secret" }
+            },
+        );
+        assert_eq!(config.config_providers()?.len(), 3);
+
+        merge_config_toml(
+            &mut config,
+            toml! {
+                [[config_provider]]
+                type = "vault"
+                url = "http://vault"
+                mount = "root"
+            },
+        );
+        assert!(config.config_providers().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn file_config_provider_is_relative_to_config_file() -> Result<()> {
+        let config_dir = tempfile::tempdir()?;
+        fs::write(config_dir.path().join("vars.json"), r#"{"password": "pw"}"#)?;
+        let config_path = config_dir.path().join("runtime-config.toml");
+        fs::write(
+            &config_path,
+            "[[config_provider]]\ntype = \"file\"\npath = \"vars.json\"\n",
+        )?;
+
+        let mut config = RuntimeConfig::new(None);
+        config.merge_config_file(&config_path)?;
+        assert_eq!(config.config_providers()?.len(), 2);
+
+        fs::remove_file(config_dir.path().join("vars.json"))?;
+        assert!(config.config_providers().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn reload_config_files_picks_up_changes() -> Result<()> {
+        let config_dir = tempfile::tempdir()?;
+        fs::write(config_dir.path().join("vars.json"), r#"{"password": "pw"}"#)?;
+        let config_path = config_dir.path().join("runtime-config.toml");
+        let file_provider = "[[config_provider]]\ntype = \"file\"\npath = \"vars.json\"\n";
+        fs::write(&config_path, file_provider)?;
+
+        let mut config = RuntimeConfig::new(None);
+        config.merge_config_file(&config_path)?;
+        assert_eq!(config.config_providers()?.len(), 2);
+
+        fs::write(&config_path, file_provider.repeat(2))?;
+        config.reload_config_files()?;
+        assert_eq!(config.config_providers()?.len(), 3);
+
+        // A file that fails to load leaves the previous options in place.
+        fs::write(&config_path, "[[config_provider]]\ntype = \"unknown\"\n")?;
+        assert!(config.reload_config_files().is_err());
+        assert_eq!(config.config_providers()?.len(), 3);
 
         Ok(())
     }
@@ -373,6 +620,148 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn postgres_key_value_store_from_file() -> Result<()> {
+        let mut config = RuntimeConfig::new(None);
+
+        merge_config_toml(
+            &mut config,
+            toml! {
+                [key_value_store.default]
+                type = "postgres"
+                url = "host=localhost user=postgres dbname=spin_dev"
+                table = "app_key_value"
+            },
+        );
+        assert_eq!(config.key_value_stores().unwrap().into_iter().count(), 1);
+
+        assert!(
+            matches!(
+                config.default_key_value_opts(),
+                KeyValueStoreOpts::Postgres(_)
+            ),
+            "expected default Postgres store",
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn backups_from_file() -> Result<()> {
+        let mut config = RuntimeConfig::new(None);
+        assert!(config.sqlite_backups()?.is_empty());
+        assert!(config.key_value_backups()?.is_empty());
+
+        merge_config_toml(
+            &mut config,
+            toml! {
+                [sqlite_database.default]
+                type = "spin"
+                path = "sqlite.db"
+                backup = { path = "backups/sqlite.db", interval_seconds = 60 }
+
+                [key_value_store.default]
+                type = "spin"
+                path = "kv.db"
+                backup = { path = "backups/kv.db", interval_seconds = 3600 }
+            },
+        );
+
+        let backups = config.sqlite_backups()?;
+        let backup = &backups["default"];
+        assert!(backup.destination.is_absolute());
+        assert!(backup.destination.ends_with("backups/sqlite.db"));
+        assert_eq!(Duration::from_secs(60), backup.interval);
+
+        let backups = config.key_value_backups()?;
+        let (_, backup) = &backups["default"];
+        assert!(backup.destination.ends_with("backups/kv.db"));
+        assert_eq!(Duration::from_secs(3600), backup.interval);
+
+        // In-memory databases can't be backed up
+        merge_config_toml(
+            &mut config,
+            toml! {
+                [sqlite_database.default]
+                type = "spin"
+                backup = { path = "backups/sqlite.db", interval_seconds = 60 }
+            },
+        );
+        assert!(config.sqlite_backups().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn llm_options_from_file() -> Result<()> {
+        let app_dir = tempfile::tempdir()?;
+        let mut config = RuntimeConfig::new(Some(app_dir.path().into()));
+
+        let options = llm::llm_options(&config)?;
+        assert_eq!(config.state_dir().unwrap(), options.model_registry);
+        assert!(options.use_gpu);
+
+        merge_config_toml(
+            &mut config,
+            toml! {
+                [llm_compute]
+                type = "spin"
+                registry = "models"
+                device = "cpu"
+                parallelism = 2
+                threads = 8
+
+                [llm_compute.models.custom]
+                file = "custom.bin"
+                architecture = "llama"
+            },
+        );
+        let options = llm::llm_options(&config)?;
+        assert!(options.model_registry.is_absolute());
+        assert!(options.model_registry.ends_with("models"));
+        assert!(!options.use_gpu);
+        assert_eq!("llama", options.models["custom"].architecture);
+        assert_eq!(2, options.limits.parallelism);
+        assert_eq!(8, options.limits.threads);
+
+        config.set_llm_model_registry("/registry");
+        config.set_llm_device(LlmDevice::Gpu);
+        let options = llm::llm_options(&config)?;
+        assert_eq!(PathBuf::from("/registry"), options.model_registry);
+        assert!(options.use_gpu);
+
+        Ok(())
+    }
+
+    #[test]
+    fn llm_remote_http_from_file() -> Result<()> {
+        let mut config = RuntimeConfig::new(None);
+        merge_config_toml(
+            &mut config,
+            toml! {
+                [llm_compute]
+                type = "remote_http"
+                url = "http://localhost:8000/v1"
+                auth_token = "s3cr3t"
+                models = { "llama2-7b-chat" = "llama-2-7b-chat-hf" }
+            },
+        );
+        llm::build_component(&config)?;
+        assert!(!format!("{config:?}").contains("s3cr3t"));
+
+        merge_config_toml(
+            &mut config,
+            toml! {
+                [llm_compute]
+                type = "remote_http"
+                url = "localhost:8000"
+            },
+        );
+        assert!(llm::build_component(&config).is_err());
+
+        Ok(())
+    }
+
     fn merge_config_toml(config: &mut RuntimeConfig, value: toml::Value) {
         let data = toml::to_vec(&value).expect("encode toml");
         let mut file = NamedTempFile::new().expect("temp file");
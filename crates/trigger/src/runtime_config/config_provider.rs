@@ -1,20 +1,31 @@
-use std::path::PathBuf;
+use std::{path::PathBuf, sync::Mutex, time::Duration};
 
+use anyhow::{bail, Result};
 use serde::Deserialize;
-use spin_config::provider::{env::EnvProvider, vault::VaultProvider};
+use spin_config::{
+    provider::{
+        env::EnvProvider,
+        file::FileProvider,
+        vault::{VaultAuth, VaultProvider},
+    },
+    ProviderChain,
+};
 
-use super::RuntimeConfig;
+use super::{resolve_config_path, RuntimeConfig, RuntimeConfigOpts};
 
 pub type ConfigProvider = Box<dyn spin_config::Provider>;
 
 const DEFAULT_ENV_PREFIX: &str = "SPIN_CONFIG";
 
+const DEFAULT_VAULT_APPROLE_MOUNT: &str = "approle";
+
 // Holds deserialized options from a `[[config_provider]]` runtime config section.
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
 #[serde(rename_all = "snake_case", tag = "type")]
 pub enum ConfigProviderOpts {
     Env(EnvConfigProviderOpts),
     Vault(VaultConfigProviderOpts),
+    File(FileConfigProviderOpts),
 }
 
 impl ConfigProviderOpts {
@@ -22,15 +33,44 @@ impl ConfigProviderOpts {
         Self::Env(EnvConfigProviderOpts::default_provider_opts(runtime_config))
     }
 
-    pub fn build_provider(&self) -> ConfigProvider {
+    pub fn build_provider(&self, config_opts: &RuntimeConfigOpts) -> Result<ConfigProvider> {
         match self {
-            Self::Env(opts) => opts.build_provider(),
+            Self::Env(opts) => Ok(opts.build_provider()),
             Self::Vault(opts) => opts.build_provider(),
+            Self::File(opts) => opts.build_provider(config_opts),
+        }
+    }
+}
+
+/// Rebuilds config providers from the runtime config files, so that changes
+/// to them (e.g. rotated secrets) take effect without restarting the app.
+pub struct ConfigProviderReloader {
+    runtime_config: Mutex<RuntimeConfig>,
+    providers: ProviderChain,
+}
+
+impl ConfigProviderReloader {
+    pub fn new(runtime_config: RuntimeConfig, providers: ProviderChain) -> Self {
+        Self {
+            runtime_config: Mutex::new(runtime_config),
+            providers,
         }
     }
+
+    /// Reloads the runtime config files and replaces the providers with ones
+    /// built from them. The existing config and providers are kept if this
+    /// fails.
+    pub fn reload(&self) -> Result<()> {
+        let mut runtime_config = self.runtime_config.lock().unwrap();
+        let mut reloaded = runtime_config.clone();
+        reloaded.reload_config_files()?;
+        self.providers.replace(reloaded.config_providers()?);
+        *runtime_config = reloaded;
+        Ok(())
+    }
 }
 
-#[derive(Debug, Default, Deserialize)]
+#[derive(Clone, Debug, Default, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct EnvConfigProviderOpts {
     /// A prefix to add to variable names when resolving from the environment.
@@ -58,23 +98,107 @@ impl EnvConfigProviderOpts {
     }
 }
 
-#[derive(Debug, Default, Deserialize)]
+#[derive(Clone, Debug, Default, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct VaultConfigProviderOpts {
     pub url: String,
-    pub token: String,
+    /// A static token to authenticate with. Exactly one of `token` and
+    /// `approle` must be set.
+    #[serde(default)]
+    pub token: Option<String>,
+    /// AppRole credentials to log in with.
+    #[serde(default)]
+    pub approle: Option<VaultAppRoleOpts>,
     pub mount: String,
     #[serde(default)]
     pub prefix: Option<String>,
+    /// How long to cache resolved values for. Values are read from Vault on
+    /// every lookup if this is not set.
+    #[serde(default)]
+    pub cache_ttl_seconds: Option<u64>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VaultAppRoleOpts {
+    /// The path at which the AppRole auth method is mounted. Defaults to `approle`.
+    #[serde(default)]
+    pub mount: Option<String>,
+    pub role_id: String,
+    pub secret_id: String,
 }
 
 impl VaultConfigProviderOpts {
-    pub fn build_provider(&self) -> ConfigProvider {
-        Box::new(VaultProvider::new(
-            &self.url,
-            &self.token,
-            &self.mount,
-            self.prefix.as_deref(),
-        ))
+    pub fn build_provider(&self) -> Result<ConfigProvider> {
+        let auth = match (&self.token, &self.approle) {
+            (Some(token), None) => VaultAuth::Token(token.clone()),
+            (None, Some(approle)) => VaultAuth::AppRole {
+                mount: approle
+                    .mount
+                    .clone()
+                    .unwrap_or_else(|| DEFAULT_VAULT_APPROLE_MOUNT.to_owned()),
+                role_id: approle.role_id.clone(),
+                secret_id: approle.secret_id.clone(),
+            },
+            _ => bail!("Vault config provider must set exactly one of 'token' and 'approle'"),
+        };
+        let mut provider = VaultProvider::new(&self.url, auth, &self.mount, self.prefix.as_deref());
+        if let Some(ttl) = self.cache_ttl_seconds {
+            provider = provider.with_cache_ttl(Duration::from_secs(ttl));
+        }
+        Ok(Box::new(provider))
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileConfigProviderOpts {
+    /// The path of a TOML or JSON file of variables, relative to the runtime
+    /// config file.
+    pub path: PathBuf,
+    /// Whether to flatten nested tables into variables named by joining the
+    /// keys with underscores.
+    #[serde(default)]
+    pub flatten: bool,
+}
+
+impl FileConfigProviderOpts {
+    pub fn build_provider(&self, config_opts: &RuntimeConfigOpts) -> Result<ConfigProvider> {
+        let path = resolve_config_path(&self.path, config_opts)?;
+        Ok(Box::new(FileProvider::new(path, self.flatten)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn failed_reload_keeps_runtime_config() -> Result<()> {
+        let config_dir = tempfile::tempdir()?;
+        fs::write(config_dir.path().join("vars.json"), r#"{"password": "pw"}"#)?;
+        let config_path = config_dir.path().join("runtime-config.toml");
+        fs::write(
+            &config_path,
+            "[[config_provider]]\ntype = \"file\"\npath = \"vars.json\"\n",
+        )?;
+
+        let mut runtime_config = RuntimeConfig::new(None);
+        runtime_config.merge_config_file(&config_path)?;
+        let providers = ProviderChain::new(runtime_config.config_providers()?);
+        let reloader = ConfigProviderReloader::new(runtime_config, providers);
+
+        // The file parses, but its provider can't be built.
+        fs::write(
+            &config_path,
+            "[[config_provider]]\ntype = \"file\"\npath = \"missing.json\"\n",
+        )?;
+        assert!(reloader.reload().is_err());
+        let runtime_config = reloader.runtime_config.lock().unwrap();
+        assert_eq!(runtime_config.config_providers()?.len(), 2);
+
+        Ok(())
     }
 }
@@ -8,19 +8,21 @@ use spin_key_value::{
     KEY_VALUE_STORES_KEY,
 };
 use spin_key_value_azure::KeyValueAzureCosmos;
+use spin_key_value_postgres::KeyValuePostgres;
 use spin_key_value_sqlite::{DatabaseLocation, KeyValueSqlite};
 
-use super::{resolve_config_path, RuntimeConfigOpts};
+use super::{resolve_config_path, BackupOpts, PeriodicBackup, RuntimeConfigOpts};
 
 const DEFAULT_SPIN_STORE_FILENAME: &str = "sqlite_key_value.db";
 
 pub type KeyValueStore = Arc<dyn StoreManager>;
 
-/// Builds a [`KeyValueComponent`] from the given [`RuntimeConfig`].
+/// Builds a [`KeyValueComponent`] from the given [`RuntimeConfig`], along with the
+/// [`DelegatingStoreManager`] it uses, which may be used to subscribe to changes.
 pub async fn build_key_value_component(
     runtime_config: &RuntimeConfig,
     init_data: &[(String, String)],
-) -> Result<KeyValueComponent> {
+) -> Result<(KeyValueComponent, Arc<DelegatingStoreManager>)> {
     let stores: HashMap<_, _> = runtime_config
         .key_value_stores()
         .context("Failed to build key-value component")?
@@ -47,11 +49,19 @@ pub async fn build_key_value_component(
         }
     }
 
-    let delegating_manager = DelegatingStoreManager::new(stores);
-    let caching_manager = Arc::new(CachingStoreManager::new(delegating_manager));
-    Ok(KeyValueComponent::new(spin_key_value::manager(move |_| {
-        caching_manager.clone()
-    })))
+    for (name, (store, backup)) in runtime_config.key_value_backups()? {
+        let store = Arc::new(store);
+        backup.spawn(format!("key-value store '{name}'"), move |destination| {
+            let store = store.clone();
+            async move { store.backup(&destination).await }
+        });
+    }
+
+    let delegating_manager = Arc::new(DelegatingStoreManager::new(stores));
+    let caching_manager = Arc::new(CachingStoreManager::new(delegating_manager.clone()));
+    let component =
+        KeyValueComponent::new(spin_key_value::manager(move |_| caching_manager.clone()));
+    Ok((component, delegating_manager))
 }
 
 // Holds deserialized options from a `[key_value_store.<name>]` runtime config section.
@@ -61,6 +71,7 @@ pub enum KeyValueStoreOpts {
     Spin(SpinKeyValueStoreOpts),
     Redis(RedisKeyValueStoreOpts),
     AzureCosmos(AzureCosmosConfig),
+    Postgres(PostgresKeyValueStoreOpts),
 }
 
 impl KeyValueStoreOpts {
@@ -73,6 +84,7 @@ impl KeyValueStoreOpts {
             Self::Spin(opts) => opts.build_store(config_opts),
             Self::Redis(opts) => opts.build_store(),
             Self::AzureCosmos(opts) => opts.build_store(),
+            Self::Postgres(opts) => opts.build_store(),
         }
     }
 }
@@ -81,6 +93,8 @@ impl KeyValueStoreOpts {
 #[serde(deny_unknown_fields)]
 pub struct SpinKeyValueStoreOpts {
     pub path: Option<PathBuf>,
+    #[serde(default)]
+    pub backup: Option<BackupOpts>,
 }
 
 impl SpinKeyValueStoreOpts {
@@ -89,10 +103,14 @@ impl SpinKeyValueStoreOpts {
         let path = runtime_config
             .state_dir()
             .map(|dir| dir.join(DEFAULT_SPIN_STORE_FILENAME));
-        Self { path }
+        Self { path, backup: None }
     }
 
     fn build_store(&self, config_opts: &RuntimeConfigOpts) -> Result<KeyValueStore> {
+        Ok(Arc::new(self.build_sqlite(config_opts)?))
+    }
+
+    pub(crate) fn build_sqlite(&self, config_opts: &RuntimeConfigOpts) -> Result<KeyValueSqlite> {
         let location = match self.path.as_ref() {
             Some(path) => {
                 let path = resolve_config_path(path, config_opts)?;
@@ -103,7 +121,17 @@ impl SpinKeyValueStoreOpts {
             }
             None => DatabaseLocation::InMemory,
         };
-        Ok(Arc::new(KeyValueSqlite::new(location)))
+        Ok(KeyValueSqlite::new(location))
+    }
+
+    pub(crate) fn periodic_backup(
+        &self,
+        config_opts: &RuntimeConfigOpts,
+    ) -> Result<Option<PeriodicBackup>> {
+        self.backup
+            .as_ref()
+            .map(|backup| backup.resolve(self.path.as_deref(), config_opts))
+            .transpose()
     }
 }
 
@@ -119,6 +147,9 @@ impl RedisKeyValueStoreOpts {
     }
 }
 
+/// Options for a store backed by an Azure Cosmos DB container. The container should
+/// have time-to-live enabled (with a default of -1) so that Cosmos deletes expired
+/// entries; otherwise they are only hidden from reads.
 #[derive(Clone, Debug, Deserialize)]
 pub struct AzureCosmosConfig {
     key: String,
@@ -139,6 +170,20 @@ impl AzureCosmosConfig {
     }
 }
 
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PostgresKeyValueStoreOpts {
+    pub url: String,
+    pub table: Option<String>,
+}
+
+impl PostgresKeyValueStoreOpts {
+    fn build_store(&self) -> Result<KeyValueStore> {
+        let kv_postgres = KeyValuePostgres::new(&self.url, self.table.clone())?;
+        Ok(Arc::new(kv_postgres))
+    }
+}
+
 // Prints startup messages about the default key value store config.
 pub struct KeyValuePersistenceMessageHook;
 
@@ -167,6 +212,15 @@ impl TriggerHooks for KeyValuePersistenceMessageHook {
             KeyValueStoreOpts::AzureCosmos(store_opts) => {
                 println!("Storing default key-value data to Azure CosmosDB: account: {}, database: {}, container: {}", store_opts.account, store_opts.database, store_opts.container);
             }
+            KeyValueStoreOpts::Postgres(store_opts) => {
+                match KeyValuePostgres::new(&store_opts.url, store_opts.table.clone()) {
+                    Ok(kv_postgres) => println!(
+                        "Storing default key-value data to Postgres: {}",
+                        kv_postgres.location()
+                    ),
+                    Err(_) => println!("Storing default key-value data to Postgres"),
+                }
+            }
         }
         Ok(())
     }
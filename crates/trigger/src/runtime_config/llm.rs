@@ -0,0 +1,183 @@
+use std::{collections::HashMap, path::PathBuf, sync::Arc};
+
+use anyhow::{ensure, Context, Result};
+use bytesize::ByteSize;
+use serde::Deserialize;
+use spin_llm::{
+    InferenceLimits, LLmOptions, LlmBackend, LlmComponent, LocalBackend, RegistryModel,
+    RemoteHttpBackend, RemoteHttpOptions,
+};
+
+use crate::runtime_config::RuntimeConfig;
+
+use super::{resolve_config_path, RuntimeConfigOpts};
+
+/// The registry directory used when there is no state dir, e.g. for remote apps.
+const DEFAULT_MODEL_REGISTRY: &str = ".spin";
+
+const DEFAULT_MODEL_CACHE_CAPACITY: ByteSize = ByteSize(16 * bytesize::GIB);
+
+pub(crate) fn build_component(runtime_config: &RuntimeConfig) -> Result<LlmComponent> {
+    let backend: Arc<dyn LlmBackend> = match configured_opts(runtime_config) {
+        Some((LlmComputeOpts::RemoteHttp(opts), _)) => Arc::new(opts.build_backend()?),
+        _ => Arc::new(
+            LocalBackend::new(llm_options(runtime_config)?)
+                .context("Failed to build LLM component")?,
+        ),
+    };
+    Ok(LlmComponent::new(backend))
+}
+
+/// Returns the options for running models locally, which are the defaults
+/// unless the `[llm_compute]` section configures the `spin` backend.
+pub(crate) fn llm_options(runtime_config: &RuntimeConfig) -> Result<LLmOptions> {
+    match configured_opts(runtime_config) {
+        Some((LlmComputeOpts::Spin(opts), config_opts)) => {
+            opts.build_options(runtime_config, config_opts)
+        }
+        _ => SpinLlmComputeOpts::default()
+            .build_options(runtime_config, &RuntimeConfigOpts::default()),
+    }
+}
+
+fn configured_opts(
+    runtime_config: &RuntimeConfig,
+) -> Option<(&LlmComputeOpts, &RuntimeConfigOpts)> {
+    runtime_config
+        .opts_layers()
+        .find_map(|opts| Some((opts.llm_compute.as_ref()?, opts)))
+}
+
+// Holds deserialized options from the `[llm_compute]` runtime config section.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum LlmComputeOpts {
+    Spin(SpinLlmComputeOpts),
+    RemoteHttp(RemoteHttpLlmComputeOpts),
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SpinLlmComputeOpts {
+    /// The directory containing the model files. Defaults to the state dir.
+    pub registry: Option<PathBuf>,
+    /// The device on which to run inference. Defaults to the GPU.
+    pub device: Option<LlmDevice>,
+    /// Models in the registry beyond the Llama 2 chat models, by name.
+    #[serde(default)]
+    pub models: HashMap<String, RegistryModelOpts>,
+    /// The number of inferences which may run at once. Defaults to 1.
+    pub parallelism: Option<usize>,
+    /// The number of threads shared between running inferences. Defaults to
+    /// the number of CPUs.
+    pub threads: Option<usize>,
+    /// The number of inferences which may wait for a turn to run. Defaults to 16.
+    pub queue_size: Option<usize>,
+}
+
+impl SpinLlmComputeOpts {
+    fn build_options(
+        &self,
+        runtime_config: &RuntimeConfig,
+        config_opts: &RuntimeConfigOpts,
+    ) -> Result<LLmOptions> {
+        let overrides = &runtime_config.llm_overrides;
+        let model_registry = match (&overrides.model_registry, &self.registry) {
+            (Some(registry), _) => registry.clone(),
+            (None, Some(registry)) => resolve_config_path(registry, config_opts)?,
+            (None, None) => runtime_config
+                .state_dir()
+                .unwrap_or_else(|| DEFAULT_MODEL_REGISTRY.into()),
+        };
+        let device = overrides.device.or(self.device).unwrap_or(LlmDevice::Gpu);
+        let models = self
+            .models
+            .iter()
+            .map(|(name, model)| {
+                let model = RegistryModel {
+                    file: model.file.clone(),
+                    architecture: model.architecture.clone(),
+                };
+                (name.clone(), model)
+            })
+            .collect();
+        let default_limits = InferenceLimits::default();
+        let limits = InferenceLimits {
+            parallelism: self.parallelism.unwrap_or(default_limits.parallelism),
+            threads: self.threads.unwrap_or(default_limits.threads),
+            queue_size: self.queue_size.unwrap_or(default_limits.queue_size),
+        };
+
+        Ok(LLmOptions {
+            model_registry,
+            use_gpu: device == LlmDevice::Gpu,
+            model_cache_capacity: overrides
+                .model_cache_capacity
+                .unwrap_or(DEFAULT_MODEL_CACHE_CAPACITY),
+            models,
+            limits,
+        })
+    }
+}
+
+/// Options for forwarding inference to an OpenAI-compatible HTTP API.
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RemoteHttpLlmComputeOpts {
+    /// The base URL of the API, e.g. `https://api.openai.com/v1`.
+    pub url: String,
+    pub auth_token: Option<String>,
+    /// The names the API uses for models, by the name guests use for them.
+    #[serde(default)]
+    pub models: HashMap<String, String>,
+}
+
+impl std::fmt::Debug for RemoteHttpLlmComputeOpts {
+    // Leaves out the auth token.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RemoteHttpLlmComputeOpts")
+            .field("url", &self.url)
+            .field("models", &self.models)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RemoteHttpLlmComputeOpts {
+    fn build_backend(&self) -> Result<RemoteHttpBackend> {
+        ensure!(
+            self.url.starts_with("https://") || self.url.starts_with("http://"),
+            "unexpected LLM compute URL '{}' in runtime config file: URL does not start with 'https://' or 'http://'",
+            self.url
+        );
+        Ok(RemoteHttpBackend::new(RemoteHttpOptions {
+            url: self.url.clone(),
+            auth_token: self.auth_token.clone(),
+            models: self.models.clone(),
+        }))
+    }
+}
+
+/// The device on which to run inference.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum LlmDevice {
+    Cpu,
+    Gpu,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RegistryModelOpts {
+    /// The model file, relative to the registry.
+    pub file: PathBuf,
+    pub architecture: String,
+}
+
+/// LLM options set on the command line, which take precedence over the
+/// `[llm_compute]` section of any runtime config file.
+#[derive(Clone, Debug, Default)]
+pub struct LlmOverrides {
+    pub model_registry: Option<PathBuf>,
+    pub device: Option<LlmDevice>,
+    pub model_cache_capacity: Option<ByteSize>,
+}
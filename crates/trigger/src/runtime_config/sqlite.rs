@@ -2,25 +2,60 @@ use std::{collections::HashMap, path::PathBuf, sync::Arc};
 
 use crate::{runtime_config::RuntimeConfig, TriggerHooks};
 use anyhow::Context;
-use spin_sqlite::{Connection, ConnectionsStore, SqliteComponent, DATABASES_KEY};
+use spin_sqlite::{
+    Connection, ConnectionsStore, SqliteComponent, Statement, DATABASES_KEY,
+    DATABASE_MIGRATIONS_KEY,
+};
 
-use super::RuntimeConfigOpts;
+use super::{BackupOpts, PeriodicBackup, RuntimeConfigOpts};
 
 const DEFAULT_SQLITE_DB_FILENAME: &str = "sqlite_db.db";
 
+pub(crate) type SqliteDatabases = HashMap<String, Arc<dyn Connection>>;
+
+/// Build the sqlite component, returning it along with its databases so that
+/// they can be initialized once the app is loaded.
 pub(crate) async fn build_component(
     runtime_config: &RuntimeConfig,
-    sqlite_statements: &[String],
-) -> anyhow::Result<SqliteComponent> {
-    let databases: HashMap<_, _> = runtime_config
+) -> anyhow::Result<(SqliteComponent, SqliteDatabases)> {
+    let databases: SqliteDatabases = runtime_config
         .sqlite_databases()
         .context("Failed to build sqlite component")?
         .into_iter()
         .collect();
-    execute_statements(sqlite_statements, &databases).await?;
+    for (name, backup) in runtime_config.sqlite_backups()? {
+        let Some(database) = databases.get(&name).cloned() else {
+            continue;
+        };
+        backup.spawn(format!("sqlite database '{name}'"), move |destination| {
+            let database = database.clone();
+            async move { database.backup(&destination).await }
+        });
+    }
     let connections_store =
-        Arc::new(SimpleConnectionsStore(databases)) as Arc<dyn ConnectionsStore>;
-    Ok(SqliteComponent::new(move |_| connections_store.clone()))
+        Arc::new(SimpleConnectionsStore(databases.clone())) as Arc<dyn ConnectionsStore>;
+    let component = SqliteComponent::new(move |_| connections_store.clone());
+    Ok((component, databases))
+}
+
+/// Apply the migrations declared in the app's manifest, then execute the
+/// statements given on the command line against the default database.
+pub(crate) async fn init_databases(
+    app: &spin_app::App<'_>,
+    databases: &SqliteDatabases,
+    sqlite_statements: &[String],
+) -> anyhow::Result<()> {
+    let mut migrations = HashMap::new();
+    for component in app.components() {
+        // The loader ensures that components agree on each database's migrations.
+        migrations.extend(
+            component
+                .get_metadata(DATABASE_MIGRATIONS_KEY)?
+                .unwrap_or_default(),
+        );
+    }
+    run_migrations(&migrations, databases).await?;
+    execute_statements(sqlite_statements, databases).await
 }
 
 /// A `ConnectionStore` based on a `HashMap`
@@ -40,6 +75,28 @@ impl ConnectionsStore for SimpleConnectionsStore {
     }
 }
 
+/// Apply any pending migrations to each database with a migrations directory.
+async fn run_migrations(
+    migrations: &HashMap<String, PathBuf>,
+    databases: &SqliteDatabases,
+) -> anyhow::Result<()> {
+    for (name, dir) in migrations {
+        let Some(database) = databases.get(name) else {
+            continue;
+        };
+        let applied = spin_sqlite::migrations::migrate(database.as_ref(), dir)
+            .await
+            .with_context(|| format!("failed to migrate sqlite database '{name}'"))?;
+        for migration in applied {
+            tracing::info!(
+                "Applied migration '{}' to sqlite database '{name}'",
+                migration.name
+            );
+        }
+    }
+    Ok(())
+}
+
 async fn execute_statements(
     statements: &[String],
     databases: &HashMap<String, Arc<dyn spin_sqlite::Connection>>,
@@ -47,9 +104,12 @@ async fn execute_statements(
     if statements.is_empty() {
         return Ok(());
     }
-    let Some(default) = databases.get("default") else { 
-        debug_assert!(false, "the 'default' sqlite database should always be available but for some reason was not");
-        return Ok(())
+    let Some(default) = databases.get("default") else {
+        debug_assert!(
+            false,
+            "the 'default' sqlite database should always be available but for some reason was not"
+        );
+        return Ok(());
     };
 
     for m in statements {
@@ -57,15 +117,16 @@ async fn execute_statements(
             let sql = std::fs::read_to_string(file).with_context(|| {
                 format!("could not read file '{file}' containing sql statements")
             })?;
-            default
-                .execute_batch(&sql)
+            spin_sqlite::execute_statements(default.as_ref(), &[Statement::Batch(sql)])
                 .await
                 .with_context(|| format!("failed to execute sql from file '{file}'"))?;
         } else {
-            default
-                .query(m, Vec::new())
-                .await
-                .with_context(|| format!("failed to execute statement: '{m}'"))?;
+            spin_sqlite::execute_statements(
+                default.as_ref(),
+                &[Statement::Query(m.clone(), Vec::new())],
+            )
+            .await
+            .with_context(|| format!("failed to execute statement: '{m}'"))?;
         }
     }
     Ok(())
@@ -90,12 +151,29 @@ impl SqliteDatabaseOpts {
             Self::Libsql(opts) => opts.build(),
         }
     }
+
+    /// The periodic backup to take of this database, if any.
+    pub fn periodic_backup(
+        &self,
+        config_opts: &RuntimeConfigOpts,
+    ) -> anyhow::Result<Option<PeriodicBackup>> {
+        match self {
+            Self::Spin(SpinSqliteDatabaseOpts {
+                path,
+                backup: Some(backup),
+                ..
+            }) => backup.resolve(path.as_deref(), config_opts).map(Some),
+            _ => Ok(None),
+        }
+    }
 }
 
 #[derive(Clone, Debug, serde::Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct SpinSqliteDatabaseOpts {
     pub path: Option<PathBuf>,
+    #[serde(default)]
+    pub backup: Option<BackupOpts>,
 }
 
 impl SpinSqliteDatabaseOpts {
@@ -103,7 +181,7 @@ impl SpinSqliteDatabaseOpts {
         let path = runtime_config
             .state_dir()
             .map(|dir| dir.join(DEFAULT_SQLITE_DB_FILENAME));
-        Self { path }
+        Self { path, backup: None }
     }
 
     fn build(&self, config_opts: &RuntimeConfigOpts) -> anyhow::Result<Arc<dyn Connection>> {
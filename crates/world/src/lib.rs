@@ -7,3 +7,13 @@ wasmtime::component::bindgen!({
 });
 
 pub use fermyon::spin::*;
+
+/// Bindings for the `key-value-trigger` world, which keeps the key-value
+/// trigger's export out of the `reactor` world.
+pub mod key_value_trigger {
+    wasmtime::component::bindgen!({
+        path: "../../wit/preview2",
+        world: "key-value-trigger",
+        async: true
+    });
+}
@@ -80,3 +80,30 @@ pub fn redis_component(_attr: TokenStream, item: TokenStream) -> TokenStream {
     )
     .into()
 }
+
+/// Generates the entrypoint to a Spin key-value change component written in Rust.
+///
+/// This requires the `key-value-trigger` feature of `spin-sdk`, which builds the SDK
+/// for the key-value trigger's world rather than the HTTP and Redis one.
+#[proc_macro_attribute]
+pub fn key_value_component(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    let func = syn::parse_macro_input!(item as syn::ItemFn);
+    let func_name = &func.sig.ident;
+
+    quote!(
+        #func
+
+        mod __spin_key_value {
+            struct Spin;
+            ::spin_sdk::export_key_value_trigger!(Spin);
+
+            impl ::spin_sdk::inbound_key_value::InboundKeyValue for Spin {
+                fn handle_change(event: ::spin_sdk::inbound_key_value::ChangeEvent) -> Result<(), String> {
+                    // The trigger logs the error, so there's no need to print it here.
+                    super::#func_name(event).map_err(|e| format!("{e:#}"))
+                }
+            }
+        }
+    )
+    .into()
+}
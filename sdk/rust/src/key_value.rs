@@ -13,6 +13,13 @@ use serde::{de::DeserializeOwned, Serialize};
 /// Errors which may be raised by the methods of `Store`
 pub use key_value::Error;
 
+/// A page of keys returned by `Store::list_keys`
+pub use key_value::KeyPage;
+
+/// A change to a key-value tuple, as passed to a `#[key_value_component]` handler
+#[cfg(feature = "key-value-trigger")]
+pub use super::wit::exports::fermyon::spin::inbound_key_value::{ChangeEvent, ChangeKind};
+
 /// Represents a store in which key value tuples may be placed
 #[derive(Debug)]
 pub struct Store(RawStore);
@@ -47,6 +54,19 @@ impl Store {
         key_value::set(self.0, key.as_ref(), value.as_ref())
     }
 
+    /// Set the value for the specified key, expiring it after `ttl_seconds` seconds.
+    ///
+    /// This will overwrite any previous value, if present.  Once expired, the tuple behaves as if it had been
+    /// deleted.
+    pub fn set_with_ttl(
+        &self,
+        key: impl AsRef<str>,
+        value: impl AsRef<[u8]>,
+        ttl_seconds: u32,
+    ) -> Result<(), Error> {
+        key_value::set_with_ttl(self.0, key.as_ref(), value.as_ref(), ttl_seconds)
+    }
+
     /// Delete the tuple for the specified key, if any.
     ///
     /// This will have no effect and return `Ok(())` if the tuple was not present.
@@ -59,11 +79,69 @@ impl Store {
         key_value::exists(self.0, key.as_ref())
     }
 
+    /// Get the values, if any, associated with each of the specified keys in this store.
+    ///
+    /// The result contains one element per key, in the same order, which is `None` if no value was found.
+    pub fn get_many(&self, keys: &[&str]) -> Result<Vec<Option<Vec<u8>>>, Error> {
+        key_value::get_many(self.0, keys)
+    }
+
+    /// Set the values for each of the specified keys.
+    ///
+    /// This will overwrite any previous values, if present. This is not atomic
+    /// for every store, so if it fails, some of the values may have been set.
+    pub fn set_many(&self, key_values: &[(&str, &[u8])]) -> Result<(), Error> {
+        key_value::set_many(self.0, key_values)
+    }
+
+    /// Delete the tuples for each of the specified keys, if any.
+    ///
+    /// This is not atomic for every store, so if it fails, some of the tuples
+    /// may have been deleted.
+    pub fn delete_many(&self, keys: &[&str]) -> Result<(), Error> {
+        key_value::delete_many(self.0, keys)
+    }
+
     /// Get the set of keys in this store.
     pub fn get_keys(&self) -> Result<Vec<String>, Error> {
         key_value::get_keys(self.0)
     }
 
+    /// Get a page of the keys in this store which start with `prefix`.
+    ///
+    /// Pass `None` as the `cursor` to get the first page, and the `cursor` from each returned page to get the
+    /// next one, stopping when it is `None`.  Pages may contain more or fewer than `limit` keys depending on the
+    /// store implementation.
+    pub fn list_keys(
+        &self,
+        prefix: impl AsRef<str>,
+        cursor: Option<&str>,
+        limit: u32,
+    ) -> Result<KeyPage, Error> {
+        key_value::list_keys(self.0, prefix.as_ref(), cursor, limit)
+    }
+
+    /// Atomically add `delta` to the integer value for the specified key, returning the new value.
+    ///
+    /// A missing value is treated as zero.  The value is stored as a decimal string, so it can also be read
+    /// using `get`.
+    pub fn increment(&self, key: impl AsRef<str>, delta: i64) -> Result<i64, Error> {
+        key_value::increment(self.0, key.as_ref(), delta)
+    }
+
+    /// Atomically set the value for the specified key if and only if its current value is `expected`.
+    ///
+    /// If `expected` is `None`, the value is only set if no tuple exists for the key.  Returns whether the value
+    /// was set.
+    pub fn compare_and_swap(
+        &self,
+        key: impl AsRef<str>,
+        expected: Option<&[u8]>,
+        value: impl AsRef<[u8]>,
+    ) -> Result<bool, Error> {
+        key_value::compare_and_swap(self.0, key.as_ref(), expected, value.as_ref())
+    }
+
     #[cfg(feature = "json")]
     /// Serialize the given data to JSON, then set it as the value for the specified `key`.
     pub fn set_json<T: Serialize>(
@@ -23,6 +23,7 @@ pub use spin_macro::*;
 /// This is only meant for internal consumption.
 pub mod wit {
     #![allow(missing_docs)]
+    #[cfg(not(feature = "key-value-trigger"))]
     wit_bindgen::generate!({
         world: "reactor",
         path: "../../wit/preview2",
@@ -30,6 +31,14 @@ pub mod wit {
         duplicate_if_necessary,
         macro_export
     });
+    #[cfg(feature = "key-value-trigger")]
+    wit_bindgen::generate!({
+        world: "key-value-trigger",
+        path: "../../wit/preview2",
+        macro_call_prefix: "::spin_sdk::wit::",
+        duplicate_if_necessary,
+        macro_export
+    });
 }
 
 /// Needed by the export macro
@@ -137,6 +146,7 @@ pub mod config {
 /// Inbound http trigger functionality
 // Hide the docs since this is only needed for the macro
 #[doc(hidden)]
+#[cfg(not(feature = "key-value-trigger"))]
 pub mod inbound_http {
     use super::wit::exports::fermyon::spin::inbound_http;
     use super::wit::fermyon::spin::http_types as spin_http_types;
@@ -259,6 +269,15 @@ pub mod inbound_http {
 /// Inbound redis trigger functionality
 // Hide the docs since this is only needed for the macro
 #[doc(hidden)]
+#[cfg(not(feature = "key-value-trigger"))]
 pub mod inbound_redis {
     pub use super::wit::exports::fermyon::spin::inbound_redis::*;
 }
+
+/// Inbound key-value change trigger functionality
+// Hide the docs since this is only needed for the macro
+#[doc(hidden)]
+#[cfg(feature = "key-value-trigger")]
+pub mod inbound_key_value {
+    pub use super::wit::exports::fermyon::spin::inbound_key_value::*;
+}
@@ -2,7 +2,7 @@ use crate::wit::fermyon::spin::llm;
 
 pub use crate::wit::fermyon::spin::llm::{
     generate_embeddings, EmbeddingModel, Error, InferencingModel, InferencingParams,
-    InferencingResult,
+    InferencingResult, InferencingUsage,
 };
 
 /// Perform inferencing using the provided model and prompt
@@ -18,3 +18,33 @@ pub fn infer_with_options(
 ) -> Result<InferencingResult, Error> {
     llm::infer(model, prompt, Some(options))
 }
+
+/// Perform inferencing using the provided model, prompt, and optional options, returning
+/// the inferred text in chunks as it is generated
+pub fn infer_stream(
+    model: InferencingModel,
+    prompt: &str,
+    options: Option<InferencingParams>,
+) -> Result<InferencingStream, Error> {
+    llm::infer_stream(model, prompt, options).map(InferencingStream)
+}
+
+/// A stream of text being inferred, returned by [`infer_stream`]
+///
+/// Inferencing is stopped if the stream is dropped before it is complete.
+#[derive(Debug)]
+pub struct InferencingStream(llm::InferencingStream);
+
+impl Iterator for InferencingStream {
+    type Item = Result<String, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        llm::read_stream(self.0).transpose()
+    }
+}
+
+impl Drop for InferencingStream {
+    fn drop(&mut self) {
+        llm::close_stream(self.0)
+    }
+}
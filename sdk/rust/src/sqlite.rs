@@ -2,6 +2,7 @@
 
 use super::wit::fermyon::spin::sqlite;
 use sqlite::Connection as RawConnection;
+use sqlite::Transaction as RawTransaction;
 
 /// Errors which may be raised by the methods of `Store`
 pub use sqlite::Error;
@@ -37,6 +38,48 @@ impl Connection {
     ) -> Result<sqlite::QueryResult, Error> {
         sqlite::execute(self.0, query, parameters)
     }
+
+    /// Begin a transaction. Statements executed in the transaction are not
+    /// visible to other connections until it is committed.
+    pub fn begin_transaction(&self) -> Result<Transaction, Error> {
+        Ok(Transaction(sqlite::begin_transaction(self.0)?))
+    }
+}
+
+/// A transaction on a database, which is rolled back if dropped without
+/// being committed
+#[derive(Debug)]
+pub struct Transaction(RawTransaction);
+
+impl Transaction {
+    /// Execute a statement within the transaction
+    pub fn execute(
+        &self,
+        query: &str,
+        parameters: &[ValueParam<'_>],
+    ) -> Result<sqlite::QueryResult, Error> {
+        sqlite::execute_in_transaction(self.0, query, parameters)
+    }
+
+    /// Commit the transaction
+    pub fn commit(self) -> Result<(), Error> {
+        let tx = self.0;
+        std::mem::forget(self);
+        sqlite::commit(tx)
+    }
+
+    /// Roll back the transaction
+    pub fn rollback(self) -> Result<(), Error> {
+        let tx = self.0;
+        std::mem::forget(self);
+        sqlite::rollback(tx)
+    }
+}
+
+impl Drop for Transaction {
+    fn drop(&mut self) {
+        let _ = sqlite::rollback(self.0);
+    }
 }
 
 impl sqlite::QueryResult {
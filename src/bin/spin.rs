@@ -8,9 +8,11 @@ use spin_cli::commands::{
     cloud::{DeployCommand, LoginCommand},
     doctor::DoctorCommand,
     external::execute_external_subcommand,
+    kv::KeyValueCommands,
     new::{AddCommand, NewCommand},
     plugins::PluginCommands,
     registry::RegistryCommands,
+    sqlite::SqliteCommands,
     templates::TemplateCommands,
     up::UpCommand,
     watch::WatchCommand,
@@ -20,6 +22,7 @@ use spin_redis_engine::RedisTrigger;
 use spin_trigger::cli::help::HelpArgsOnlyTrigger;
 use spin_trigger::cli::TriggerExecutorCommand;
 use spin_trigger_http::HttpTrigger;
+use spin_trigger_key_value::KeyValueTrigger;
 
 #[tokio::main]
 async fn main() {
@@ -133,12 +136,18 @@ enum SpinApp {
     External(Vec<String>),
     Watch(WatchCommand),
     Doctor(DoctorCommand),
+    #[clap(subcommand)]
+    Kv(KeyValueCommands),
+    #[clap(subcommand)]
+    Sqlite(SqliteCommands),
 }
 
 #[derive(Subcommand)]
 enum TriggerCommands {
     Http(TriggerExecutorCommand<HttpTrigger>),
     Redis(TriggerExecutorCommand<RedisTrigger>),
+    #[clap(name = "key_value")]
+    KeyValue(TriggerExecutorCommand<KeyValueTrigger>),
     #[clap(name = spin_cli::HELP_ARGS_ONLY_TRIGGER_TYPE, hide = true)]
     HelpArgsOnly(TriggerExecutorCommand<HelpArgsOnlyTrigger>),
 }
@@ -157,11 +166,14 @@ impl SpinApp {
             Self::Build(cmd) => cmd.run().await,
             Self::Trigger(TriggerCommands::Http(cmd)) => cmd.run().await,
             Self::Trigger(TriggerCommands::Redis(cmd)) => cmd.run().await,
+            Self::Trigger(TriggerCommands::KeyValue(cmd)) => cmd.run().await,
             Self::Trigger(TriggerCommands::HelpArgsOnly(cmd)) => cmd.run().await,
             Self::Plugins(cmd) => cmd.run().await,
             Self::External(cmd) => execute_external_subcommand(cmd, app).await,
             Self::Watch(cmd) => cmd.run().await,
             Self::Doctor(cmd) => cmd.run().await,
+            Self::Kv(cmd) => cmd.run().await,
+            Self::Sqlite(cmd) => cmd.run().await,
         }
     }
 }
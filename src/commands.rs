@@ -8,12 +8,16 @@ pub mod cloud;
 pub mod doctor;
 /// Commands for external subcommands (i.e. plugins)
 pub mod external;
+/// Commands for working with key-value stores.
+pub mod kv;
 /// Command for creating a new application.
 pub mod new;
 /// Command for adding a plugin to Spin
 pub mod plugins;
 /// Commands for working with OCI registries.
 pub mod registry;
+/// Commands for working with SQLite databases.
+pub mod sqlite;
 /// Commands for working with templates.
 pub mod templates;
 /// Commands for starting the runtime.
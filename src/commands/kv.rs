@@ -0,0 +1,412 @@
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Write},
+    path::PathBuf,
+    sync::Arc,
+};
+
+use anyhow::{bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use clap::{Args, Parser, Subcommand};
+use serde::{Deserialize, Serialize};
+use spin_key_value::{Error as KeyValueError, Store};
+use spin_trigger::RuntimeConfig;
+
+use crate::opts::*;
+
+/// The number of keys to fetch or write at a time when exporting or importing.
+const BATCH_SIZE: u32 = 100;
+
+/// Commands for inspecting and modifying an application's key-value stores.
+#[derive(Subcommand, Debug)]
+pub enum KeyValueCommands {
+    /// List the keys in a store.
+    List(List),
+    /// Print the value for a key.
+    Get(Get),
+    /// Set the value for a key.
+    Set(Set),
+    /// Delete one or more keys.
+    Delete(Delete),
+    /// Export the contents of a store as JSON lines.
+    Export(Export),
+    /// Import JSON lines, as written by `spin kv export`, into a store.
+    Import(Import),
+}
+
+impl KeyValueCommands {
+    pub async fn run(self) -> Result<()> {
+        match self {
+            KeyValueCommands::List(cmd) => cmd.run().await,
+            KeyValueCommands::Get(cmd) => cmd.run().await,
+            KeyValueCommands::Set(cmd) => cmd.run().await,
+            KeyValueCommands::Delete(cmd) => cmd.run().await,
+            KeyValueCommands::Export(cmd) => cmd.run().await,
+            KeyValueCommands::Import(cmd) => cmd.run().await,
+        }
+    }
+}
+
+/// Options for locating an application's runtime configuration, resolved in
+/// the same way as by `spin up`.
+#[derive(Args, Debug)]
+pub struct AppRuntimeConfigOptions {
+    /// The application whose state to use. This may be a manifest (spin.toml)
+    /// file, or a directory containing a spin.toml file.
+    /// If omitted, it defaults to "spin.toml".
+    #[clap(
+        name = APP_MANIFEST_FILE_OPT,
+        short = 'f',
+        long = "from",
+        alias = "file",
+        default_value = DEFAULT_MANIFEST_FILE
+    )]
+    pub app_source: PathBuf,
+
+    /// Configuration file for key-value stores and other runtime resources.
+    #[clap(long = "runtime-config-file", env = "RUNTIME_CONFIG_FILE")]
+    pub runtime_config_file: Option<PathBuf>,
+
+    /// Set the application state directory path. This is used in the default
+    /// locations for key value stores, etc.
+    ///
+    /// This defaults to `.spin/` relative to the `spin.toml` file.
+    #[clap(long)]
+    pub state_dir: Option<String>,
+}
+
+impl AppRuntimeConfigOptions {
+    /// Build the runtime config for the application.
+    pub fn runtime_config(&self) -> Result<RuntimeConfig> {
+        let manifest_file = spin_common::paths::resolve_manifest_file_path(&self.app_source)?;
+        let local_app_dir = manifest_file.parent().map(ToOwned::to_owned);
+
+        let mut config = RuntimeConfig::new(local_app_dir);
+        if let Some(state_dir) = &self.state_dir {
+            config.set_state_dir(state_dir);
+        }
+        if let Some(config_file) = &self.runtime_config_file {
+            config.merge_config_file(config_file)?;
+        }
+        Ok(config)
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct StoreOptions {
+    #[clap(flatten)]
+    pub app: AppRuntimeConfigOptions,
+
+    /// The name of the store to use.
+    #[clap(short = 's', long = "store", default_value = "default")]
+    pub store: String,
+}
+
+impl StoreOptions {
+    async fn open(&self) -> Result<Arc<dyn Store>> {
+        let stores: HashMap<_, _> = self
+            .app
+            .runtime_config()?
+            .key_value_stores()
+            .context("Failed to build key-value stores")?
+            .into_iter()
+            .collect();
+
+        let Some(manager) = stores.get(&self.store) else {
+            bail!(
+                "No key-value store named '{}' is defined. Pass a runtime configuration file which defines it.",
+                self.store
+            );
+        };
+
+        manager
+            .get(&self.store)
+            .await
+            .with_context(|| format!("Failed to open key-value store '{}'", self.store))
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct List {
+    #[clap(flatten)]
+    pub store: StoreOptions,
+
+    /// Only list keys starting with this prefix.
+    #[clap(long = "prefix", default_value = "")]
+    pub prefix: String,
+}
+
+impl List {
+    pub async fn run(self) -> Result<()> {
+        let store = self.store.open().await?;
+
+        let mut cursor = None;
+        loop {
+            let page = store
+                .list_keys(&self.prefix, cursor.as_deref(), BATCH_SIZE)
+                .await
+                .context("Failed to list keys")?;
+            for key in page.keys {
+                println!("{key}");
+            }
+            match page.cursor {
+                Some(next) => cursor = Some(next),
+                None => return Ok(()),
+            }
+        }
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct Get {
+    #[clap(flatten)]
+    pub store: StoreOptions,
+
+    /// The key to get.
+    pub key: String,
+}
+
+impl Get {
+    pub async fn run(self) -> Result<()> {
+        let store = self.store.open().await?;
+
+        let value = match store.get(&self.key).await {
+            Ok(value) => value,
+            Err(KeyValueError::NoSuchKey) => bail!("No value for key '{}'", self.key),
+            Err(e) => return Err(e).with_context(|| format!("Failed to get key '{}'", self.key)),
+        };
+
+        // Write the raw bytes so that binary values survive redirection to a file.
+        std::io::stdout().write_all(&value)?;
+        Ok(())
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct Set {
+    #[clap(flatten)]
+    pub store: StoreOptions,
+
+    /// The key to set.
+    pub key: String,
+
+    /// The value to set. To read the value from a file, prefix the filename
+    /// with @ e.g. spin kv set mykey @value.bin
+    pub value: String,
+}
+
+impl Set {
+    pub async fn run(self) -> Result<()> {
+        let value = match self.value.strip_prefix('@') {
+            Some(path) => std::fs::read(path)
+                .with_context(|| format!("Failed to read value from file {path}"))?,
+            None => self.value.into_bytes(),
+        };
+
+        let store = self.store.open().await?;
+        store
+            .set(&self.key, &value)
+            .await
+            .with_context(|| format!("Failed to set key '{}'", self.key))
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct Delete {
+    #[clap(flatten)]
+    pub store: StoreOptions,
+
+    /// The key(s) to delete.
+    #[clap(required = true)]
+    pub keys: Vec<String>,
+}
+
+impl Delete {
+    pub async fn run(self) -> Result<()> {
+        let store = self.store.open().await?;
+        store
+            .delete_many(&self.keys)
+            .await
+            .context("Failed to delete keys")
+    }
+}
+
+/// A key-value tuple as represented in `spin kv export` output.
+///
+/// Values which are valid UTF-8 are written as `value` so that they are easy
+/// to read and edit; all others are written as base64 in `value_base64`.
+#[derive(Debug, Serialize, Deserialize)]
+struct ExportedEntry {
+    key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    value: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    value_base64: Option<String>,
+}
+
+impl ExportedEntry {
+    fn new(key: String, value: Vec<u8>) -> Self {
+        match String::from_utf8(value) {
+            Ok(value) => Self {
+                key,
+                value: Some(value),
+                value_base64: None,
+            },
+            Err(e) => Self {
+                key,
+                value: None,
+                value_base64: Some(BASE64.encode(e.into_bytes())),
+            },
+        }
+    }
+
+    fn into_tuple(self) -> Result<(String, Vec<u8>)> {
+        let value = match (self.value, self.value_base64) {
+            (Some(value), None) => value.into_bytes(),
+            (None, Some(value)) => BASE64
+                .decode(value)
+                .with_context(|| format!("Invalid base64 value for key '{}'", self.key))?,
+            _ => bail!(
+                "Entry for key '{}' must have exactly one of 'value' or 'value_base64'",
+                self.key
+            ),
+        };
+        Ok((self.key, value))
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct Export {
+    #[clap(flatten)]
+    pub store: StoreOptions,
+
+    /// Only export keys starting with this prefix.
+    #[clap(long = "prefix", default_value = "")]
+    pub prefix: String,
+
+    /// The file to write to. If omitted, the export is written to stdout.
+    #[clap(short = 'o', long = "output")]
+    pub output: Option<PathBuf>,
+}
+
+impl Export {
+    pub async fn run(self) -> Result<()> {
+        let store = self.store.open().await?;
+
+        let mut output: Box<dyn Write> = match &self.output {
+            Some(path) => Box::new(
+                std::fs::File::create(path)
+                    .with_context(|| format!("Failed to create {}", path.display()))?,
+            ),
+            None => Box::new(std::io::stdout().lock()),
+        };
+
+        let mut cursor = None;
+        loop {
+            let page = store
+                .list_keys(&self.prefix, cursor.as_deref(), BATCH_SIZE)
+                .await
+                .context("Failed to list keys")?;
+            let values = store
+                .get_many(&page.keys)
+                .await
+                .context("Failed to get values")?;
+
+            // Keys deleted since being listed come back as `None` and are skipped.
+            for (key, value) in page.keys.into_iter().zip(values) {
+                if let Some(value) = value {
+                    serde_json::to_writer(&mut output, &ExportedEntry::new(key, value))?;
+                    writeln!(output)?;
+                }
+            }
+
+            match page.cursor {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+
+        output.flush()?;
+        Ok(())
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct Import {
+    #[clap(flatten)]
+    pub store: StoreOptions,
+
+    /// The file to read from. If omitted, entries are read from stdin.
+    #[clap(short = 'i', long = "input")]
+    pub input: Option<PathBuf>,
+}
+
+impl Import {
+    pub async fn run(self) -> Result<()> {
+        let input: Box<dyn BufRead> = match &self.input {
+            Some(path) => Box::new(BufReader::new(
+                std::fs::File::open(path)
+                    .with_context(|| format!("Failed to open {}", path.display()))?,
+            )),
+            None => Box::new(std::io::stdin().lock()),
+        };
+
+        let store = self.store.open().await?;
+
+        let mut batch = Vec::new();
+        let mut count = 0;
+        for (index, line) in input.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry: ExportedEntry = serde_json::from_str(&line)
+                .with_context(|| format!("Invalid entry on line {}", index + 1))?;
+            batch.push(entry.into_tuple()?);
+
+            if batch.len() == BATCH_SIZE as usize {
+                count += batch.len();
+                store
+                    .set_many(&std::mem::take(&mut batch))
+                    .await
+                    .context("Failed to import entries")?;
+            }
+        }
+
+        count += batch.len();
+        store
+            .set_many(&batch)
+            .await
+            .context("Failed to import entries")?;
+
+        println!("Imported {count} entries into store '{}'", self.store.store);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn exported_entries_round_trip() -> Result<()> {
+        let text = ExportedEntry::new("text".to_owned(), b"hello".to_vec());
+        assert_eq!(
+            r#"{"key":"text","value":"hello"}"#,
+            serde_json::to_string(&text)?
+        );
+        assert_eq!(("text".to_owned(), b"hello".to_vec()), text.into_tuple()?);
+
+        let binary = ExportedEntry::new("binary".to_owned(), vec![0xff, 0x00]);
+        assert_eq!(
+            r#"{"key":"binary","value_base64":"/wA="}"#,
+            serde_json::to_string(&binary)?
+        );
+        assert_eq!(("binary".to_owned(), vec![0xff, 0x00]), binary.into_tuple()?);
+
+        let invalid: ExportedEntry = serde_json::from_str(r#"{"key":"none"}"#)?;
+        assert!(invalid.into_tuple().is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,464 @@
+use std::{
+    collections::HashMap,
+    io::{BufRead, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use comfy_table::Table;
+use is_terminal::IsTerminal;
+use spin_key_value_sqlite::KeyValueSqlite;
+use spin_sqlite::{migrations, Connection};
+use spin_world::sqlite::{QueryResult, Value};
+
+use spin_trigger::RuntimeConfig;
+
+use crate::commands::kv::AppRuntimeConfigOptions;
+
+const KEY_VALUE_STORE_OPT: &str = "KEY_VALUE_STORE";
+
+/// Commands for working with an application's SQLite databases.
+#[derive(Subcommand, Debug)]
+pub enum SqliteCommands {
+    /// Write a consistent snapshot of a database to a file while the
+    /// application is running.
+    Backup(Backup),
+    /// Apply pending migrations to the application's databases.
+    Migrate(Migrate),
+    /// Execute a SQL statement against a database and print the results.
+    Query(Query),
+    /// Replace the contents of a database with a backup.
+    Restore(Restore),
+    /// Start an interactive SQL shell for a database.
+    Shell(Shell),
+}
+
+impl SqliteCommands {
+    pub async fn run(self) -> Result<()> {
+        match self {
+            SqliteCommands::Backup(cmd) => cmd.run().await,
+            SqliteCommands::Migrate(cmd) => cmd.run().await,
+            SqliteCommands::Query(cmd) => cmd.run().await,
+            SqliteCommands::Restore(cmd) => cmd.run().await,
+            SqliteCommands::Shell(cmd) => cmd.run().await,
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct DatabaseOptions {
+    #[clap(flatten)]
+    pub app: AppRuntimeConfigOptions,
+
+    /// The name of the database to use.
+    #[clap(short = 'd', long = "database", default_value = "default")]
+    pub database: String,
+}
+
+impl DatabaseOptions {
+    fn open(&self) -> Result<Arc<dyn Connection>> {
+        open_database(&self.app.runtime_config()?, &self.database)
+    }
+}
+
+fn open_database(runtime_config: &RuntimeConfig, name: &str) -> Result<Arc<dyn Connection>> {
+    let mut databases: HashMap<_, _> = runtime_config
+        .sqlite_databases()
+        .context("Failed to build SQLite databases")?
+        .into_iter()
+        .collect();
+
+    match databases.remove(name) {
+        Some(database) => Ok(database),
+        None => bail!(
+            "No SQLite database named '{name}' is defined. Pass a runtime configuration file which defines it."
+        ),
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct BackupTargetOptions {
+    #[clap(flatten)]
+    pub app: AppRuntimeConfigOptions,
+
+    /// The SQLite database to use. If neither this nor a key-value store is
+    /// given, the "default" database is used.
+    #[clap(short = 'd', long = "database", conflicts_with = KEY_VALUE_STORE_OPT)]
+    pub database: Option<String>,
+
+    /// The key-value store to use. This must be a `spin` store.
+    #[clap(name = KEY_VALUE_STORE_OPT, long = "key-value-store")]
+    pub key_value_store: Option<String>,
+}
+
+/// A database that can be backed up and restored.
+enum BackupTarget {
+    Database(String, Arc<dyn Connection>),
+    KeyValueStore(String, KeyValueSqlite),
+}
+
+impl BackupTargetOptions {
+    fn open(&self) -> Result<BackupTarget> {
+        let runtime_config = self.app.runtime_config()?;
+        if let Some(store) = &self.key_value_store {
+            let Some(kv) = runtime_config.spin_key_value_store(store)? else {
+                bail!(
+                    "No Spin key-value store named '{store}' is defined. Only `spin` key-value stores can be backed up or restored."
+                );
+            };
+            return Ok(BackupTarget::KeyValueStore(store.clone(), kv));
+        }
+        let name = self.database.as_deref().unwrap_or("default");
+        let database = open_database(&runtime_config, name)?;
+        Ok(BackupTarget::Database(name.to_owned(), database))
+    }
+}
+
+impl BackupTarget {
+    async fn backup(&self, destination: &Path) -> Result<()> {
+        match self {
+            Self::Database(_, database) => database.backup(destination).await,
+            Self::KeyValueStore(_, kv) => kv.backup(destination).await,
+        }
+    }
+
+    async fn restore(&self, source: &Path) -> Result<()> {
+        match self {
+            Self::Database(_, database) => database.restore(source).await,
+            Self::KeyValueStore(_, kv) => kv.restore(source).await,
+        }
+    }
+}
+
+impl std::fmt::Display for BackupTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Database(name, _) => write!(f, "SQLite database '{name}'"),
+            Self::KeyValueStore(name, _) => write!(f, "key-value store '{name}'"),
+        }
+    }
+}
+
+/// The format in which to print query results.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum OutputFormat {
+    Table,
+    Json,
+}
+
+#[derive(Parser, Debug)]
+pub struct Backup {
+    #[clap(flatten)]
+    pub target: BackupTargetOptions,
+
+    /// The file to write the backup to. If it exists, it is replaced.
+    pub output: PathBuf,
+}
+
+impl Backup {
+    pub async fn run(self) -> Result<()> {
+        let target = self.target.open()?;
+        target
+            .backup(&self.output)
+            .await
+            .with_context(|| format!("Failed to back up {target}"))?;
+        println!("Backed up {target} to {}", self.output.display());
+        Ok(())
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct Restore {
+    #[clap(flatten)]
+    pub target: BackupTargetOptions,
+
+    /// The backup file to restore from.
+    pub input: PathBuf,
+}
+
+impl Restore {
+    pub async fn run(self) -> Result<()> {
+        let target = self.target.open()?;
+        target
+            .restore(&self.input)
+            .await
+            .with_context(|| format!("Failed to restore {target}"))?;
+        println!("Restored {target} from {}", self.input.display());
+        Ok(())
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct Migrate {
+    #[clap(flatten)]
+    pub app: AppRuntimeConfigOptions,
+
+    /// The database to migrate. If omitted, all databases with a migrations
+    /// directory declared in the manifest are migrated.
+    #[clap(short = 'd', long = "database")]
+    pub database: Option<String>,
+
+    /// Show the applied and pending migrations without applying any.
+    #[clap(long = "status")]
+    pub status: bool,
+}
+
+impl Migrate {
+    pub async fn run(self) -> Result<()> {
+        let manifest_file = spin_common::paths::resolve_manifest_file_path(&self.app.app_source)?;
+        let mut migration_dirs = spin_loader::local::sqlite_migrations(&manifest_file)
+            .await
+            .with_context(|| format!("Failed to read SQLite migrations from {manifest_file:?}"))?;
+
+        if let Some(database) = &self.database {
+            let Some(dir) = migration_dirs.remove(database) else {
+                bail!(
+                    "No migrations directory is declared for SQLite database '{database}'. Set `migrations` on its `sqlite_databases` entry in the manifest."
+                );
+            };
+            migration_dirs = HashMap::from([(database.clone(), dir)]);
+        }
+
+        if migration_dirs.is_empty() {
+            println!("No SQLite databases have a migrations directory declared in the manifest.");
+            return Ok(());
+        }
+
+        let runtime_config = self.app.runtime_config()?;
+
+        let databases: HashMap<_, _> = runtime_config
+            .sqlite_databases()
+            .context("Failed to build SQLite databases")?
+            .into_iter()
+            .collect();
+
+        let mut names: Vec<_> = migration_dirs.keys().cloned().collect();
+        names.sort();
+        for name in names {
+            let dir = &migration_dirs[&name];
+            let database = databases
+                .get(&name)
+                .with_context(|| format!("No SQLite database named '{name}' is defined"))?;
+
+            if self.status {
+                let status = migrations::status(database.as_ref(), dir)
+                    .await
+                    .with_context(|| format!("Failed to get migration status for '{name}'"))?;
+                println!("Database '{name}' ({}):", dir.display());
+                for migration in &status.applied {
+                    println!("  applied  {}  ({})", migration.name, migration.applied_at);
+                }
+                for migration in &status.pending {
+                    println!("  pending  {}", migration.name);
+                }
+            } else {
+                let applied = migrations::migrate(database.as_ref(), dir)
+                    .await
+                    .with_context(|| format!("Failed to migrate SQLite database '{name}'"))?;
+                if applied.is_empty() {
+                    println!("Database '{name}' is up to date");
+                }
+                for migration in applied {
+                    println!("Applied {} to database '{name}'", migration.name);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct Query {
+    #[clap(flatten)]
+    pub database: DatabaseOptions,
+
+    /// The format in which to print the results.
+    #[clap(value_enum, long = "format", default_value = "table")]
+    pub format: OutputFormat,
+
+    /// The SQL statement to execute.
+    pub sql: String,
+}
+
+impl Query {
+    pub async fn run(self) -> Result<()> {
+        let database = self.database.open()?;
+        let result = database
+            .query(&self.sql, vec![])
+            .await
+            .context("Failed to execute query")?;
+        print_result(&result, self.format)
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct Shell {
+    #[clap(flatten)]
+    pub database: DatabaseOptions,
+
+    /// The format in which to print the results.
+    #[clap(value_enum, long = "format", default_value = "table")]
+    pub format: OutputFormat,
+}
+
+const SHELL_HELP: &str = "\
+Enter SQL statements terminated by a semicolon. Statements may span several lines.
+.tables   List the tables in the database
+.help     Show this help
+.quit     Exit the shell";
+
+impl Shell {
+    pub async fn run(self) -> Result<()> {
+        let database = self.database.open()?;
+        let interactive = std::io::stdin().is_terminal();
+        if interactive {
+            println!(
+                "Connected to SQLite database '{}'. Enter .help for usage hints.",
+                self.database.database
+            );
+        }
+
+        let mut lines = std::io::stdin().lock().lines();
+        let mut statement = String::new();
+        loop {
+            if interactive {
+                print!(
+                    "{}",
+                    if statement.is_empty() {
+                        "sqlite> "
+                    } else {
+                        "   ...> "
+                    }
+                );
+                std::io::stdout().flush()?;
+            }
+            let Some(line) = lines.next().transpose()? else {
+                break;
+            };
+
+            if statement.is_empty() {
+                match line.trim() {
+                    "" => continue,
+                    ".quit" | ".exit" => break,
+                    ".help" => {
+                        println!("{SHELL_HELP}");
+                        continue;
+                    }
+                    ".tables" => {
+                        statement =
+                            "SELECT name FROM sqlite_master WHERE type = 'table' ORDER BY name;"
+                                .to_owned();
+                    }
+                    command if command.starts_with('.') => {
+                        eprintln!("Unknown command '{command}'. Enter .help for usage hints.");
+                        continue;
+                    }
+                    _ => statement.push_str(&line),
+                }
+            } else {
+                statement.push('\n');
+                statement.push_str(&line);
+            }
+
+            if !statement.trim_end().ends_with(';') {
+                continue;
+            }
+            // Errors are reported without leaving the shell so that a typo
+            // doesn't lose the session.
+            match database
+                .query(&std::mem::take(&mut statement), vec![])
+                .await
+            {
+                Ok(result) => print_result(&result, self.format)?,
+                Err(e) => eprintln!("Error: {e}"),
+            }
+        }
+
+        if !statement.trim().is_empty() {
+            eprintln!("Ignoring incomplete statement: missing ';'");
+        }
+        Ok(())
+    }
+}
+
+fn print_result(result: &QueryResult, format: OutputFormat) -> Result<()> {
+    match format {
+        OutputFormat::Table => {
+            if !result.columns.is_empty() {
+                println!("{}", result_table(result));
+            }
+        }
+        OutputFormat::Json => {
+            println!("{}", serde_json::to_string_pretty(&result_json(result))?);
+        }
+    }
+    Ok(())
+}
+
+fn result_table(result: &QueryResult) -> Table {
+    let mut table = Table::new();
+    table.set_header(&result.columns);
+    table.load_preset(comfy_table::presets::ASCII_BORDERS_ONLY_CONDENSED);
+    for row in &result.rows {
+        table.add_row(row.values.iter().map(|value| match value {
+            Value::Integer(i) => i.to_string(),
+            Value::Real(r) => r.to_string(),
+            Value::Text(t) => t.clone(),
+            Value::Blob(b) => format!("<{} bytes>", b.len()),
+            Value::Null => "NULL".to_owned(),
+        }));
+    }
+    table
+}
+
+/// Results are written as `{"columns": [...], "rows": [[...], ...]}` rather
+/// than as objects so that column order and duplicate column names survive.
+/// Blobs are written as base64 strings.
+fn result_json(result: &QueryResult) -> serde_json::Value {
+    let rows = result
+        .rows
+        .iter()
+        .map(|row| {
+            row.values
+                .iter()
+                .map(|value| match value {
+                    Value::Integer(i) => serde_json::json!(i),
+                    Value::Real(r) => serde_json::json!(r),
+                    Value::Text(t) => serde_json::json!(t),
+                    Value::Blob(b) => serde_json::json!(BASE64.encode(b)),
+                    Value::Null => serde_json::Value::Null,
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    serde_json::json!({ "columns": result.columns, "rows": rows })
+}
+
+#[cfg(test)]
+mod test {
+    use spin_world::sqlite::RowResult;
+
+    use super::*;
+
+    #[test]
+    fn results_render_as_json() {
+        let result = QueryResult {
+            columns: vec!["id".to_owned(), "id".to_owned(), "data".to_owned()],
+            rows: vec![RowResult {
+                values: vec![
+                    Value::Integer(1),
+                    Value::Text("one".to_owned()),
+                    Value::Blob(vec![0xff, 0x00]),
+                ],
+            }],
+        };
+        assert_eq!(
+            r#"{"columns":["id","id","data"],"rows":[[1,"one","/wA="]]}"#,
+            result_json(&result).to_string()
+        );
+    }
+}